@@ -59,3 +59,22 @@ def test_event_File():
 
     with pytest.raises(Exception):
         sf.events()
+
+
+def test_event_reader_follow(tmp_path):
+    """Test EventReader in follow mode reads events written after its
+    creation"""
+    with open("test_data/test_events.json") as f:
+        lines = f.readlines()
+
+    path = tmp_path / "retis.data"
+    with open(path, "w") as f:
+        f.write(lines[0])
+        f.flush()
+
+        r = EventReader(str(path), follow=True)
+        verify_event(next(r))
+
+        f.write(lines[1])
+        f.flush()
+        verify_event(next(r))
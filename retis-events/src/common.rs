@@ -20,6 +20,28 @@ impl EventFmt for StartupEvent {
     }
 }
 
+/// Origin event section. Identifies the collection an event comes from when
+/// events from multiple collections (e.g. from different hosts) are merged.
+#[event_section(SectionId::Origin)]
+pub struct OriginEvent {
+    /// Name of the host the event was collected on.
+    pub host: String,
+    /// Hash of the packet payload, only set when the same payload was seen on
+    /// multiple hosts. Events sharing the same hash are likely related to the
+    /// same packet.
+    pub payload_hash: Option<u64>,
+}
+
+impl EventFmt for OriginEvent {
+    fn event_fmt(&self, f: &mut Formatter, _: &DisplayFormat) -> fmt::Result {
+        write!(f, "origin {}", self.host)?;
+        if let Some(hash) = self.payload_hash {
+            write!(f, " payload {hash:#018x}")?;
+        }
+        Ok(())
+    }
+}
+
 /// Information about a given task.
 #[event_type]
 #[derive(Default)]
@@ -44,19 +66,26 @@ pub struct CommonEvent {
     pub task: Option<TaskEvent>,
 }
 
-impl EventFmt for CommonEvent {
-    fn event_fmt(&self, f: &mut Formatter, format: &DisplayFormat) -> fmt::Result {
+impl CommonEvent {
+    /// Writes the event time, following the time format.
+    pub(crate) fn time_fmt<W: fmt::Write>(&self, w: &mut W, format: &DisplayFormat) -> fmt::Result {
         match format.time_format {
-            TimeFormat::MonotonicTimestamp => write!(f, "{}", self.timestamp)?,
+            TimeFormat::MonotonicTimestamp => write!(w, "{}", self.timestamp),
             TimeFormat::UtcDate => match format.monotonic_offset {
                 Some(offset) => {
                     let timestamp = TimeSpec::new(0, self.timestamp as i64) + offset;
                     let time: DateTime<Utc> = timestamp.into();
-                    write!(f, "{}", time.format("%F %T.%6f"))?;
+                    write!(w, "{}", time.format("%F %T.%6f"))
                 }
-                None => write!(f, "{}", self.timestamp)?,
+                None => write!(w, "{}", self.timestamp),
             },
         }
+    }
+}
+
+impl EventFmt for CommonEvent {
+    fn event_fmt(&self, f: &mut Formatter, format: &DisplayFormat) -> fmt::Result {
+        self.time_fmt(f, format)?;
 
         if let Some(smp_id) = self.smp_id {
             write!(f, " ({})", smp_id)?;
@@ -1,8 +1,10 @@
 use std::{
     fmt::{self, Write},
-    result, str,
+    result,
+    str::{self, FromStr},
 };
 
+use anyhow::{bail, Result};
 use log::warn;
 
 use super::TimeSpec;
@@ -15,8 +17,102 @@ pub enum TimeFormat {
     UtcDate,
 }
 
+/// Column of the table format.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum TableColumn {
+    /// Time of the event.
+    Time,
+    /// CPU the event was generated on.
+    Cpu,
+    /// Name of the task the event was generated in.
+    Comm,
+    /// Probe which generated the event.
+    Probe,
+    /// Tracking id of the packet.
+    Tracking,
+    /// Net device of the packet.
+    Dev,
+    /// Source address (and port) of the packet.
+    Src,
+    /// Destination address (and port) of the packet.
+    Dst,
+    /// Protocol of the packet.
+    Proto,
+    /// Drop reason, if the packet was dropped.
+    Drop,
+}
+
+impl TableColumn {
+    /// Default columns of the table format.
+    pub const DEFAULT: &'static str = "time,cpu,comm,probe,tracking,dev,src,dst,proto,drop";
+
+    /// Name of the column, used in the table header.
+    pub fn name(&self) -> &'static str {
+        use TableColumn::*;
+        match self {
+            Time => "TIME",
+            Cpu => "CPU",
+            Comm => "COMM",
+            Probe => "PROBE",
+            Tracking => "TRACKING",
+            Dev => "DEV",
+            Src => "SRC",
+            Dst => "DST",
+            Proto => "PROTO",
+            Drop => "DROP",
+        }
+    }
+
+    /// Width of the column, in characters. Longer values are truncated.
+    pub fn width(&self, format: &DisplayFormat) -> usize {
+        use TableColumn::*;
+        match self {
+            Time => match format.time_format {
+                TimeFormat::MonotonicTimestamp => 16,
+                TimeFormat::UtcDate => 26,
+            },
+            Cpu => 3,
+            // TASK_COMM_LEN - 1.
+            Comm => 15,
+            Probe => 36,
+            // Timestamp and skb head address, in hex.
+            Tracking => 29,
+            // IFNAMSIZ - 1.
+            Dev => 15,
+            // Room for an IPv4 address and a port.
+            Src | Dst => 21,
+            Proto => 6,
+            Drop => 24,
+        }
+    }
+}
+
+impl FromStr for TableColumn {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        use TableColumn::*;
+        Ok(match s {
+            "time" => Time,
+            "cpu" => Cpu,
+            "comm" => Comm,
+            "probe" => Probe,
+            "tracking" => Tracking,
+            "dev" => Dev,
+            "src" => Src,
+            "dst" => Dst,
+            "proto" => Proto,
+            "drop" => Drop,
+            x => bail!(
+                "Unknown table column '{x}' (expected one of {})",
+                Self::DEFAULT
+            ),
+        })
+    }
+}
+
 /// Controls how an event is formatted.
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone)]
 pub struct DisplayFormat {
     /// Can the formatting logic use more than a single line?
     pub multiline: bool,
@@ -24,6 +120,9 @@ pub struct DisplayFormat {
     pub time_format: TimeFormat,
     /// Offset of the monotonic clock to the wall-clock time.
     pub monotonic_offset: Option<TimeSpec>,
+    /// Columns of the table format. If not empty, events are displayed as a
+    /// single table row.
+    pub columns: Vec<TableColumn>,
 }
 
 impl DisplayFormat {
@@ -48,6 +147,57 @@ impl DisplayFormat {
         self.monotonic_offset = Some(offset);
         self
     }
+
+    /// Configure the table format, using the given columns. An empty list
+    /// disables it.
+    pub fn table(mut self, columns: Vec<TableColumn>) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    /// Is the table format used?
+    pub fn is_table(&self) -> bool {
+        !self.columns.is_empty()
+    }
+
+    /// Returns the header of the table format, or None if not used.
+    pub fn table_header(&self) -> Option<String> {
+        if !self.is_table() {
+            return None;
+        }
+
+        let mut header = String::new();
+        table_row(
+            &mut header,
+            self.columns
+                .iter()
+                .map(|c| (c.name().to_string(), c.width(self))),
+        )
+        .ok()?;
+        Some(header)
+    }
+}
+
+/// Writes a table row made of (value, width) cells. Values are padded or
+/// truncated to their column width, except for the last one.
+pub(crate) fn table_row<W, I>(w: &mut W, cells: I) -> fmt::Result
+where
+    W: fmt::Write,
+    I: Iterator<Item = (String, usize)>,
+{
+    let mut row = String::new();
+    let mut cells = cells.peekable();
+    while let Some((value, width)) = cells.next() {
+        if cells.peek().is_none() {
+            row.push_str(&value);
+        } else if value.chars().count() > width {
+            row.extend(value.chars().take(width - 1));
+            row.push_str("… ");
+        } else {
+            row.push_str(&format!("{value:width$} "));
+        }
+    }
+    w.write_str(row.trim_end())
 }
 
 /// `Formatter` implements `std::fmt::Write` and controls how events are being
@@ -323,3 +473,29 @@ impl DelimWriter {
         !self.first
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_format() {
+        let mut row = String::new();
+        table_row(
+            &mut row,
+            [("a", 3), ("abcdef", 4), ("", 2), ("last value", 2)]
+                .into_iter()
+                .map(|(v, w)| (v.to_string(), w)),
+        )
+        .unwrap();
+        assert_eq!(row, "a   abc…    last value");
+
+        let format = DisplayFormat::new().table(vec![TableColumn::Cpu, TableColumn::Drop]);
+        assert!(format.is_table());
+        assert_eq!(format.table_header().unwrap(), "CPU DROP");
+        assert!(DisplayFormat::new().table_header().is_none());
+
+        assert_eq!(TableColumn::from_str("src").unwrap(), TableColumn::Src);
+        assert!(TableColumn::from_str("foo").is_err());
+    }
+}
@@ -68,6 +68,13 @@ impl Event {
             })?;
             event.insert_section(SectionId::from_u8(section.id())?, section)?;
         }
+
+        // Application layer data is decoded from the raw packet at
+        // post-processing time.
+        if let Some(skb) = event.get_section_mut::<SkbEvent>(SectionId::Skb) {
+            skb.decode_app();
+        }
+
         Ok(event)
     }
 
@@ -131,10 +138,142 @@ impl Event {
     pub fn sections(&self) -> impl Iterator<Item = SectionId> + '_ {
         self.0.keys().map(|s| s.to_owned())
     }
+
+    /// Returns the name of the probe the event was generated from (e.g.
+    /// `raw_tracepoint:net:netif_receive_skb`), if any.
+    pub fn probe_name(&self) -> Option<String> {
+        if let Some(kernel) = self.get_section::<KernelEvent>(SectionId::Kernel) {
+            Some(format!("{}:{}", kernel.probe_type, kernel.symbol))
+        } else {
+            self.get_section::<UserEvent>(SectionId::Userspace)
+                .map(|user| format!("{}:{}", user.probe_type, user.symbol))
+        }
+    }
+
+    /// Returns the tracking id of the packet the event is about, if any. The
+    /// post-processing tracking information (e.g. from sorted files) is
+    /// preferred as it also tracks packets in OvS upcalls.
+    pub fn tracking_id(&self) -> Option<u128> {
+        match self.get_section::<TrackingInfo>(SectionId::Tracking) {
+            Some(tracking) => Some(tracking.skb.tracking_id()),
+            None => self
+                .get_section::<SkbTrackingEvent>(SectionId::SkbTracking)
+                .map(|t| t.tracking_id()),
+        }
+    }
+
+    /// Returns the drop reason of the event (e.g. `NO_SOCKET` or
+    /// `openvswitch/OVS_DROP_LAST_ACTION`), if any.
+    pub fn drop_reason(&self) -> Option<String> {
+        self.get_section::<SkbDropEvent>(SectionId::SkbDrop)
+            .map(|drop| match &drop.subsys {
+                Some(subsys) => format!("{subsys}/{}", drop.drop_reason),
+                None => drop.drop_reason.clone(),
+            })
+    }
+}
+
+impl Event {
+    /// Returns the value of a table column for this event, or an empty string
+    /// if not available.
+    fn table_cell(&self, column: TableColumn, format: &DisplayFormat) -> String {
+        use TableColumn::*;
+
+        let common = self.get_section::<CommonEvent>(SectionId::Common);
+        let skb = self.get_section::<SkbEvent>(SectionId::Skb);
+        // Address (and port) of the packet, in the `addr.port` form.
+        let addr = |src: bool| -> Option<String> {
+            let skb = skb?;
+            let Some(ip) = &skb.ip else {
+                let eth = skb.eth.as_ref()?;
+                return Some(if src {
+                    eth.src.clone()
+                } else {
+                    eth.dst.clone()
+                });
+            };
+            let ports = skb.ports();
+            let (addr, port) = match src {
+                true => (&ip.saddr, ports.map(|p| p.0)),
+                false => (&ip.daddr, ports.map(|p| p.1)),
+            };
+            Some(match port {
+                Some(port) => format!("{addr}.{port}"),
+                None => addr.clone(),
+            })
+        };
+
+        let cell = match column {
+            Time => common.map(|common| {
+                let mut time = String::new();
+                let _ = common.time_fmt(&mut time, format);
+                time
+            }),
+            Cpu => common.and_then(|c| c.smp_id).map(|cpu| cpu.to_string()),
+            Comm => common
+                .and_then(|c| c.task.as_ref())
+                .map(|task| task.comm.clone()),
+            Probe => self.probe_name().and_then(|probe| {
+                let (kind, symbol) = probe.split_once(':')?;
+                let kind = match kind {
+                    _ if !self.0.contains_key(&SectionId::Kernel) => "u",
+                    "raw_tracepoint" => "tp",
+                    "kprobe" => "k",
+                    "kretprobe" => "kr",
+                    _ => "invalid",
+                };
+                Some(format!("{kind}:{symbol}"))
+            }),
+            Tracking => self.tracking_id().map(|id| format!("#{id:x}")),
+            Dev => skb
+                .and_then(|s| s.dev.as_ref())
+                .map(|dev| match dev.name.is_empty() {
+                    true => dev.ifindex.to_string(),
+                    false => dev.name.clone(),
+                }),
+            Src => addr(true),
+            Dst => addr(false),
+            Proto => skb.and_then(|skb| match (&skb.ip, &skb.eth) {
+                (Some(ip), _) => Some(match helpers::protocol_str(ip.protocol) {
+                    Some(proto) => proto.to_string(),
+                    None => ip.protocol.to_string(),
+                }),
+                (None, Some(eth)) => Some(match helpers::etype_str(eth.etype) {
+                    Some(etype) => etype.to_string(),
+                    None => format!("{:#06x}", eth.etype),
+                }),
+                _ => None,
+            }),
+            Drop => self.drop_reason(),
+        };
+
+        cell.unwrap_or_default()
+    }
+
+    /// Formats the event as a single table row, following the columns of the
+    /// table format.
+    fn table_fmt(&self, f: &mut Formatter, format: &DisplayFormat) -> std::fmt::Result {
+        // Startup events are not related to the collection itself.
+        if self.0.contains_key(&SectionId::Startup) {
+            return Ok(());
+        }
+
+        table_row(
+            f,
+            format
+                .columns
+                .iter()
+                .map(|c| (self.table_cell(*c, format), c.width(format))),
+        )
+    }
 }
 
 impl EventFmt for Event {
     fn event_fmt(&self, f: &mut Formatter, format: &DisplayFormat) -> std::fmt::Result {
+        if format.is_table() {
+            return self.table_fmt(f, format);
+        }
+
         // First format the first event line starting with the always-there
         // {common} section, followed by the {kernel} or {user} one.
         self.0
@@ -207,8 +346,9 @@ pub enum SectionId {
     Nft = 9,
     Ct = 10,
     Startup = 11,
+    Origin = 12,
     // TODO: use std::mem::variant_count once in stable.
-    _MAX = 12,
+    _MAX = 13,
 }
 
 impl SectionId {
@@ -227,6 +367,7 @@ impl SectionId {
             9 => Nft,
             10 => Ct,
             11 => Startup,
+            12 => Origin,
             x => bail!("Can't construct a SectionId from {}", x),
         })
     }
@@ -246,6 +387,7 @@ impl SectionId {
             Nft => "nft",
             Ct => "ct",
             Startup => "startup",
+            Origin => "origin",
             _MAX => "_max",
         }
     }
@@ -276,6 +418,7 @@ impl FromStr for SectionId {
             "nft" => Nft,
             "ct" => Ct,
             "startup" => Startup,
+            "origin" => Origin,
             x => bail!("Can't construct a SectionId from {}", x),
         })
     }
@@ -307,6 +450,7 @@ fn event_sections() -> Result<&'static EventSectionMap> {
         insert_section!(events, NftEvent);
         insert_section!(events, CtEvent);
         insert_section!(events, StartupEvent);
+        insert_section!(events, OriginEvent);
         insert_section!(events, TrackingInfo);
 
         Ok(events)
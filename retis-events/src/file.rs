@@ -1,15 +1,20 @@
 //! Handles the file (json) to Rust event retrieval and the unmarshaling process.
 
 use std::{
-    fs::File,
+    fs::{self, File},
     io::{BufRead, BufReader, Seek},
-    path::Path,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    time::Duration,
 };
 
 use anyhow::{anyhow, bail, Result};
 
 use super::{Event, EventSeries};
 
+/// Interval at which files are polled for new data in follow mode.
+pub const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
 // Type of file that is being processed.
 #[derive(Debug, Clone)]
 pub enum FileType {
@@ -22,8 +27,13 @@ pub enum FileType {
 /// File events factory retrieving and unmarshaling events
 /// parts.
 pub struct FileEventsFactory {
+    path: PathBuf,
     reader: BufReader<File>,
     filetype: FileType,
+    /// Follow mode: keep reading the file as it is being written.
+    follow: bool,
+    /// Partially written line, only used in follow mode.
+    partial: Vec<u8>,
 }
 
 impl FileEventsFactory {
@@ -37,7 +47,39 @@ impl FileEventsFactory {
         );
         let filetype = Self::detect_type(&mut reader)?;
 
-        Ok(FileEventsFactory { reader, filetype })
+        Ok(FileEventsFactory {
+            path: file.as_ref().to_path_buf(),
+            reader,
+            filetype,
+            follow: false,
+            partial: Vec::new(),
+        })
+    }
+
+    /// Enable or disable the follow mode. In follow mode reaching the end of
+    /// the file is not considered final: `next_event()` and `next_series()`
+    /// return `None` until new data is written to the file, which can be
+    /// polled by calling them again. Partially written lines are kept until
+    /// complete and the file is re-opened if rotated or truncated.
+    pub fn follow(mut self, follow: bool) -> Self {
+        self.follow = follow;
+        self
+    }
+
+    /// Checks if a file is ready to be read by a `FileEventsFactory`, that is
+    /// if it exists and contains at least one complete line. This is useful
+    /// to wait for a file being written to in follow mode.
+    pub fn ready<P>(file: P) -> bool
+    where
+        P: AsRef<Path>,
+    {
+        let mut line = Vec::new();
+        match File::open(file) {
+            Ok(file) => {
+                BufReader::new(file).read_until(b'\n', &mut line).is_ok() && line.ends_with(b"\n")
+            }
+            Err(_) => false,
+        }
     }
 }
 
@@ -49,13 +91,11 @@ impl FileEventsFactory {
             FileType::Event => (),
             FileType::Series => bail!("Cannot read event from sorted file"),
         }
-        let mut line = String::new();
 
-        match self.reader.read_line(&mut line) {
-            Err(e) => Err(e.into()),
-            Ok(0) => Ok(None),
-            Ok(_) => Ok(Some(Event::from_json(line)?)),
-        }
+        Ok(match self.next_line()? {
+            Some(line) => Some(Event::from_json(line)?),
+            None => None,
+        })
     }
 
     /// Retrieve the next series or None if we've reached the end of the file.
@@ -65,13 +105,60 @@ impl FileEventsFactory {
             FileType::Event => bail!("Cannot read series from unsorted file"),
             FileType::Series => (),
         }
-        let mut line = String::new();
 
-        match self.reader.read_line(&mut line) {
-            Err(e) => Err(e.into()),
-            Ok(0) => Ok(None),
-            Ok(_) => Ok(Some(EventSeries::from_json(line)?)),
+        Ok(match self.next_line()? {
+            Some(line) => Some(EventSeries::from_json(line)?),
+            None => None,
+        })
+    }
+
+    /// Retrieve the next complete line or None if there is none (yet).
+    fn next_line(&mut self) -> Result<Option<String>> {
+        if !self.follow {
+            let mut line = String::new();
+            return match self.reader.read_line(&mut line)? {
+                0 => Ok(None),
+                _ => Ok(Some(line)),
+            };
+        }
+
+        // In follow mode the last line might be partially written. Keep it
+        // until it is complete.
+        if self.reader.read_until(b'\n', &mut self.partial)? != 0 && self.partial.ends_with(b"\n") {
+            let line = std::mem::take(&mut self.partial);
+            return Ok(Some(String::from_utf8(line)?));
+        }
+
+        // Nothing complete to read. Check if the file was rotated or truncated
+        // while we were waiting.
+        self.check_rotation()?;
+        Ok(None)
+    }
+
+    /// Re-open or rewind the file if it was rotated or truncated, respectively.
+    fn check_rotation(&mut self) -> Result<()> {
+        let current = self.reader.get_ref().metadata()?;
+        let on_disk = match fs::metadata(&self.path) {
+            Ok(meta) => meta,
+            // The file might not be there for a short period of time while
+            // being rotated. Try again later.
+            Err(_) => return Ok(()),
+        };
+
+        if current.dev() != on_disk.dev() || current.ino() != on_disk.ino() {
+            log::info!("{} was rotated, re-opening it", self.path.display());
+            self.reader = BufReader::new(File::open(&self.path)?);
+            self.partial.clear();
+        } else if on_disk.len() < self.reader.stream_position()? {
+            log::info!(
+                "{} was truncated, reading from the start",
+                self.path.display()
+            );
+            self.reader.rewind()?;
+            self.partial.clear();
         }
+
+        Ok(())
     }
 
     fn detect_type<T>(reader: &mut T) -> Result<FileType>
@@ -116,4 +203,45 @@ mod tests {
         }
         assert!(events.len() == 4);
     }
+
+    #[test]
+    fn follow_file() {
+        use std::{fs::OpenOptions, io::Write};
+
+        let path = std::env::temp_dir().join(format!("retis-follow-{}.json", std::process::id()));
+        let events = fs::read_to_string("test_data/test_events.json").unwrap();
+        let mut lines = events.lines();
+
+        let mut file = File::create(&path).unwrap();
+        assert!(!FileEventsFactory::ready(&path));
+        writeln!(file, "{}", lines.next().unwrap()).unwrap();
+        assert!(FileEventsFactory::ready(&path));
+
+        let mut fact = FileEventsFactory::new(&path).unwrap().follow(true);
+        assert!(fact.next_event().unwrap().is_some());
+        assert!(fact.next_event().unwrap().is_none());
+
+        // Partially written lines are only returned once complete.
+        let line = lines.next().unwrap();
+        let (start, end) = line.split_at(line.len() / 2);
+        write!(file, "{start}").unwrap();
+        assert!(fact.next_event().unwrap().is_none());
+        writeln!(file, "{end}").unwrap();
+        assert!(fact.next_event().unwrap().is_some());
+        assert!(fact.next_event().unwrap().is_none());
+
+        // Rotated files are re-opened.
+        fs::remove_file(&path).unwrap();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        writeln!(file, "{}", lines.next().unwrap()).unwrap();
+        assert!(fact.next_event().unwrap().is_none());
+        assert!(fact.next_event().unwrap().is_some());
+
+        fs::remove_file(&path).unwrap();
+    }
 }
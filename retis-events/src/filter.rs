@@ -0,0 +1,447 @@
+//! # Filter
+//!
+//! Expressions matching events based on the content of their sections, as seen
+//! in their JSON representation, e.g.
+//! `skb.tcp.dport == 443 && kernel.symbol ~ "ip_*"`.
+//!
+//! Fields are referenced using their path, starting with the section name
+//! (e.g. `skb-drop.drop_reason`). Supported operators are, by decreasing
+//! precedence:
+//! - Comparisons: `==`, `!=`, `<`, `<=`, `>`, `>=` and `~` (glob matching on
+//!   strings, supporting `*` and `?`). A field used without an operator matches
+//!   if it exists and is not `false`.
+//! - `!` (or `not`).
+//! - `&&` (or `and`).
+//! - `||` (or `or`).
+//!
+//! Values can be integers (decimal or hexadecimal), floats, strings (using
+//! single or double quotes) or booleans. Comparisons on a missing field or
+//! between incompatible types never match. Comparisons on arrays match if any
+//! of their elements match.
+
+use std::{cmp::Ordering, str::FromStr};
+
+use anyhow::{anyhow, bail, Result};
+
+use crate::{Event, SectionId};
+
+/// Comparison operators.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Glob,
+}
+
+/// Values fields can be compared to.
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Int(i128),
+    Float(f64),
+    Str(String),
+    Bool(bool),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Field(String),
+    Value(Value),
+    Op(Op),
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+}
+
+/// Field reference, as a section and a path in its JSON representation.
+#[derive(Debug)]
+struct Field {
+    section: SectionId,
+    path: Vec<String>,
+}
+
+#[derive(Debug)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Exists(Field),
+    Cmp(Field, Op, Value),
+}
+
+/// Filter matching events, see the module documentation for the syntax.
+#[derive(Debug)]
+pub struct EventFilter(Expr);
+
+impl EventFilter {
+    /// Returns true if the event matches the filter.
+    pub fn matches(&self, event: &Event) -> bool {
+        self.0.eval(event)
+    }
+}
+
+impl FromStr for EventFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            pos: 0,
+        };
+
+        let expr = parser.parse_or()?;
+        if let Some(token) = parser.peek() {
+            bail!("Invalid filter '{s}': unexpected {token:?}");
+        }
+
+        Ok(EventFilter(expr))
+    }
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>> {
+    let chars = s.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    let is_field_char = |c: char| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.');
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        let (token, len) = match (c, next) {
+            (c, _) if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            ('(', _) => (Token::LParen, 1),
+            (')', _) => (Token::RParen, 1),
+            ('&', Some('&')) => (Token::And, 2),
+            ('|', Some('|')) => (Token::Or, 2),
+            ('=', Some('=')) => (Token::Op(Op::Eq), 2),
+            ('!', Some('=')) => (Token::Op(Op::Ne), 2),
+            ('<', Some('=')) => (Token::Op(Op::Le), 2),
+            ('>', Some('=')) => (Token::Op(Op::Ge), 2),
+            ('!', _) => (Token::Not, 1),
+            ('<', _) => (Token::Op(Op::Lt), 1),
+            ('>', _) => (Token::Op(Op::Gt), 1),
+            ('~', _) => (Token::Op(Op::Glob), 1),
+            ('"' | '\'', _) => {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|x| *x == c)
+                    .ok_or_else(|| anyhow!("Invalid filter '{s}': unterminated string"))?;
+                let value = chars[i + 1..i + 1 + end].iter().collect::<String>();
+                (Token::Value(Value::Str(value)), end + 2)
+            }
+            (c, _) if is_field_char(c) => {
+                let len = chars[i..].iter().take_while(|x| is_field_char(**x)).count();
+                let word = chars[i..i + len].iter().collect::<String>();
+
+                let token = match word.as_str() {
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "not" => Token::Not,
+                    "true" => Token::Value(Value::Bool(true)),
+                    "false" => Token::Value(Value::Bool(false)),
+                    _ if c.is_ascii_digit() || c == '-' => {
+                        Token::Value(parse_number(&word).ok_or_else(|| {
+                            anyhow!("Invalid filter '{s}': invalid number '{word}'")
+                        })?)
+                    }
+                    _ => Token::Field(word),
+                };
+                (token, len)
+            }
+            (c, _) => bail!("Invalid filter '{s}': unexpected character '{c}'"),
+        };
+
+        tokens.push(token);
+        i += len;
+    }
+
+    Ok(tokens)
+}
+
+fn parse_number(s: &str) -> Option<Value> {
+    if let Some(hex) = s.strip_prefix("0x") {
+        return i128::from_str_radix(hex, 16).ok().map(Value::Int);
+    }
+    match s.parse::<i128>() {
+        Ok(val) => Some(Value::Int(val)),
+        Err(_) => s.parse::<f64>().ok().map(Value::Float),
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr> {
+        Ok(match self.next() {
+            Some(Token::Not) => Expr::Not(Box::new(self.parse_unary()?)),
+            Some(Token::LParen) => {
+                let expr = self.parse_or()?;
+                if self.next() != Some(Token::RParen) {
+                    bail!("Invalid filter: missing ')'");
+                }
+                expr
+            }
+            Some(Token::Field(field)) => {
+                let field = Field::from_str(&field)?;
+                match self.peek() {
+                    Some(Token::Op(op)) => {
+                        let op = *op;
+                        self.pos += 1;
+                        match self.next() {
+                            Some(Token::Value(value)) => {
+                                if op == Op::Glob && !matches!(value, Value::Str(_)) {
+                                    bail!("Invalid filter: '~' expects a string");
+                                }
+                                Expr::Cmp(field, op, value)
+                            }
+                            token => bail!("Invalid filter: expected a value, found {token:?}"),
+                        }
+                    }
+                    _ => Expr::Exists(field),
+                }
+            }
+            token => bail!("Invalid filter: expected a field, found {token:?}"),
+        })
+    }
+}
+
+impl FromStr for Field {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut path = s.split('.');
+        // Unwrap is safe as split always returns at least one element.
+        let section = path.next().unwrap();
+
+        let path = path.map(String::from).collect::<Vec<_>>();
+        if path.iter().any(|p| p.is_empty()) {
+            bail!("Invalid filter: invalid field '{s}'");
+        }
+
+        Ok(Field {
+            section: SectionId::from_str(section)
+                .map_err(|_| anyhow!("Invalid filter: unknown section '{section}'"))?,
+            path,
+        })
+    }
+}
+
+impl Field {
+    /// Retrieves the field value in an event, if any.
+    fn get(&self, event: &Event) -> Option<serde_json::Value> {
+        let mut value = event.get(self.section)?.to_json();
+
+        for key in self.path.iter() {
+            value = match value {
+                serde_json::Value::Object(mut obj) => obj.remove(key)?,
+                serde_json::Value::Array(mut arr) => {
+                    let idx = key.parse::<usize>().ok()?;
+                    if idx >= arr.len() {
+                        return None;
+                    }
+                    arr.swap_remove(idx)
+                }
+                _ => return None,
+            };
+        }
+
+        Some(value)
+    }
+}
+
+impl Expr {
+    fn eval(&self, event: &Event) -> bool {
+        match self {
+            Expr::And(a, b) => a.eval(event) && b.eval(event),
+            Expr::Or(a, b) => a.eval(event) || b.eval(event),
+            Expr::Not(e) => !e.eval(event),
+            Expr::Exists(field) => !matches!(
+                field.get(event),
+                None | Some(serde_json::Value::Null) | Some(serde_json::Value::Bool(false))
+            ),
+            Expr::Cmp(field, op, value) => match field.get(event) {
+                Some(field) => compare(&field, *op, value),
+                None => false,
+            },
+        }
+    }
+}
+
+fn compare(field: &serde_json::Value, op: Op, value: &Value) -> bool {
+    use serde_json::Value as Json;
+
+    let ord = match (field, value) {
+        (Json::Array(arr), _) => return arr.iter().any(|f| compare(f, op, value)),
+        (Json::String(f), Value::Str(v)) => {
+            if op == Op::Glob {
+                return glob_match(v.as_bytes(), f.as_bytes());
+            }
+            f.as_str().cmp(v.as_str())
+        }
+        (Json::Number(f), Value::Int(v)) => match f.as_u64().map(i128::from) {
+            Some(f) => f.cmp(v),
+            None => match f.as_i64().map(i128::from) {
+                Some(f) => f.cmp(v),
+                None => match f.as_f64().and_then(|f| f.partial_cmp(&(*v as f64))) {
+                    Some(ord) => ord,
+                    None => return false,
+                },
+            },
+        },
+        (Json::Number(f), Value::Float(v)) => match f.as_f64().and_then(|f| f.partial_cmp(v)) {
+            Some(ord) => ord,
+            None => return false,
+        },
+        (Json::Bool(f), Value::Bool(v)) => match op {
+            Op::Eq | Op::Ne => f.cmp(v),
+            _ => return false,
+        },
+        _ => return false,
+    };
+
+    match op {
+        Op::Eq => ord == Ordering::Equal,
+        Op::Ne => ord != Ordering::Equal,
+        Op::Lt => ord == Ordering::Less,
+        Op::Le => ord != Ordering::Greater,
+        Op::Gt => ord == Ordering::Greater,
+        Op::Ge => ord != Ordering::Less,
+        Op::Glob => false,
+    }
+}
+
+/// Matches a string against a glob pattern supporting `*` and `?`.
+fn glob_match(pattern: &[u8], s: &[u8]) -> bool {
+    let (mut p, mut i) = (0, 0);
+    // Position of the last '*' in the pattern and of the matching position in
+    // the string, to backtrack on mismatches.
+    let mut star: Option<(usize, usize)> = None;
+
+    while i < s.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                star = Some((p, i));
+                p += 1;
+            }
+            Some(c) if *c == b'?' || *c == s[i] => {
+                p += 1;
+                i += 1;
+            }
+            _ => match star {
+                Some((sp, si)) => {
+                    p = sp + 1;
+                    i = si + 1;
+                    star = Some((sp, si + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == b'*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event() -> Event {
+        Event::from_json(
+            r#"{"common":{"timestamp":1234},"kernel":{"probe_type":"kprobe","symbol":"ip_rcv"},"skb":{"ip":{"saddr":"10.0.0.1","daddr":"10.0.0.2","ecn":0,"len":60,"protocol":6,"ttl":64,"v4":{"flags":2,"id":1,"offset":0,"tos":0}},"tcp":{"sport":34567,"dport":443,"flags":2,"seq":1,"ack_seq":0,"window":64240,"doff":10}}}"#
+                .to_string(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn filter_match() {
+        let event = event();
+        let matches = |f: &str| EventFilter::from_str(f).unwrap().matches(&event);
+
+        assert!(matches("skb.tcp.dport == 443"));
+        assert!(matches("skb.tcp.dport == 0x1bb"));
+        assert!(matches(r#"skb.tcp.dport == 443 && kernel.symbol ~ "ip_*""#));
+        assert!(matches("kernel.symbol ~ 'ip_?cv'"));
+        assert!(matches("skb.tcp.dport > 80 and skb.ip.ttl <= 64"));
+        assert!(matches("skb.tcp.dport == 80 || skb.tcp.sport >= 1024"));
+        assert!(matches("!(skb.tcp.dport == 80) && not skb.udp"));
+        assert!(matches("skb.tcp && common.timestamp != 0"));
+        assert!(matches("skb.ip.saddr == '10.0.0.1'"));
+
+        assert!(!matches("skb.tcp.dport == 80"));
+        assert!(!matches("skb.udp.dport == 443"));
+        assert!(!matches("skb.udp.dport != 443"));
+        assert!(!matches("skb-drop"));
+        assert!(!matches("kernel.symbol ~ 'ip_'"));
+        assert!(!matches("skb.tcp.dport == '443'"));
+    }
+
+    #[test]
+    fn filter_parse_errors() {
+        for filter in [
+            "",
+            "skb.tcp.dport ==",
+            "foo.bar == 1",
+            "skb..tcp",
+            "(skb.tcp",
+            "skb.tcp.dport == 443 skb",
+            "kernel.symbol ~ 1",
+            "kernel.symbol == \"ip_rcv",
+            "skb.tcp.dport == 1x",
+        ] {
+            assert!(EventFilter::from_str(filter).is_err(), "{filter}");
+        }
+    }
+
+    #[test]
+    fn glob() {
+        assert!(glob_match(b"*", b""));
+        assert!(glob_match(b"ip_*", b"ip_rcv"));
+        assert!(glob_match(b"*_rcv", b"ip_rcv"));
+        assert!(glob_match(b"*r*v", b"ip_rcv"));
+        assert!(glob_match(b"i?_rcv", b"ip_rcv"));
+        assert!(!glob_match(b"ip_", b"ip_rcv"));
+        assert!(!glob_match(b"*_xmit", b"ip_rcv"));
+    }
+}
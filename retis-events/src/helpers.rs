@@ -31,7 +31,7 @@ pub fn etype_str(etype: u16) -> Option<&'static str> {
 }
 
 /// Returns a translation of some protocols into a readable format.
-pub(crate) fn protocol_str(protocol: u8) -> Option<&'static str> {
+pub fn protocol_str(protocol: u8) -> Option<&'static str> {
     Some(match protocol {
         1 => "ICMP",
         2 => "IGMP",
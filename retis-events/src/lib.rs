@@ -13,6 +13,7 @@ pub mod display;
 pub use display::*;
 
 pub mod file;
+pub mod filter;
 pub mod helpers;
 #[cfg(feature = "python")]
 pub mod python;
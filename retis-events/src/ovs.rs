@@ -57,6 +57,22 @@ pub enum OvsEvent {
     },
 }
 
+impl OvsEvent {
+    /// Returns the event type, as used in the `event_type` field of its JSON
+    /// representation.
+    pub fn event_type(&self) -> &'static str {
+        use OvsEvent::*;
+        match self {
+            Upcall { .. } => "upcall",
+            UpcallEnqueue { .. } => "upcall_enqueue",
+            UpcallReturn { .. } => "upcall_return",
+            RecvUpcall { .. } => "recv_upcall",
+            Operation { .. } => "flow_operation",
+            Action { .. } => "action_execute",
+        }
+    }
+}
+
 impl EventFmt for OvsEvent {
     fn event_fmt(&self, f: &mut Formatter, format: &DisplayFormat) -> fmt::Result {
         use OvsEvent::*;
@@ -668,6 +684,12 @@ mod tests {
             let parsed: OvsEvent = serde_json::from_str(event_json)
                 .map_err(|e| anyhow!("Failed to convert json '{event_json}' to event: {e}"))?;
             assert_eq!(&parsed, event);
+
+            // The event type must match the serde tag.
+            assert_eq!(
+                serde_json::from_str::<Value>(event_json).unwrap()["event_type"],
+                event.event_type()
+            );
         }
         Ok(())
     }
@@ -201,6 +201,9 @@ impl PyEventSeries {
 ///
 /// Objects of this class can read events from unsorted event files.
 ///
+/// When `follow` is set, the reader keeps waiting for new events once the end
+/// of the file is reached (similar to `tail -f`), which allows processing
+/// events of an ongoing collection. The file is re-opened if rotated.
 ///
 /// ## Example
 ///
@@ -209,25 +212,37 @@ impl PyEventSeries {
 ///
 /// for event in series:
 ///     print(event.show())
+///
+/// for event in EventReader("retis.data", follow=True):
+///     print(event.show())
 /// ```
 #[pyclass(name = "EventReader")]
 pub(crate) struct PyEventReader {
     pub(crate) factory: file::FileEventsFactory,
+    follow: bool,
 }
 
 #[pymethods]
 impl PyEventReader {
     #[new]
-    pub(crate) fn new(path: PathBuf) -> PyResult<Self> {
+    #[pyo3(signature = (path, follow=false))]
+    pub(crate) fn new(py: Python<'_>, path: PathBuf, follow: bool) -> PyResult<Self> {
+        // In follow mode, wait for the file to be created and populated.
+        while follow && !file::FileEventsFactory::ready(&path) {
+            py.check_signals()?;
+            py.allow_threads(|| std::thread::sleep(file::FOLLOW_POLL_INTERVAL));
+        }
+
         let factory = file::FileEventsFactory::new(path)
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?
+            .follow(follow);
 
         if matches!(factory.file_type(), file::FileType::Series) {
             return Err(PyRuntimeError::new_err(
                 "Cannot create a EventReader from a sorted file. Use an SeriesReader instead",
             ));
         }
-        Ok(PyEventReader { factory })
+        Ok(PyEventReader { factory, follow })
     }
 
     // Implementation of the iterator protocol.
@@ -240,16 +255,23 @@ impl PyEventReader {
         mut slf: PyRefMut<'_, Self>,
         py: Python<'_>,
     ) -> PyResult<Option<Py<PyAny>>> {
-        match slf
-            .factory
-            .next_event()
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?
-        {
-            Some(event) => {
-                let pyevent: Bound<'_, PyEvent> = Bound::new(py, PyEvent::new(event))?;
-                Ok(Some(pyevent.into_any().into()))
+        loop {
+            match slf
+                .factory
+                .next_event()
+                .map_err(|e| PyRuntimeError::new_err(e.to_string()))?
+            {
+                Some(event) => {
+                    let pyevent: Bound<'_, PyEvent> = Bound::new(py, PyEvent::new(event))?;
+                    return Ok(Some(pyevent.into_any().into()));
+                }
+                None if slf.follow => {
+                    // Allow interrupting the wait (e.g. ctrl+c).
+                    py.check_signals()?;
+                    py.allow_threads(|| std::thread::sleep(file::FOLLOW_POLL_INTERVAL));
+                }
+                None => return Ok(None),
             }
-            None => Ok(None),
         }
     }
 }
@@ -357,8 +379,9 @@ impl PyEventFile {
         }
     }
 
-    pub(crate) fn events(&self) -> PyResult<PyEventReader> {
-        PyEventReader::new(self.path.clone())
+    #[pyo3(signature = (follow=false))]
+    pub(crate) fn events(&self, py: Python<'_>, follow: bool) -> PyResult<PyEventReader> {
+        PyEventReader::new(py, self.path.clone(), follow)
     }
 
     pub(crate) fn series(&self) -> PyResult<PySeriesReader> {
@@ -14,6 +14,11 @@ pub struct SkbEvent {
     pub eth: Option<SkbEthEvent>,
     /// VLAN tag fields, if any.
     pub vlan: Option<SkbVlanEvent>,
+    /// Stacked VLAN tags (e.g. QinQ), outermost first, if the packet has more
+    /// than one tag.
+    pub vlans: Option<Vec<SkbVlanEvent>>,
+    /// MPLS label stack, outermost first, if any.
+    pub mpls: Option<Vec<SkbMplsEvent>>,
     /// ARP fields, if any.
     pub arp: Option<SkbArpEvent>,
     /// IPv4 or IPv6 fields, if any.
@@ -26,6 +31,17 @@ pub struct SkbEvent {
     pub icmp: Option<SkbIcmpEvent>,
     /// ICMPv6 fields, if any.
     pub icmpv6: Option<SkbIcmpV6Event>,
+    /// SCTP fields, if any.
+    pub sctp: Option<SkbSctpEvent>,
+    /// DCCP fields, if any.
+    pub dccp: Option<SkbDccpEvent>,
+    /// GRE fields, if any.
+    pub gre: Option<SkbGreEvent>,
+    /// Encapsulated packet fields, if any.
+    pub inner: Option<SkbInnerEvent>,
+    /// Application layer fields, decoded from the raw packet at
+    /// post-processing time, if any.
+    pub app: Option<SkbAppEvent>,
     /// Net device data, if any.
     pub dev: Option<SkbDevEvent>,
     /// Net namespace data, if any.
@@ -40,10 +56,45 @@ pub struct SkbEvent {
     pub packet: Option<SkbPacketEvent>,
 }
 
+impl SkbEvent {
+    /// L4 source and destination ports, for protocols having them.
+    pub fn ports(&self) -> Option<(u16, u16)> {
+        self.headers().ports()
+    }
+
+    /// Decodes the application layer data (DNS, DHCP, HTTP/1) from the raw
+    /// packet, if not already done.
+    pub fn decode_app(&mut self) {
+        if self.app.is_some() {
+            return;
+        }
+
+        if let Some(packet) = &self.packet {
+            self.app = SkbAppEvent::from_packet(&packet.packet.0);
+        }
+    }
+
+    fn headers(&self) -> PacketHeaders<'_> {
+        PacketHeaders {
+            eth: self.eth.as_ref(),
+            vlan: self.vlan.as_ref(),
+            vlans: self.vlans.as_deref(),
+            mpls: self.mpls.as_deref(),
+            arp: self.arp.as_ref(),
+            ip: self.ip.as_ref(),
+            tcp: self.tcp.as_ref(),
+            udp: self.udp.as_ref(),
+            icmp: self.icmp.as_ref(),
+            icmpv6: self.icmpv6.as_ref(),
+            sctp: self.sctp.as_ref(),
+            dccp: self.dccp.as_ref(),
+            gre: self.gre.as_ref(),
+        }
+    }
+}
+
 impl EventFmt for SkbEvent {
     fn event_fmt(&self, f: &mut Formatter, _: &DisplayFormat) -> fmt::Result {
-        let mut len = 0;
-
         let mut space = DelimWriter::new(' ');
 
         if let Some(ns) = &self.ns {
@@ -65,7 +116,148 @@ impl EventFmt for SkbEvent {
             }
         }
 
-        if let Some(eth) = &self.eth {
+        self.headers().fmt(f, &mut space)?;
+
+        if let Some(app) = &self.app {
+            app.fmt(f, &mut space)?;
+        }
+
+        if let Some(inner) = &self.inner {
+            space.write(f)?;
+
+            write!(f, "{}", inner.encap.as_str())?;
+            if let Some(vni) = inner.vni {
+                write!(f, " vni {vni}")?;
+            }
+            if let Some(key) = inner.key {
+                write!(f, " key {key:#x}")?;
+            }
+
+            write!(f, " [")?;
+            let mut inner_space = DelimWriter::new(' ');
+            inner.headers().fmt(f, &mut inner_space)?;
+            if !inner_space.used() {
+                write!(f, "unknown packet")?;
+            }
+            write!(f, "]")?;
+        }
+
+        if self.meta.is_some() || self.data_ref.is_some() {
+            space.write(f)?;
+            write!(f, "skb [")?;
+
+            if let Some(meta) = &self.meta {
+                write!(f, "csum ")?;
+                match meta.ip_summed {
+                    0 => write!(f, "none ")?,
+                    1 => write!(f, "unnecessary (level {}) ", meta.csum_level)?,
+                    2 => write!(f, "complete ({:#x}) ", meta.csum)?,
+                    3 => {
+                        let start = meta.csum & 0xffff;
+                        let off = meta.csum >> 16;
+                        write!(f, "partial (start {start} off {off}) ")?;
+                    }
+                    x => write!(f, "unknown ({}) ", x)?,
+                }
+
+                if meta.hash != 0 {
+                    write!(f, "hash {:#x} ", meta.hash)?;
+                }
+                write!(f, "len {} ", meta.len,)?;
+                if meta.data_len != 0 {
+                    write!(f, "data_len {} ", meta.data_len)?;
+                }
+                write!(f, "priority {}", meta.priority)?;
+            }
+
+            if self.meta.is_some() && self.data_ref.is_some() {
+                write!(f, " ")?;
+            }
+
+            if let Some(dataref) = &self.data_ref {
+                if dataref.nohdr {
+                    write!(f, "nohdr ")?;
+                }
+                if dataref.cloned {
+                    write!(f, "cloned ")?;
+                }
+                if dataref.fclone > 0 {
+                    write!(f, "fclone {} ", dataref.fclone)?;
+                }
+                write!(f, "users {} dataref {}", dataref.users, dataref.dataref)?;
+            }
+
+            write!(f, "]")?;
+        }
+
+        if let Some(gso) = &self.gso {
+            space.write(f)?;
+            write!(f, "gso [type {:#x} ", gso.r#type)?;
+
+            if gso.flags != 0 {
+                write!(f, "flags {:#x} ", gso.flags)?;
+            }
+
+            if gso.frags != 0 {
+                write!(f, "frags {} ", gso.frags)?;
+            }
+
+            if gso.segs != 0 {
+                write!(f, "segs {} ", gso.segs)?;
+            }
+
+            write!(f, "size {}]", gso.size)?;
+        }
+
+        // If we didn't print any section, it means the section has raw packet
+        // data but we were unable to decode it. Print something.
+        if !space.used() {
+            space.write(f)?;
+            write!(f, "unknown packet (see raw data)")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Packet headers, shared by the outer and encapsulated packets for display
+/// purposes.
+struct PacketHeaders<'a> {
+    eth: Option<&'a SkbEthEvent>,
+    vlan: Option<&'a SkbVlanEvent>,
+    vlans: Option<&'a [SkbVlanEvent]>,
+    mpls: Option<&'a [SkbMplsEvent]>,
+    arp: Option<&'a SkbArpEvent>,
+    ip: Option<&'a SkbIpEvent>,
+    tcp: Option<&'a SkbTcpEvent>,
+    udp: Option<&'a SkbUdpEvent>,
+    icmp: Option<&'a SkbIcmpEvent>,
+    icmpv6: Option<&'a SkbIcmpV6Event>,
+    sctp: Option<&'a SkbSctpEvent>,
+    dccp: Option<&'a SkbDccpEvent>,
+    gre: Option<&'a SkbGreEvent>,
+}
+
+impl PacketHeaders<'_> {
+    /// Source and destination ports, for protocols having them.
+    fn ports(&self) -> Option<(u16, u16)> {
+        if let Some(tcp) = self.tcp {
+            Some((tcp.sport, tcp.dport))
+        } else if let Some(udp) = self.udp {
+            Some((udp.sport, udp.dport))
+        } else if let Some(sctp) = self.sctp {
+            Some((sctp.sport, sctp.dport))
+        } else {
+            self.dccp.map(|dccp| (dccp.sport, dccp.dport))
+        }
+    }
+}
+
+impl PacketHeaders<'_> {
+    fn fmt(&self, f: &mut Formatter, space: &mut DelimWriter) -> fmt::Result {
+        let mut len = 0;
+
+        if let Some(eth) = self.eth {
             space.write(f)?;
 
             write!(f, "{} > {} ethertype", eth.src, eth.dst)?;
@@ -75,19 +267,43 @@ impl EventFmt for SkbEvent {
             write!(f, " ({:#06x})", eth.etype)?;
         }
 
-        if let Some(vlan) = &self.vlan {
+        // The full stack of tags, if reported, includes the single tag one.
+        let vlans = match (self.vlans, self.vlan) {
+            (Some(vlans), _) => vlans,
+            (None, Some(vlan)) => std::slice::from_ref(vlan),
+            _ => &[],
+        };
+        for vlan in vlans {
             space.write(f)?;
 
-            let drop = if vlan.dei { " drop" } else { "" };
-            let accel = if vlan.acceleration { " accel" } else { "" };
-            write!(
-                f,
-                "vlan (id {} prio {}{}{})",
-                vlan.vid, vlan.pcp, drop, accel
-            )?;
+            write!(f, "vlan (id {} prio {}", vlan.vid, vlan.pcp)?;
+            if vlan.dei {
+                write!(f, " drop")?;
+            }
+            if vlan.acceleration {
+                write!(f, " accel")?;
+            }
+            // Only report non-802.1Q tags' protocol (e.g. 802.1ad).
+            if let Some(tpid) = vlan.tpid.filter(|tpid| *tpid != 0x8100) {
+                write!(f, " tpid {tpid:#06x}")?;
+            }
+            write!(f, ")")?;
+        }
+
+        if let Some(mpls) = self.mpls {
+            space.write(f)?;
+
+            write!(f, "MPLS")?;
+            for label in mpls {
+                write!(f, " (label {}, tc {}", label.label, label.tc)?;
+                if label.bos {
+                    write!(f, ", [S]")?;
+                }
+                write!(f, ", ttl {})", label.ttl)?;
+            }
         }
 
-        if let Some(arp) = &self.arp {
+        if let Some(arp) = self.arp {
             space.write(f)?;
 
             match arp.operation {
@@ -110,23 +326,21 @@ impl EventFmt for SkbEvent {
             }
         }
 
-        if let Some(ip) = &self.ip {
+        if let Some(ip) = self.ip {
             space.write(f)?;
 
-            // The below is not 100% correct:
-            // - IPv4: we use the fixed 20 bytes size as options are rarely used.
-            // - IPv6: we do not support extension headers.
-            len = match ip.version {
-                SkbIpVersion::V4 { .. } => ip.len.saturating_sub(20),
-                _ => ip.len,
+            len = match &ip.version {
+                SkbIpVersion::V4 { v4 } => ip.len.saturating_sub(v4.header_len()),
+                SkbIpVersion::V6 { v6 } => ip
+                    .len
+                    .saturating_sub(v6.ext_headers.iter().flatten().map(|h| h.len).sum::<u16>()),
             };
 
-            if let Some(tcp) = &self.tcp {
-                write!(f, "{}.{} > {}.{}", ip.saddr, tcp.sport, ip.daddr, tcp.dport)?;
-            } else if let Some(udp) = &self.udp {
-                write!(f, "{}.{} > {}.{}", ip.saddr, udp.sport, ip.daddr, udp.dport)?;
-            } else {
-                write!(f, "{} > {}", ip.saddr, ip.daddr)?;
+            match self.ports() {
+                Some((sport, dport)) => {
+                    write!(f, "{}.{} > {}.{}", ip.saddr, sport, ip.daddr, dport)?
+                }
+                None => write!(f, "{} > {}", ip.saddr, ip.daddr)?,
             }
 
             write!(
@@ -161,11 +375,47 @@ impl EventFmt for SkbEvent {
                     if !flags.is_empty() {
                         write!(f, " [{}]", flags.join(","))?;
                     }
+
+                    if let Some(options) = &v4.options {
+                        write!(f, " options [")?;
+                        for (i, option) in options.iter().enumerate() {
+                            if i > 0 {
+                                write!(f, ",")?;
+                            }
+                            option.fmt(f)?;
+                        }
+                        write!(f, "]")?;
+                    }
                 }
                 SkbIpVersion::V6 { v6 } => {
                     if v6.flow_label != 0 {
                         write!(f, " label {:#x}", v6.flow_label)?;
                     }
+
+                    if let Some(ext_headers) = &v6.ext_headers {
+                        let ext_headers = ext_headers
+                            .iter()
+                            .map(|h| match (h.routing_type, h.segments_left) {
+                                (Some(rtype), Some(left)) => {
+                                    let mut s = format!("rh(type {rtype} segleft {left}");
+                                    if let Some(segments) = &h.segments {
+                                        s.push_str(&format!(" [{}]", segments.join(",")));
+                                    }
+                                    s.push(')');
+                                    s
+                                }
+                                _ => h.r#type.as_str().to_string(),
+                            })
+                            .collect::<Vec<_>>();
+                        write!(f, " exthdrs [{}]", ext_headers.join(","))?;
+                    }
+
+                    if let Some(frag) = &v6.frag {
+                        write!(f, " frag id {:#x} off {}", frag.id, frag.offset * 8)?;
+                        if frag.more {
+                            write!(f, " [+]")?;
+                        }
+                    }
                 }
             }
 
@@ -182,7 +432,7 @@ impl EventFmt for SkbEvent {
             write!(f, " ({})", ip.protocol)?;
         }
 
-        if let Some(tcp) = &self.tcp {
+        if let Some(tcp) = self.tcp {
             space.write(f)?;
 
             let mut flags = Vec::new();
@@ -218,99 +468,119 @@ impl EventFmt for SkbEvent {
             }
 
             write!(f, " win {}", tcp.window)?;
+            tcp.fmt_options(f)?;
         }
 
-        if let Some(udp) = &self.udp {
+        if let Some(udp) = self.udp {
             space.write(f)?;
             let len = udp.len;
             // Substract the UDP header size when reporting the length.
             write!(f, "len {}", len.saturating_sub(8))?;
         }
 
-        if let Some(icmp) = &self.icmp {
+        if let Some(icmp) = self.icmp {
             space.write(f)?;
-            // TODO: text version
-            write!(f, "type {} code {}", icmp.r#type, icmp.code)?;
-        }
 
-        if let Some(icmpv6) = &self.icmpv6 {
-            space.write(f)?;
-            // TODO: text version
-            write!(f, "type {} code {}", icmpv6.r#type, icmpv6.code)?;
+            match icmp.type_str() {
+                Some(r#type) => write!(f, "{type}")?,
+                None => write!(f, "type {}", icmp.r#type)?,
+            }
+            match icmp.code_str() {
+                Some(code) => write!(f, " ({code})")?,
+                None if icmp.code != 0 => write!(f, " code {}", icmp.code)?,
+                None => (),
+            }
+
+            if let Some(mtu) = icmp.mtu {
+                write!(f, " mtu {mtu}")?;
+            }
+            if let Some(orig) = &icmp.orig {
+                orig.fmt(f)?;
+            }
         }
 
-        if self.meta.is_some() || self.data_ref.is_some() {
+        if let Some(icmpv6) = self.icmpv6 {
             space.write(f)?;
-            write!(f, "skb [")?;
-
-            if let Some(meta) = &self.meta {
-                write!(f, "csum ")?;
-                match meta.ip_summed {
-                    0 => write!(f, "none ")?,
-                    1 => write!(f, "unnecessary (level {}) ", meta.csum_level)?,
-                    2 => write!(f, "complete ({:#x}) ", meta.csum)?,
-                    3 => {
-                        let start = meta.csum & 0xffff;
-                        let off = meta.csum >> 16;
-                        write!(f, "partial (start {start} off {off}) ")?;
-                    }
-                    x => write!(f, "unknown ({}) ", x)?,
-                }
 
-                if meta.hash != 0 {
-                    write!(f, "hash {:#x} ", meta.hash)?;
-                }
-                write!(f, "len {} ", meta.len,)?;
-                if meta.data_len != 0 {
-                    write!(f, "data_len {} ", meta.data_len)?;
-                }
-                write!(f, "priority {}", meta.priority)?;
+            match icmpv6.type_str() {
+                Some(r#type) => write!(f, "{type}")?,
+                None => write!(f, "type {}", icmpv6.r#type)?,
             }
-
-            if self.meta.is_some() && self.data_ref.is_some() {
-                write!(f, " ")?;
+            match icmpv6.code_str() {
+                Some(code) => write!(f, " ({code})")?,
+                None if icmpv6.code != 0 => write!(f, " code {}", icmpv6.code)?,
+                None => (),
             }
 
-            if let Some(dataref) = &self.data_ref {
-                if dataref.nohdr {
-                    write!(f, "nohdr ")?;
-                }
-                if dataref.cloned {
-                    write!(f, "cloned ")?;
-                }
-                if dataref.fclone > 0 {
-                    write!(f, "fclone {} ", dataref.fclone)?;
-                }
-                write!(f, "users {} dataref {}", dataref.users, dataref.dataref)?;
+            if let Some(mtu) = icmpv6.mtu {
+                write!(f, " mtu {mtu}")?;
+            }
+            if let Some(target) = &icmpv6.target {
+                write!(f, " target {target}")?;
+            }
+            if let Some(orig) = &icmpv6.orig {
+                orig.fmt(f)?;
             }
-
-            write!(f, "]")?;
         }
 
-        if let Some(gso) = &self.gso {
+        if let Some(sctp) = self.sctp {
             space.write(f)?;
-            write!(f, "gso [type {:#x} ", gso.r#type)?;
+            write!(f, "vtag {:#x}", sctp.vtag)?;
 
-            if gso.flags != 0 {
-                write!(f, "flags {:#x} ", gso.flags)?;
+            let chunks = sctp
+                .chunks
+                .iter()
+                .map(|c| match SkbSctpEvent::chunk_str(*c) {
+                    Some(chunk) => chunk.to_string(),
+                    None => c.to_string(),
+                })
+                .collect::<Vec<_>>();
+            write!(f, " chunks [{}]", chunks.join(","))?;
+        }
+
+        if let Some(dccp) = self.dccp {
+            space.write(f)?;
+            match SkbDccpEvent::type_str(dccp.r#type) {
+                Some(r#type) => write!(f, "{}", r#type)?,
+                None => write!(f, "type {}", dccp.r#type)?,
             }
+            write!(f, " seq {}", dccp.seq)?;
+        }
 
-            if gso.frags != 0 {
-                write!(f, "frags {} ", gso.frags)?;
+        if let Some(gre) = self.gre {
+            space.write(f)?;
+            write!(f, "gre")?;
+
+            let mut flags = Vec::new();
+            if gre.flags & 0x80 != 0 {
+                flags.push("C");
+            }
+            if gre.flags & 0x20 != 0 {
+                flags.push("K");
+            }
+            if gre.flags & 0x10 != 0 {
+                flags.push("S");
+            }
+            if !flags.is_empty() {
+                write!(f, " [{}]", flags.join(","))?;
             }
 
-            if gso.segs != 0 {
-                write!(f, "segs {} ", gso.segs)?;
+            if gre.version != 0 {
+                write!(f, " v{}", gre.version)?;
             }
 
-            write!(f, "size {}]", gso.size)?;
-        }
+            write!(f, " proto")?;
+            if let Some(etype) = etype_str(gre.protocol) {
+                write!(f, " {etype}")?;
+            }
+            write!(f, " ({:#06x})", gre.protocol)?;
 
-        // If we didn't print any section, it means the section has raw packet
-        // data but we were unable to decode it. Print something.
-        if !space.used() {
-            space.write(f)?;
-            write!(f, "unknown packet (see raw data)")?;
+            if let Some(key) = gre.key {
+                write!(f, " key {key:#x}")?;
+            }
+            if let Some(seq) = gre.seq {
+                write!(f, " seq {seq}")?;
+            }
         }
 
         Ok(())
@@ -339,6 +609,22 @@ pub struct SkbVlanEvent {
     pub vid: u16,
     /// VLAN acceleration field.
     pub acceleration: bool,
+    /// Tag protocol identifier (e.g. 0x8100 or 0x88a8), for tags found in the
+    /// packet data.
+    pub tpid: Option<u16>,
+}
+
+/// MPLS label stack entry.
+#[event_type]
+pub struct SkbMplsEvent {
+    /// Label.
+    pub label: u32,
+    /// Traffic class.
+    pub tc: u8,
+    /// Bottom of stack.
+    pub bos: bool,
+    /// TTL.
+    pub ttl: u8,
 }
 
 /// ARP fields.
@@ -375,7 +661,8 @@ pub struct SkbIpEvent {
     /// IP version: 4 or 6.
     #[serde(flatten)]
     pub version: SkbIpVersion,
-    /// L4 protocol, from IPv4 "protocol" field or IPv6 "next header" one.
+    /// L4 protocol, from IPv4 "protocol" field or IPv6 "next header" one
+    /// (of the last extension header, if any).
     pub protocol: u8,
     /// "total len" from the IPv4 header or "payload length" from the IPv6 one.
     pub len: u16,
@@ -410,6 +697,68 @@ pub struct SkbIpv4Event {
     pub flags: u8,
     /// Fragment offset.
     pub offset: u16,
+    /// Header length, in 4-byte units.
+    pub ihl: Option<u8>,
+    /// Options, if any.
+    pub options: Option<Vec<SkbIpv4Option>>,
+}
+
+impl SkbIpv4Event {
+    /// Header length, in bytes. Defaults to the length of a header without
+    /// options if unknown.
+    pub fn header_len(&self) -> u16 {
+        self.ihl.unwrap_or(5) as u16 * 4
+    }
+}
+
+/// IPv4 option.
+#[event_type]
+pub struct SkbIpv4Option {
+    /// Option type, including the copied flag and class.
+    pub r#type: u8,
+    /// Length of the option, in bytes.
+    pub len: u8,
+    /// Recorded addresses, for record route and timestamp (with addresses)
+    /// options.
+    pub addrs: Option<Vec<String>>,
+    /// Timestamps, for timestamp options.
+    pub timestamps: Option<Vec<u32>>,
+    /// Value, for router alert options.
+    pub value: Option<u16>,
+}
+
+impl SkbIpv4Option {
+    /// Record route.
+    pub const RR: u8 = 7;
+    /// Timestamp.
+    pub const TS: u8 = 68;
+    /// Router alert.
+    pub const RA: u8 = 148;
+
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.r#type {
+            Self::RR => write!(f, "rr")?,
+            Self::TS => write!(f, "ts")?,
+            Self::RA => write!(f, "ra")?,
+            x => write!(f, "opt-{x} len {}", self.len)?,
+        }
+
+        // Timestamps are reported along the recorded addresses, if any.
+        match (&self.addrs, &self.timestamps) {
+            (Some(addrs), Some(timestamps)) => addrs
+                .iter()
+                .zip(timestamps)
+                .try_for_each(|(addr, ts)| write!(f, " {addr}@{ts}"))?,
+            (Some(addrs), None) => addrs.iter().try_for_each(|addr| write!(f, " {addr}"))?,
+            (None, Some(timestamps)) => timestamps.iter().try_for_each(|ts| write!(f, " {ts}"))?,
+            (None, None) => (),
+        }
+
+        if let Some(value) = self.value {
+            write!(f, " value {value}")?;
+        }
+        Ok(())
+    }
 }
 
 /// IPv6 specific fields.
@@ -417,6 +766,59 @@ pub struct SkbIpv4Event {
 pub struct SkbIpv6Event {
     /// Flow label.
     pub flow_label: u32,
+    /// Extension headers, in the order they appear in the packet.
+    pub ext_headers: Option<Vec<SkbIpv6ExtHeader>>,
+    /// Fragment header fields, if the packet is a fragment.
+    pub frag: Option<SkbIpv6FragEvent>,
+}
+
+/// IPv6 extension header types.
+#[event_type]
+#[serde(rename_all = "snake_case")]
+pub enum SkbIpv6ExtType {
+    HopByHop,
+    Routing,
+    Fragment,
+    DestOpts,
+    Auth,
+}
+
+impl SkbIpv6ExtType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SkbIpv6ExtType::HopByHop => "hbh",
+            SkbIpv6ExtType::Routing => "rh",
+            SkbIpv6ExtType::Fragment => "frag",
+            SkbIpv6ExtType::DestOpts => "dstopt",
+            SkbIpv6ExtType::Auth => "ah",
+        }
+    }
+}
+
+/// IPv6 extension header.
+#[event_type]
+pub struct SkbIpv6ExtHeader {
+    /// Extension header type.
+    pub r#type: SkbIpv6ExtType,
+    /// Length of the extension header, in bytes.
+    pub len: u16,
+    /// Routing type, for routing headers.
+    pub routing_type: Option<u8>,
+    /// Segments left, for routing headers.
+    pub segments_left: Option<u8>,
+    /// Segment list, for segment routing headers (SRH).
+    pub segments: Option<Vec<String>>,
+}
+
+/// IPv6 fragment header fields.
+#[event_type]
+pub struct SkbIpv6FragEvent {
+    /// Identification.
+    pub id: u32,
+    /// Fragment offset, in 8-byte units.
+    pub offset: u16,
+    /// More fragments flag.
+    pub more: bool,
 }
 
 /// TCP fields.
@@ -433,17 +835,99 @@ pub struct SkbTcpEvent {
     pub doff: u8,
     /// Bitfield of TCP flags as defined in `struct tcphdr` in the kernel.
     pub flags: u8,
+    /// Maximum segment size option, if any.
+    pub mss: Option<u16>,
+    /// Window scale option (shift count), if any.
+    pub wscale: Option<u8>,
+    /// SACK permitted option.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub sack_permitted: bool,
+    /// SACK blocks, if any.
+    pub sack: Option<Vec<SkbTcpSackBlock>>,
+    /// Timestamp option value, if any.
+    pub tsval: Option<u32>,
+    /// Timestamp option echo reply, if any.
+    pub tsecr: Option<u32>,
+    /// Kinds of the above options, in the order they appear in the packet.
+    pub options: Option<Vec<u8>>,
 }
 
-/// UDP fields.
-#[event_type]
-pub struct SkbUdpEvent {
-    /// Source port.
-    pub sport: u16,
-    /// Destination port.
-    pub dport: u16,
-    /// Length from the UDP header.
-    pub len: u16,
+impl SkbTcpEvent {
+    /// Maximum segment size.
+    pub const OPT_MSS: u8 = 2;
+    /// Window scale.
+    pub const OPT_WSCALE: u8 = 3;
+    /// SACK permitted.
+    pub const OPT_SACK_PERMITTED: u8 = 4;
+    /// SACK.
+    pub const OPT_SACK: u8 = 5;
+    /// Timestamps.
+    pub const OPT_TS: u8 = 8;
+
+    fn fmt_options(&self, f: &mut Formatter) -> fmt::Result {
+        let Some(kinds) = &self.options else {
+            return Ok(());
+        };
+
+        let mut options = Vec::new();
+        for kind in kinds {
+            match *kind {
+                Self::OPT_MSS => {
+                    if let Some(mss) = self.mss {
+                        options.push(format!("mss {mss}"));
+                    }
+                }
+                Self::OPT_WSCALE => {
+                    if let Some(wscale) = self.wscale {
+                        options.push(format!("wscale {wscale}"));
+                    }
+                }
+                Self::OPT_SACK_PERMITTED if self.sack_permitted => {
+                    options.push("sackOK".to_string())
+                }
+                Self::OPT_SACK => {
+                    if let Some(sack) = &self.sack {
+                        let blocks = sack
+                            .iter()
+                            .map(|b| format!("{{{}:{}}}", b.left, b.right))
+                            .collect::<Vec<_>>();
+                        options.push(format!("sack {} {}", sack.len(), blocks.join("")));
+                    }
+                }
+                Self::OPT_TS => {
+                    if let Some(tsval) = self.tsval {
+                        options.push(format!("TS val {tsval} ecr {}", self.tsecr.unwrap_or(0)));
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        if !options.is_empty() {
+            write!(f, " options [{}]", options.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+/// TCP SACK block.
+#[event_type]
+pub struct SkbTcpSackBlock {
+    /// Left edge (first sequence number) of the block.
+    pub left: u32,
+    /// Right edge (sequence number following the last one) of the block.
+    pub right: u32,
+}
+
+/// UDP fields.
+#[event_type]
+pub struct SkbUdpEvent {
+    /// Source port.
+    pub sport: u16,
+    /// Destination port.
+    pub dport: u16,
+    /// Length from the UDP header.
+    pub len: u16,
 }
 
 /// ICMP fields.
@@ -451,6 +935,52 @@ pub struct SkbUdpEvent {
 pub struct SkbIcmpEvent {
     pub r#type: u8,
     pub code: u8,
+    /// Next-hop MTU, for fragmentation needed errors.
+    pub mtu: Option<u16>,
+    /// Original packet the error is about, for error messages.
+    pub orig: Option<SkbIcmpOrigEvent>,
+}
+
+impl SkbIcmpEvent {
+    /// Returns the name of the ICMP type, if known.
+    pub fn type_str(&self) -> Option<&'static str> {
+        Some(match self.r#type {
+            0 => "echo reply",
+            3 => "destination unreachable",
+            5 => "redirect",
+            8 => "echo request",
+            9 => "router advertisement",
+            10 => "router solicitation",
+            11 => "time exceeded",
+            12 => "parameter problem",
+            13 => "timestamp request",
+            14 => "timestamp reply",
+            _ => return None,
+        })
+    }
+
+    /// Returns the name of the ICMP code, if known. Codes of types having a
+    /// single one are not named.
+    pub fn code_str(&self) -> Option<&'static str> {
+        Some(match (self.r#type, self.code) {
+            (3, 0) => "net unreachable",
+            (3, 1) => "host unreachable",
+            (3, 2) => "protocol unreachable",
+            (3, 3) => "port unreachable",
+            (3, 4) => "fragmentation needed",
+            (3, 5) => "source route failed",
+            (3, 6) => "net unknown",
+            (3, 7) => "host unknown",
+            (3, 9) => "net prohibited",
+            (3, 10) => "host prohibited",
+            (3, 13) => "admin prohibited",
+            (5, 0) => "net",
+            (5, 1) => "host",
+            (11, 0) => "ttl exceeded in transit",
+            (11, 1) => "fragment reassembly time exceeded",
+            _ => return None,
+        })
+    }
 }
 
 /// ICMPv6 fields.
@@ -458,6 +988,773 @@ pub struct SkbIcmpEvent {
 pub struct SkbIcmpV6Event {
     pub r#type: u8,
     pub code: u8,
+    /// MTU, for packet too big errors.
+    pub mtu: Option<u32>,
+    /// Target address, for neighbor solicitations & advertisements and
+    /// redirects.
+    pub target: Option<String>,
+    /// Original packet the error is about, for error messages.
+    pub orig: Option<SkbIcmpOrigEvent>,
+}
+
+impl SkbIcmpV6Event {
+    /// Returns the name of the ICMPv6 type, if known.
+    pub fn type_str(&self) -> Option<&'static str> {
+        Some(match self.r#type {
+            1 => "destination unreachable",
+            2 => "packet too big",
+            3 => "time exceeded",
+            4 => "parameter problem",
+            128 => "echo request",
+            129 => "echo reply",
+            130 => "multicast listener query",
+            131 => "multicast listener report",
+            132 => "multicast listener done",
+            133 => "router solicitation",
+            134 => "router advertisement",
+            135 => "neighbor solicitation",
+            136 => "neighbor advertisement",
+            137 => "redirect",
+            143 => "multicast listener report v2",
+            _ => return None,
+        })
+    }
+
+    /// Returns the name of the ICMPv6 code, if known. Codes of types having a
+    /// single one are not named.
+    pub fn code_str(&self) -> Option<&'static str> {
+        Some(match (self.r#type, self.code) {
+            (1, 0) => "no route",
+            (1, 1) => "admin prohibited",
+            (1, 2) => "beyond scope",
+            (1, 3) => "address unreachable",
+            (1, 4) => "port unreachable",
+            (1, 5) => "source address failed policy",
+            (1, 6) => "reject route",
+            (3, 0) => "hop limit exceeded in transit",
+            (3, 1) => "fragment reassembly time exceeded",
+            (4, 0) => "erroneous header field",
+            (4, 1) => "unrecognized next header",
+            (4, 2) => "unrecognized option",
+            _ => return None,
+        })
+    }
+}
+
+/// Headers of the original packet embedded in ICMP and ICMPv6 error messages.
+#[event_type]
+pub struct SkbIcmpOrigEvent {
+    /// IPv4 or IPv6 fields.
+    pub ip: SkbIpEvent,
+    /// Source port, for protocols having one.
+    pub sport: Option<u16>,
+    /// Destination port, for protocols having one.
+    pub dport: Option<u16>,
+}
+
+impl SkbIcmpOrigEvent {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match (self.sport, self.dport) {
+            (Some(sport), Some(dport)) => write!(
+                f,
+                " [orig {}.{} > {}.{}",
+                self.ip.saddr, sport, self.ip.daddr, dport
+            )?,
+            _ => write!(f, " [orig {} > {}", self.ip.saddr, self.ip.daddr)?,
+        }
+
+        if let Some(proto) = protocol_str(self.ip.protocol) {
+            write!(f, " proto {}", proto)?;
+        }
+        write!(f, " ({})]", self.ip.protocol)
+    }
+}
+
+/// SCTP fields.
+#[event_type]
+pub struct SkbSctpEvent {
+    /// Source port.
+    pub sport: u16,
+    /// Destination port.
+    pub dport: u16,
+    /// Verification tag.
+    pub vtag: u32,
+    /// Types of the chunks found in the packet, in order.
+    pub chunks: Vec<u8>,
+}
+
+impl SkbSctpEvent {
+    /// Returns the name of an SCTP chunk type, if known.
+    pub fn chunk_str(r#type: u8) -> Option<&'static str> {
+        Some(match r#type {
+            0 => "DATA",
+            1 => "INIT",
+            2 => "INIT_ACK",
+            3 => "SACK",
+            4 => "HEARTBEAT",
+            5 => "HEARTBEAT_ACK",
+            6 => "ABORT",
+            7 => "SHUTDOWN",
+            8 => "SHUTDOWN_ACK",
+            9 => "ERROR",
+            10 => "COOKIE_ECHO",
+            11 => "COOKIE_ACK",
+            12 => "ECNE",
+            13 => "CWR",
+            14 => "SHUTDOWN_COMPLETE",
+            15 => "AUTH",
+            128 => "ASCONF_ACK",
+            130 => "RE_CONFIG",
+            132 => "PAD",
+            192 => "FORWARD_TSN",
+            193 => "ASCONF",
+            _ => return None,
+        })
+    }
+}
+
+/// DCCP fields.
+#[event_type]
+pub struct SkbDccpEvent {
+    /// Source port.
+    pub sport: u16,
+    /// Destination port.
+    pub dport: u16,
+    /// Packet type.
+    pub r#type: u8,
+    /// Sequence number (24 or 48 bits).
+    pub seq: u64,
+}
+
+impl SkbDccpEvent {
+    /// Returns the name of a DCCP packet type, if known.
+    pub fn type_str(r#type: u8) -> Option<&'static str> {
+        Some(match r#type {
+            0 => "request",
+            1 => "response",
+            2 => "data",
+            3 => "ack",
+            4 => "dataack",
+            5 => "closereq",
+            6 => "close",
+            7 => "reset",
+            8 => "sync",
+            9 => "syncack",
+            _ => return None,
+        })
+    }
+}
+
+/// GRE fields.
+#[event_type]
+pub struct SkbGreEvent {
+    /// Flags, from the first byte of the header (C, R, K, S, s and recursion
+    /// control).
+    pub flags: u8,
+    /// Version.
+    pub version: u8,
+    /// Protocol type (ethertype) of the payload.
+    pub protocol: u16,
+    /// Key, if present.
+    pub key: Option<u32>,
+    /// Sequence number, if present.
+    pub seq: Option<u32>,
+}
+
+/// Encapsulation type.
+#[event_type]
+#[serde(rename_all = "snake_case")]
+pub enum SkbEncapType {
+    /// VXLAN (UDP port 4789).
+    Vxlan,
+    /// Geneve (UDP port 6081).
+    Geneve,
+    /// GRE, including Ethernet over GRE.
+    Gre,
+    /// IP in IP (IPv4 or IPv6, in IPv4 or IPv6).
+    Ipip,
+}
+
+impl SkbEncapType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SkbEncapType::Vxlan => "vxlan",
+            SkbEncapType::Geneve => "geneve",
+            SkbEncapType::Gre => "gre",
+            SkbEncapType::Ipip => "ipip",
+        }
+    }
+}
+
+/// Encapsulated (inner) packet fields.
+#[event_type]
+pub struct SkbInnerEvent {
+    /// Encapsulation type.
+    pub encap: SkbEncapType,
+    /// VXLAN or Geneve network identifier.
+    pub vni: Option<u32>,
+    /// GRE key, if any.
+    pub key: Option<u32>,
+    /// Inner Ethernet fields, if any.
+    pub eth: Option<SkbEthEvent>,
+    /// Inner VLAN tag fields, if any.
+    pub vlan: Option<SkbVlanEvent>,
+    /// Inner stacked VLAN tags, outermost first, if the inner packet has more
+    /// than one tag.
+    pub vlans: Option<Vec<SkbVlanEvent>>,
+    /// Inner MPLS label stack, outermost first, if any.
+    pub mpls: Option<Vec<SkbMplsEvent>>,
+    /// Inner IPv4 or IPv6 fields, if any.
+    pub ip: Option<SkbIpEvent>,
+    /// Inner TCP fields, if any.
+    pub tcp: Option<SkbTcpEvent>,
+    /// Inner UDP fields, if any.
+    pub udp: Option<SkbUdpEvent>,
+    /// Inner ICMP fields, if any.
+    pub icmp: Option<SkbIcmpEvent>,
+    /// Inner ICMPv6 fields, if any.
+    pub icmpv6: Option<SkbIcmpV6Event>,
+    /// Inner SCTP fields, if any.
+    pub sctp: Option<SkbSctpEvent>,
+    /// Inner DCCP fields, if any.
+    pub dccp: Option<SkbDccpEvent>,
+}
+
+impl SkbInnerEvent {
+    /// Inner L4 source and destination ports, for protocols having them.
+    pub fn ports(&self) -> Option<(u16, u16)> {
+        self.headers().ports()
+    }
+
+    fn headers(&self) -> PacketHeaders<'_> {
+        PacketHeaders {
+            eth: self.eth.as_ref(),
+            vlan: self.vlan.as_ref(),
+            vlans: self.vlans.as_deref(),
+            mpls: self.mpls.as_deref(),
+            arp: None,
+            ip: self.ip.as_ref(),
+            tcp: self.tcp.as_ref(),
+            udp: self.udp.as_ref(),
+            icmp: self.icmp.as_ref(),
+            icmpv6: self.icmpv6.as_ref(),
+            sctp: self.sctp.as_ref(),
+            dccp: self.dccp.as_ref(),
+            gre: None,
+        }
+    }
+}
+
+/// Application layer fields, decoded from the L4 payload of the raw packet.
+#[event_type]
+#[derive(Default)]
+pub struct SkbAppEvent {
+    /// DNS fields, if any.
+    pub dns: Option<SkbDnsEvent>,
+    /// DHCP fields, if any.
+    pub dhcp: Option<SkbDhcpEvent>,
+    /// HTTP/1 request or status line fields, if any.
+    pub http: Option<SkbHttpEvent>,
+}
+
+impl SkbAppEvent {
+    /// Decodes the application layer data of a raw packet, starting at its
+    /// Ethernet header. DNS is detected on ports 53 and 5353 (TCP or UDP),
+    /// DHCP on UDP ports 67 and 68 and HTTP/1 on any TCP port.
+    pub fn from_packet(packet: &[u8]) -> Option<Self> {
+        let (protocol, sport, dport, data) = l4_payload(packet)?;
+        if data.is_empty() {
+            return None;
+        }
+
+        let port = |p| sport == p || dport == p;
+        let mut app = Self::default();
+
+        if port(53) || port(5353) {
+            // DNS messages over TCP are prefixed with their length.
+            app.dns = match protocol {
+                6 => SkbDnsEvent::parse(data.get(2..)?),
+                _ => SkbDnsEvent::parse(data),
+            };
+        } else if protocol == 17 && (port(67) || port(68)) {
+            app.dhcp = SkbDhcpEvent::parse(data);
+        } else if protocol == 6 {
+            app.http = SkbHttpEvent::parse(data);
+        }
+
+        (app.dns.is_some() || app.dhcp.is_some() || app.http.is_some()).then_some(app)
+    }
+
+    fn fmt(&self, f: &mut Formatter, space: &mut DelimWriter) -> fmt::Result {
+        if let Some(dns) = &self.dns {
+            space.write(f)?;
+            dns.fmt(f)?;
+        }
+
+        if let Some(dhcp) = &self.dhcp {
+            space.write(f)?;
+            dhcp.fmt(f)?;
+        }
+
+        if let Some(http) = &self.http {
+            space.write(f)?;
+            http.fmt(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the L4 protocol, the source and destination ports and the payload of
+/// TCP and UDP packets.
+fn l4_payload(packet: &[u8]) -> Option<(u8, u16, u16, &[u8])> {
+    let be16 = |off: usize| {
+        packet
+            .get(off..off + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+    };
+
+    let mut etype = be16(12)?;
+    let mut off = 14;
+
+    // VLAN tags.
+    while matches!(etype, 0x8100 | 0x88a8 | 0x9100) {
+        etype = be16(off + 2)?;
+        off += 4;
+    }
+
+    // MPLS label stack, the IP version is guessed after its bottom.
+    if matches!(etype, 0x8847 | 0x8848) {
+        loop {
+            let bos = packet.get(off + 2)? & 1 != 0;
+            off += 4;
+            if bos {
+                break;
+            }
+        }
+
+        etype = match packet.get(off)? >> 4 {
+            4 => 0x0800,
+            6 => 0x86dd,
+            _ => return None,
+        };
+    }
+
+    // The end of the IP packet is tracked to strip the Ethernet padding, if
+    // any. A zero length (e.g. TSO, jumbograms) means it is unknown.
+    let ip_end = |len: u16, hlen: usize| match len {
+        0 => packet.len(),
+        len => (off + hlen + len as usize).min(packet.len()),
+    };
+
+    let (protocol, off, end) = match etype {
+        0x0800 => {
+            let ip = packet.get(off..off + 20)?;
+            // Non-first fragments do not hold the L4 header.
+            if u16::from_be_bytes([ip[6], ip[7]]) & 0x1fff != 0 {
+                return None;
+            }
+            let end = ip_end(u16::from_be_bytes([ip[2], ip[3]]), 0);
+            (ip[9], off + (ip[0] & 0xf) as usize * 4, end)
+        }
+        0x86dd => {
+            let end = ip_end(be16(off + 4)?, 40);
+            let mut next = *packet.get(off + 6)?;
+            let mut off = off + 40;
+
+            loop {
+                match next {
+                    // Hop-by-hop, routing and destination options.
+                    0 | 43 | 60 => {
+                        let len = *packet.get(off + 1)? as usize;
+                        next = *packet.get(off)?;
+                        off += (len + 1) * 8;
+                    }
+                    // Fragment, non-first fragments do not hold the L4 header.
+                    44 => {
+                        if be16(off + 2)? & 0xfff8 != 0 {
+                            return None;
+                        }
+                        next = *packet.get(off)?;
+                        off += 8;
+                    }
+                    // Authentication header.
+                    51 => {
+                        let len = *packet.get(off + 1)? as usize;
+                        next = *packet.get(off)?;
+                        off += (len + 2) * 4;
+                    }
+                    _ => break,
+                }
+            }
+
+            (next, off, end)
+        }
+        _ => return None,
+    };
+
+    let (sport, dport) = (be16(off)?, be16(off + 2)?);
+    match protocol {
+        6 => {
+            let doff = (packet.get(off + 12)? >> 4) as usize * 4;
+            Some((protocol, sport, dport, packet.get(off + doff..end)?))
+        }
+        17 => {
+            let len = (be16(off + 4)? as usize).saturating_sub(8);
+            let data = packet.get(off + 8..end)?;
+            Some((protocol, sport, dport, &data[..len.min(data.len())]))
+        }
+        _ => None,
+    }
+}
+
+/// DNS message fields.
+#[event_type]
+#[derive(Default)]
+pub struct SkbDnsEvent {
+    /// Transaction ID.
+    pub id: u16,
+    /// Flags, including the opcode and response code.
+    pub flags: u16,
+    /// Name of the first question, if any.
+    pub qname: Option<String>,
+    /// Type of the first question, if any.
+    pub qtype: Option<u16>,
+    /// Number of answers.
+    pub ancount: u16,
+}
+
+impl SkbDnsEvent {
+    /// Is the message a response?
+    pub fn is_response(&self) -> bool {
+        self.flags & 0x8000 != 0
+    }
+
+    /// Operation code.
+    pub fn opcode(&self) -> u8 {
+        ((self.flags >> 11) & 0xf) as u8
+    }
+
+    /// Response code.
+    pub fn rcode(&self) -> u8 {
+        (self.flags & 0xf) as u8
+    }
+
+    /// Returns a translation of some query types into a readable format.
+    pub fn qtype_str(qtype: u16) -> Option<&'static str> {
+        Some(match qtype {
+            1 => "A",
+            2 => "NS",
+            5 => "CNAME",
+            6 => "SOA",
+            12 => "PTR",
+            15 => "MX",
+            16 => "TXT",
+            28 => "AAAA",
+            33 => "SRV",
+            35 => "NAPTR",
+            41 => "OPT",
+            43 => "DS",
+            46 => "RRSIG",
+            48 => "DNSKEY",
+            64 => "SVCB",
+            65 => "HTTPS",
+            255 => "ANY",
+            257 => "CAA",
+            _ => return None,
+        })
+    }
+
+    /// Returns a translation of some response codes into a readable format.
+    pub fn rcode_str(rcode: u8) -> Option<&'static str> {
+        Some(match rcode {
+            0 => "NoError",
+            1 => "FormErr",
+            2 => "ServFail",
+            3 => "NXDomain",
+            4 => "NotImp",
+            5 => "Refused",
+            _ => return None,
+        })
+    }
+
+    fn parse(data: &[u8]) -> Option<Self> {
+        let hdr = data.get(..12)?;
+        let be16 = |off: usize| u16::from_be_bytes([hdr[off], hdr[off + 1]]);
+
+        let mut dns = Self {
+            id: be16(0),
+            flags: be16(2),
+            ancount: be16(6),
+            ..Default::default()
+        };
+
+        if be16(4) > 0 {
+            if let Some((qname, end)) = Self::parse_name(data, 12) {
+                dns.qname = Some(qname);
+                dns.qtype = data
+                    .get(end..end + 2)
+                    .map(|b| u16::from_be_bytes([b[0], b[1]]));
+            }
+        }
+
+        Some(dns)
+    }
+
+    /// Parses a domain name starting at `off`, following compression pointers.
+    /// Returns the name and the offset following it in the message.
+    fn parse_name(data: &[u8], mut off: usize) -> Option<(String, usize)> {
+        let mut name = String::new();
+        let mut end = None;
+        let mut jumps = 0;
+
+        loop {
+            let len = *data.get(off)? as usize;
+            match len {
+                0 => break,
+                // Compression pointer.
+                l if l & 0xc0 == 0xc0 => {
+                    jumps += 1;
+                    if jumps > 16 {
+                        return None;
+                    }
+                    end.get_or_insert(off + 2);
+                    off = ((l & 0x3f) << 8) | *data.get(off + 1)? as usize;
+                }
+                l if l & 0xc0 != 0 => return None,
+                l => {
+                    data.get(off + 1..off + 1 + l)?.iter().for_each(|c| {
+                        if c.is_ascii_graphic() && *c != b'.' && *c != b'\\' {
+                            name.push(*c as char);
+                        } else {
+                            name.push_str(&format!("\\{c:03}"));
+                        }
+                    });
+                    name.push('.');
+                    off += l + 1;
+                }
+            }
+        }
+
+        if name.is_empty() {
+            name.push('.');
+        }
+        Some((name, end.unwrap_or(off + 1)))
+    }
+
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "dns id {:#06x}", self.id)?;
+
+        let flags: Vec<_> = [
+            (0x8000, "qr"),
+            (0x0400, "aa"),
+            (0x0200, "tc"),
+            (0x0100, "rd"),
+            (0x0080, "ra"),
+            (0x0020, "ad"),
+            (0x0010, "cd"),
+        ]
+        .iter()
+        .filter(|(bit, _)| self.flags & bit != 0)
+        .map(|(_, name)| *name)
+        .collect();
+        if !flags.is_empty() {
+            write!(f, " [{}]", flags.join(","))?;
+        }
+
+        if self.opcode() != 0 {
+            write!(f, " opcode {}", self.opcode())?;
+        }
+
+        if let Some(qname) = &self.qname {
+            match self.qtype {
+                Some(qtype) => match Self::qtype_str(qtype) {
+                    Some(name) => write!(f, " {name}?")?,
+                    None => write!(f, " type{qtype}?")?,
+                },
+                None => write!(f, " ?")?,
+            }
+            write!(f, " {qname}")?;
+        }
+
+        if self.is_response() {
+            write!(f, " an {}", self.ancount)?;
+
+            let rcode = self.rcode();
+            if rcode != 0 {
+                match Self::rcode_str(rcode) {
+                    Some(name) => write!(f, " {name}")?,
+                    None => write!(f, " rcode {rcode}")?,
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// DHCP (and BOOTP) message fields.
+#[event_type]
+#[derive(Default)]
+pub struct SkbDhcpEvent {
+    /// Operation, 1 for requests and 2 for replies.
+    pub op: u8,
+    /// Transaction ID.
+    pub xid: u32,
+    /// DHCP message type (option 53), if any.
+    pub msg_type: Option<u8>,
+}
+
+impl SkbDhcpEvent {
+    /// Returns a translation of the DHCP message types into a readable format.
+    pub fn msg_type_str(msg_type: u8) -> Option<&'static str> {
+        Some(match msg_type {
+            1 => "discover",
+            2 => "offer",
+            3 => "request",
+            4 => "decline",
+            5 => "ack",
+            6 => "nak",
+            7 => "release",
+            8 => "inform",
+            _ => return None,
+        })
+    }
+
+    fn parse(data: &[u8]) -> Option<Self> {
+        // Fixed part of the BOOTP message, up to the options.
+        let hdr = data.get(..236)?;
+        if !matches!(hdr[0], 1 | 2) {
+            return None;
+        }
+
+        let mut dhcp = Self {
+            op: hdr[0],
+            xid: u32::from_be_bytes([hdr[4], hdr[5], hdr[6], hdr[7]]),
+            ..Default::default()
+        };
+
+        // DHCP options follow the magic cookie.
+        if data.get(236..240) == Some(&[99, 130, 83, 99]) {
+            let mut off = 240;
+            while let Some(&code) = data.get(off) {
+                match code {
+                    0 => off += 1,
+                    255 => break,
+                    _ => {
+                        let len = *data.get(off + 1)? as usize;
+                        if code == 53 && len == 1 {
+                            dhcp.msg_type = data.get(off + 2).copied();
+                            break;
+                        }
+                        off += len + 2;
+                    }
+                }
+            }
+        }
+
+        Some(dhcp)
+    }
+
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.op {
+            1 => write!(f, "dhcp request")?,
+            2 => write!(f, "dhcp reply")?,
+            op => write!(f, "dhcp op {op}")?,
+        }
+        write!(f, " xid {:#x}", self.xid)?;
+
+        if let Some(msg_type) = self.msg_type {
+            match Self::msg_type_str(msg_type) {
+                Some(name) => write!(f, " {name}")?,
+                None => write!(f, " type {msg_type}")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// HTTP/1 request or status line fields.
+#[event_type]
+#[derive(Default)]
+pub struct SkbHttpEvent {
+    /// Method, for requests.
+    pub method: Option<String>,
+    /// Request target, for requests.
+    pub uri: Option<String>,
+    /// Protocol version, e.g. `HTTP/1.1`.
+    pub version: String,
+    /// Status code, for responses.
+    pub status: Option<u16>,
+    /// Reason phrase, for responses.
+    pub reason: Option<String>,
+}
+
+impl SkbHttpEvent {
+    const METHODS: [&'static str; 9] = [
+        "GET", "HEAD", "POST", "PUT", "DELETE", "CONNECT", "OPTIONS", "TRACE", "PATCH",
+    ];
+
+    fn parse(data: &[u8]) -> Option<Self> {
+        // Only consider complete lines.
+        let end = data.windows(2).position(|w| w == b"\r\n")?;
+        let line = std::str::from_utf8(&data[..end]).ok()?;
+        if !line.chars().all(|c| c.is_ascii() && !c.is_ascii_control()) {
+            return None;
+        }
+
+        let is_version = |v: &str| v.starts_with("HTTP/1.");
+
+        // Status line: version, status code and reason phrase.
+        if is_version(line) {
+            let mut parts = line.splitn(3, ' ');
+            let version = parts.next()?;
+            let status = parts.next()?;
+            if status.len() != 3 {
+                return None;
+            }
+
+            return Some(Self {
+                version: version.to_string(),
+                status: Some(status.parse().ok()?),
+                reason: parts.next().map(String::from),
+                ..Default::default()
+            });
+        }
+
+        // Request line: method, target and version.
+        let mut parts = line.split(' ');
+        let (method, uri, version) = (parts.next()?, parts.next()?, parts.next()?);
+        if parts.next().is_some() || !is_version(version) || !Self::METHODS.contains(&method) {
+            return None;
+        }
+
+        Some(Self {
+            method: Some(method.to_string()),
+            uri: Some(uri.to_string()),
+            version: version.to_string(),
+            ..Default::default()
+        })
+    }
+
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "http")?;
+
+        if let (Some(method), Some(uri)) = (&self.method, &self.uri) {
+            write!(f, " {method} {uri} {}", self.version)?;
+        } else {
+            write!(f, " {}", self.version)?;
+            if let Some(status) = self.status {
+                write!(f, " {status}")?;
+            }
+            if let Some(reason) = &self.reason {
+                write!(f, " {reason}")?;
+            }
+        }
+
+        Ok(())
+    }
 }
 
 /// Network device fields.
@@ -540,3 +1837,183 @@ pub struct SkbPacketEvent {
     /// Raw packet data.
     pub packet: RawPacket,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Builds an Ethernet + IPv4 + TCP/UDP packet around the given payload.
+    fn packet(protocol: u8, sport: u16, dport: u16, payload: &[u8]) -> Vec<u8> {
+        let mut l4 = Vec::new();
+        l4.extend_from_slice(&sport.to_be_bytes());
+        l4.extend_from_slice(&dport.to_be_bytes());
+        match protocol {
+            6 => {
+                l4.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 0, 0x50, 0x18, 0xff, 0xff, 0, 0, 0, 0])
+            }
+            _ => {
+                l4.extend_from_slice(&((payload.len() + 8) as u16).to_be_bytes());
+                l4.extend_from_slice(&[0, 0]);
+            }
+        }
+        l4.extend_from_slice(payload);
+
+        let mut pkt = vec![0; 12];
+        pkt.extend_from_slice(&[0x08, 0x00, 0x45, 0]);
+        pkt.extend_from_slice(&((l4.len() + 20) as u16).to_be_bytes());
+        pkt.extend_from_slice(&[0, 0, 0x40, 0, 64, protocol, 0, 0]);
+        pkt.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2]);
+        pkt.extend_from_slice(&l4);
+        // Ethernet padding.
+        pkt.extend_from_slice(&[0; 4]);
+        pkt
+    }
+
+    // Displays an skb event holding the given raw packet, and thus only its
+    // application layer data.
+    fn display(packet: Vec<u8>) -> String {
+        let mut skb = SkbEvent {
+            packet: Some(SkbPacketEvent {
+                len: packet.len() as u32,
+                capture_len: packet.len() as u32,
+                packet: RawPacket(packet),
+            }),
+            ..Default::default()
+        };
+        skb.decode_app();
+        format!(
+            "{}",
+            skb.display(&DisplayFormat::new(), &FormatterConf::new())
+        )
+    }
+
+    #[test]
+    fn tcp_options() {
+        let mut tcp = SkbTcpEvent {
+            sport: 1234,
+            dport: 443,
+            seq: 1,
+            ack_seq: 0,
+            window: 65535,
+            doff: 10,
+            flags: 1 << 1,
+            mss: Some(1460),
+            wscale: Some(7),
+            sack_permitted: true,
+            sack: Some(vec![SkbTcpSackBlock {
+                left: 10,
+                right: 20,
+            }]),
+            tsval: Some(42),
+            tsecr: Some(0),
+            options: Some(vec![2, 4, 8, 3, 5]),
+        };
+        let display = |tcp: &SkbTcpEvent| {
+            let skb = SkbEvent {
+                tcp: Some(tcp.clone()),
+                ..Default::default()
+            };
+            format!(
+                "{}",
+                skb.display(&DisplayFormat::new(), &FormatterConf::new())
+            )
+        };
+        assert_eq!(
+            display(&tcp),
+            "flags [S] seq 1 win 65535 options [mss 1460,sackOK,TS val 42 ecr 0,wscale 7,sack 1 {10:20}]"
+        );
+
+        // Options are displayed in the order they appear in the packet.
+        tcp.options = Some(vec![2, 3, 5, 4, 8]);
+        assert_eq!(
+            display(&tcp),
+            "flags [S] seq 1 win 65535 options [mss 1460,wscale 7,sack 1 {10:20},sackOK,TS val 42 ecr 0]"
+        );
+
+        // SACK permitted is only serialized when set.
+        tcp.sack_permitted = false;
+        assert!(serde_json::to_value(&tcp)
+            .unwrap()
+            .get("sack_permitted")
+            .is_none());
+    }
+
+    #[test]
+    fn app_dns() {
+        let query = [
+            0x12, 0x34, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0, 7, b'e', b'x', b'a', b'm', b'p', b'l',
+            b'e', 3, b'c', b'o', b'm', 0, 0, 28, 0, 1,
+        ];
+        let pkt = packet(17, 43210, 53, &query);
+        let app = SkbAppEvent::from_packet(&pkt).unwrap();
+        let dns = app.dns.as_ref().unwrap();
+        assert_eq!(dns.id, 0x1234);
+        assert_eq!(dns.qname.as_deref(), Some("example.com."));
+        assert_eq!(dns.qtype, Some(28));
+        assert!(!dns.is_response());
+        assert_eq!(display(pkt), "dns id 0x1234 [rd] AAAA? example.com.");
+
+        // Response with a compressed name in the question and over TCP.
+        let mut response = vec![0, 33, 0x12, 0x34, 0x81, 0x83, 0, 1, 0, 0, 0, 0, 0, 0];
+        response.extend_from_slice(&[0xc0, 18, 0, 1, 0, 1]);
+        response.extend_from_slice(&[3, b'f', b'o', b'o', 0]);
+        let pkt = packet(6, 53, 43210, &response);
+        let app = SkbAppEvent::from_packet(&pkt).unwrap();
+        let dns = app.dns.as_ref().unwrap();
+        assert_eq!(dns.qname.as_deref(), Some("foo."));
+        assert_eq!(dns.rcode(), 3);
+        assert_eq!(
+            display(pkt),
+            "dns id 0x1234 [qr,rd,ra] A? foo. an 0 NXDomain"
+        );
+    }
+
+    #[test]
+    fn app_dhcp() {
+        let mut msg = vec![0; 240];
+        msg[0] = 1;
+        msg[4..8].copy_from_slice(&0xdeadbeef_u32.to_be_bytes());
+        msg[236..240].copy_from_slice(&[99, 130, 83, 99]);
+        msg.extend_from_slice(&[0, 61, 2, 1, 2, 53, 1, 1, 255]);
+
+        let pkt = packet(17, 68, 67, &msg);
+        let app = SkbAppEvent::from_packet(&pkt).unwrap();
+        let dhcp = app.dhcp.as_ref().unwrap();
+        assert_eq!(dhcp.xid, 0xdeadbeef);
+        assert_eq!(dhcp.msg_type, Some(1));
+        assert_eq!(display(pkt), "dhcp request xid 0xdeadbeef discover");
+
+        // Too short for a BOOTP message.
+        assert!(SkbAppEvent::from_packet(&packet(17, 68, 67, &msg[..100])).is_none());
+    }
+
+    #[test]
+    fn app_http() {
+        let pkt = packet(
+            6,
+            43210,
+            8080,
+            b"GET /index.html HTTP/1.1\r\nHost: foo\r\n\r\n",
+        );
+        let app = SkbAppEvent::from_packet(&pkt).unwrap();
+        let http = app.http.as_ref().unwrap();
+        assert_eq!(http.method.as_deref(), Some("GET"));
+        assert_eq!(http.uri.as_deref(), Some("/index.html"));
+        assert_eq!(display(pkt), "http GET /index.html HTTP/1.1");
+
+        let pkt = packet(6, 80, 43210, b"HTTP/1.1 404 Not Found\r\n");
+        let app = SkbAppEvent::from_packet(&pkt).unwrap();
+        assert_eq!(app.http.as_ref().unwrap().status, Some(404));
+        assert_eq!(display(pkt), "http HTTP/1.1 404 Not Found");
+
+        // Data past the end of the IP packet (Ethernet padding) is ignored.
+        let mut pkt = packet(6, 43210, 80, b"");
+        pkt.truncate(pkt.len() - 4);
+        pkt.extend_from_slice(b"GET / HTTP/1.1\r\n");
+        assert!(SkbAppEvent::from_packet(&pkt).is_none());
+
+        // Not HTTP, or incomplete lines.
+        assert!(SkbAppEvent::from_packet(&packet(6, 80, 43210, b"\x16\x03\x01\x02\x00")).is_none());
+        assert!(SkbAppEvent::from_packet(&packet(6, 43210, 80, b"GET /index.ht")).is_none());
+    }
+}
@@ -74,6 +74,7 @@ pub(super) fn build_raw_event() -> Result<Vec<u8>> {
         data: event
             .try_into()
             .expect("Could not convert event Vec to [u8]"),
+        tail: RawEventTail::default(),
     };
 
     // And convert it to a Vec<u8>.
@@ -13,11 +13,13 @@ pub const SECTION_NS: skb_sections = 4;
 pub const SECTION_META: skb_sections = 5;
 pub const SECTION_DATA_REF: skb_sections = 6;
 pub const SECTION_GSO: skb_sections = 7;
+pub const SECTION_PACKET_DATA: skb_sections = 8;
 pub type skb_sections = ::std::os::raw::c_uint;
 #[repr(C)]
 #[derive(Debug, Default, Copy, Clone)]
 pub struct skb_config {
     pub sections: u64_,
+    pub capture_len: u32_,
 }
 pub const IFNAMSIZ: enum_IFNAMSIZ = 16;
 pub type enum_IFNAMSIZ = ::std::os::raw::c_uint;
@@ -62,20 +64,12 @@ pub struct skb_gso_event {
     pub gso_segs: u32_,
     pub gso_type: u32_,
 }
+pub const PACKET_CAPTURE_MAX: enum_PACKET_CAPTURE_MAX = 2048;
+pub type enum_PACKET_CAPTURE_MAX = ::std::os::raw::c_uint;
 #[repr(C)]
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Default, Copy, Clone)]
 pub struct skb_packet_event {
     pub len: u32_,
     pub capture_len: u32_,
-    pub packet: [u8_; 255usize],
     pub fake_eth: u8_,
 }
-impl Default for skb_packet_event {
-    fn default() -> Self {
-        let mut s = ::std::mem::MaybeUninit::<Self>::uninit();
-        unsafe {
-            ::std::ptr::write_bytes(s.as_mut_ptr(), 0, 1);
-            s.assume_init()
-        }
-    }
-}
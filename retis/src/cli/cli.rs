@@ -194,6 +194,15 @@ impl RetisCli {
         #[cfg(feature = "python")]
         cli.add_subcommand(Box::new(PythonCli::new()?))?;
         cli.add_subcommand(Box::new(Pcap::new()?))?;
+        cli.add_subcommand(Box::new(Stats::new()?))?;
+        cli.add_subcommand(Box::new(Latency::new()?))?;
+        cli.add_subcommand(Box::new(Diff::new()?))?;
+        cli.add_subcommand(Box::new(Merge::new()?))?;
+        cli.add_subcommand(Box::new(Graph::new()?))?;
+        cli.add_subcommand(Box::new(Export::new()?))?;
+        cli.add_subcommand(Box::new(Import::new()?))?;
+        cli.add_subcommand(Box::new(Anonymize::new()?))?;
+        cli.add_subcommand(Box::new(Flows::new()?))?;
         cli.add_subcommand(Box::new(Inspect::new()?))?;
         cli.add_subcommand(Box::new(ProfileCmd::new()?))?;
         cli.add_subcommand(Box::new(Complete::new()?))?;
@@ -384,4 +393,14 @@ pub(crate) enum CliDisplayFormat {
     SingleLine,
     #[default]
     MultiLine,
+    Table,
+}
+
+/// Type of the "format" argument of commands reporting aggregated data (e.g.
+/// statistics).
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, ValueEnum)]
+pub(crate) enum ReportFormat {
+    #[default]
+    Text,
+    Json,
 }
@@ -2,13 +2,23 @@
 //!
 //! Collect is a dynamic CLI subcommand that allows collectors to register their arguments.
 
-use std::path::PathBuf;
+use std::{path::PathBuf, time::Duration};
 
 use anyhow::Result;
 use clap::{builder::PossibleValuesParser, Parser};
 
 use super::Collectors;
-use crate::{cli::*, collect::collector::*, core::inspect::init_inspector};
+use crate::{
+    cli::*,
+    collect::collector::*,
+    core::inspect::init_inspector,
+    events::TableColumn,
+    helpers::time::parse_duration,
+    process::{
+        socket::{SocketAddr, DEFAULT_SOCKET_BUFFER},
+        template::EventTemplate,
+    },
+};
 
 /// Collect events.
 ///
@@ -96,7 +106,25 @@ defaults to \"retis.data\"."
     pub(super) out: Option<PathBuf>,
     #[arg(
         long,
-        help = "Write the events to stdout even if --out is used.",
+        help = "Stream the events to readers connected to a socket. The same newline-delimited JSON
+as written by --out is used. The socket follows the TYPE:ADDRESS pattern, with TYPE being 'unix'
+or 'tcp'. Multiple readers can be connected at the same time.
+
+Examples:
+  --out-socket unix:/run/retis.sock
+  --out-socket tcp:127.0.0.1:9000"
+    )]
+    pub(super) out_socket: Option<SocketAddr>,
+    #[arg(
+        long,
+        default_value_t = DEFAULT_SOCKET_BUFFER,
+        help = "Maximum number of events buffered per socket reader. Events are dropped for readers
+not keeping up once their buffer is full."
+    )]
+    pub(super) out_socket_buffer: usize,
+    #[arg(
+        long,
+        help = "Write the events to stdout even if --out or --out-socket is used.",
         default_value = "false"
     )]
     pub(super) print: bool,
@@ -130,6 +158,25 @@ Notes:
         help = "Execute a command and terminate the collection once done."
     )]
     pub(super) cmd: Option<String>,
+    #[arg(
+        long,
+        value_parser = parse_duration,
+        help = "Terminate the collection after the given duration. The duration is an integer
+optionally followed by a unit: ms, s (default), m or h. E.g. \"30s\", \"5m\"."
+    )]
+    pub(super) duration: Option<Duration>,
+    #[arg(
+        long,
+        help = "Terminate the collection once the given number of events was collected."
+    )]
+    pub(super) max_events: Option<u64>,
+    #[arg(
+        long,
+        help = "Terminate the collection once the given number of distinct packets was seen.
+Packets are identified using their skb tracking id and thus this requires the 'skb-tracking'
+collector."
+    )]
+    pub(super) max_packets: Option<u64>,
     #[arg(
         long,
         default_value = "false",
@@ -164,6 +211,22 @@ fully operational:
     #[arg(long, help = "Format used when printing an event.")]
     #[clap(value_enum, default_value_t=CliDisplayFormat::MultiLine)]
     pub(super) format: CliDisplayFormat,
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = TableColumn::DEFAULT,
+        help = "Comma-separated list of columns printed when using the table format."
+    )]
+    pub(super) columns: Vec<TableColumn>,
+    #[arg(
+        long,
+        help = "Print events on a single line following a template referencing event fields, e.g.
+'{common.timestamp} {kernel.symbol} {skb.ip.saddr} {skb-drop.drop_reason?}'. Fields are
+referenced using their path in the JSON representation of events; missing fields are printed
+as '-' unless followed by '?'. Values can be formatted using ':hex', ':ifname' or ':delta'
+(e.g. '{common.timestamp:delta}'). Takes precedence over --format."
+    )]
+    pub(super) template: Option<EventTemplate>,
 
     /// Embed below all the per-collector arguments.
     #[command(flatten)]
@@ -6,7 +6,7 @@ use std::{
     io::{self, BufWriter},
     process::{Command, Stdio},
     sync::Arc,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use anyhow::{anyhow, bail, Result};
@@ -41,7 +41,7 @@ use crate::{
     },
     events::*,
     helpers::{signals::Running, time::*},
-    process::display::*,
+    process::{display::*, socket::SocketWriter},
 };
 
 #[cfg(not(test))]
@@ -301,6 +301,11 @@ impl Collectors {
             );
         }
 
+        // Counting packets relies on their skb tracking id.
+        if collect.max_packets.is_some() && !self.collectors.contains_key("skb-tracking") {
+            bail!("--max-packets requires the 'skb-tracking' collector");
+        }
+
         // Initialize tracking & filters.
         if !cfg!(test) && self.known_kernel_types.contains("struct sk_buff *") {
             let (gc, map) = init_tracking(self.probes.builder_mut()?)?;
@@ -487,9 +492,9 @@ impl Collectors {
     pub(super) fn process(&mut self, collect: &Collect) -> Result<()> {
         let mut printers = Vec::new();
 
-        // Write events to stdout if we don't write to a file (--out) or if
-        // explicitly asked to (--print).
-        if collect.out.is_none() || collect.print {
+        // Write events to stdout if we don't write to a file (--out) or a
+        // socket (--out-socket) or if explicitly asked to (--print).
+        if (collect.out.is_none() && collect.out_socket.is_none()) || collect.print {
             let format = DisplayFormat::new()
                 .multiline(collect.format == CliDisplayFormat::MultiLine)
                 .time_format(if collect.utc {
@@ -497,11 +502,19 @@ impl Collectors {
                 } else {
                     TimeFormat::MonotonicTimestamp
                 })
+                .table(if collect.format == CliDisplayFormat::Table {
+                    collect.columns.clone()
+                } else {
+                    Vec::new()
+                })
                 .monotonic_offset(monotonic_clock_offset()?);
 
             printers.push(PrintEvent::new(
                 Box::new(io::stdout()),
-                PrintEventFormat::Text(format),
+                match &collect.template {
+                    Some(template) => PrintEventFormat::Template(template.clone()),
+                    None => PrintEventFormat::Text(format),
+                },
             ));
         }
 
@@ -520,6 +533,14 @@ impl Collectors {
             ));
         }
 
+        // Stream the events to a socket if asked to.
+        if let Some(addr) = collect.out_socket.as_ref() {
+            printers.push(PrintEvent::new(
+                Box::new(SocketWriter::new(addr.clone(), collect.out_socket_buffer)?),
+                PrintEventFormat::Json,
+            ));
+        }
+
         if let Some(cmd) = collect.cmd.to_owned() {
             let run = self.run.clone();
             std::thread::spawn(move || {
@@ -547,8 +568,28 @@ impl Collectors {
             self.known_kernel_types.clone(),
         );
 
+        // Unique packets seen so far, only tracked if --max-packets is used.
+        let mut packets = HashSet::new();
+        let start = Instant::now();
+
         use EventResult::*;
         while self.run.running() {
+            // Do not wait for events past the collection deadline, if any.
+            let timeout = match collect.duration {
+                Some(duration) => match duration.checked_sub(start.elapsed()) {
+                    Some(remaining) => remaining.min(Duration::from_secs(1)),
+                    None => {
+                        info!(
+                            "Duration limit reached ({}s), terminating ...",
+                            duration.as_secs_f64()
+                        );
+                        self.run.terminate();
+                        break;
+                    }
+                },
+                None => Duration::from_secs(1),
+            };
+
             // First always try to dequeue all Retis events. This is not a
             // blocking call.
             while let Some(event) = self.events_factory.next_event() {
@@ -559,7 +600,7 @@ impl Collectors {
             }
 
             // Then get raw events, if any.
-            match self.factory.next_event(Some(Duration::from_secs(1)))? {
+            match self.factory.next_event(Some(timeout))? {
                 Event(mut event) => {
                     if collect.probe_stack {
                         probe_stack.process_event(self.probes.runtime_mut()?, &mut event)?;
@@ -569,6 +610,24 @@ impl Collectors {
                         .iter_mut()
                         .try_for_each(|p| p.process_one(&event))?;
                     eccount += 1;
+
+                    if collect.max_events.is_some_and(|max| eccount >= max) {
+                        info!("Event limit reached ({eccount}), terminating ...");
+                        self.run.terminate();
+                    }
+
+                    if let Some(max) = collect.max_packets {
+                        if let Some(track) =
+                            event.get_section::<SkbTrackingEvent>(SectionId::SkbTracking)
+                        {
+                            packets.insert(track.tracking_id());
+                        }
+
+                        if packets.len() as u64 >= max {
+                            info!("Packet limit reached ({}), terminating ...", packets.len());
+                            self.run.terminate();
+                        }
+                    }
                 }
                 Timeout => continue,
             }
@@ -576,6 +635,9 @@ impl Collectors {
 
         printers.iter_mut().try_for_each(|p| p.flush())?;
         info!("{} event(s) processed", eccount);
+        if collect.max_packets.is_some() {
+            info!("{} distinct packet(s) seen", packets.len());
+        }
         debug!("{} internal event(s) processed", iccount);
 
         self.stop()
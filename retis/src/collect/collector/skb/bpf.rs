@@ -5,7 +5,7 @@
 //! Please keep this file in sync with its BPF counterpart in bpf/skb_hook.bpf.c
 
 use anyhow::bail;
-use std::str;
+use std::{net::Ipv6Addr, str};
 
 use anyhow::{anyhow, Result};
 use pnet_packet::{
@@ -64,6 +64,8 @@ pub(super) fn unmarshal_ipv4(ip: &Ipv4Packet) -> Result<SkbIpEvent> {
                 flags: ip.get_flags(),
                 id: ip.get_identification(),
                 offset: ip.get_fragment_offset(),
+                ihl: Some(ip.get_header_length()),
+                options: unmarshal_ipv4_options(ip.get_options_raw())?,
             },
         },
         protocol: ip.get_next_level_protocol().0,
@@ -73,24 +75,212 @@ pub(super) fn unmarshal_ipv4(ip: &Ipv4Packet) -> Result<SkbIpEvent> {
     })
 }
 
-pub(super) fn unmarshal_ipv6(ip: &Ipv6Packet) -> Result<SkbIpEvent> {
-    Ok(SkbIpEvent {
-        saddr: ip.get_source().to_string(),
-        daddr: ip.get_destination().to_string(),
-        version: SkbIpVersion::V6 {
-            v6: SkbIpv6Event {
-                flow_label: ip.get_flow_label(),
+/// Parses IPv4 options. Returns None if the header has no option.
+fn unmarshal_ipv4_options(mut data: &[u8]) -> Result<Option<Vec<SkbIpv4Option>>> {
+    let mut options = Vec::new();
+
+    while let Some(&r#type) = data.first() {
+        match r#type {
+            // End of options list.
+            0 => break,
+            // No operation.
+            1 => {
+                data = &data[1..];
+                continue;
+            }
+            _ => (),
+        }
+
+        // Type (1B), length (1B), including both.
+        if data.len() < 2 || data[1] < 2 || data.len() < data[1] as usize {
+            break;
+        }
+        let (opt, rest) = data.split_at(data[1] as usize);
+        data = rest;
+
+        let mut option = SkbIpv4Option {
+            r#type,
+            len: opt[1],
+            addrs: None,
+            timestamps: None,
+            value: None,
+        };
+
+        match r#type {
+            // Pointer (1B, 1-based) to the next free slot, followed by the
+            // recorded addresses (4B).
+            SkbIpv4Option::RR if opt.len() >= 3 => {
+                let end = (opt[2] as usize).saturating_sub(1).clamp(3, opt.len());
+                option.addrs = Some(
+                    opt[3..end]
+                        .chunks_exact(4)
+                        .map(|a| {
+                            helpers::net::parse_ipv4_addr(u32::from_be_bytes([
+                                a[0], a[1], a[2], a[3],
+                            ]))
+                        })
+                        .collect::<Result<_>>()?,
+                );
+            }
+            // Pointer (1B, 1-based) to the next free slot, overflow (4b) and
+            // flags (4b), followed by timestamps (4B) prefixed by addresses
+            // (4B) depending on the flags.
+            SkbIpv4Option::TS if opt.len() >= 4 => {
+                let end = (opt[2] as usize).saturating_sub(1).clamp(4, opt.len());
+                let entries = &opt[4..end];
+                match opt[3] & 0xf {
+                    0 => {
+                        option.timestamps = Some(
+                            entries
+                                .chunks_exact(4)
+                                .map(|ts| u32::from_be_bytes([ts[0], ts[1], ts[2], ts[3]]))
+                                .collect(),
+                        );
+                    }
+                    1 | 3 => {
+                        let entries = entries.chunks_exact(8).collect::<Vec<_>>();
+                        option.addrs = Some(
+                            entries
+                                .iter()
+                                .map(|e| {
+                                    helpers::net::parse_ipv4_addr(u32::from_be_bytes([
+                                        e[0], e[1], e[2], e[3],
+                                    ]))
+                                })
+                                .collect::<Result<_>>()?,
+                        );
+                        option.timestamps = Some(
+                            entries
+                                .iter()
+                                .map(|e| u32::from_be_bytes([e[4], e[5], e[6], e[7]]))
+                                .collect(),
+                        );
+                    }
+                    _ => (),
+                }
+            }
+            // Value (2B).
+            SkbIpv4Option::RA if opt.len() >= 4 => {
+                option.value = Some(u16::from_be_bytes([opt[2], opt[3]]));
+            }
+            _ => (),
+        }
+
+        options.push(option);
+    }
+
+    Ok((!options.is_empty()).then_some(options))
+}
+
+/// Parses an IPv6 packet, walking its extension headers. Also returns the
+/// offset of the upper-layer header in the IPv6 payload, unless it can't be
+/// reached (truncated extension headers, non-first fragments).
+pub(super) fn unmarshal_ipv6(ip: &Ipv6Packet) -> Result<(SkbIpEvent, Option<usize>)> {
+    let payload = ip.payload();
+    let mut protocol = ip.get_next_header();
+    let mut offset = 0;
+    let mut ext_headers = Vec::new();
+    let mut frag = None;
+
+    let l4 = loop {
+        let r#type = match protocol {
+            IpNextHeaderProtocols::Hopopt => SkbIpv6ExtType::HopByHop,
+            IpNextHeaderProtocols::Ipv6Route => SkbIpv6ExtType::Routing,
+            IpNextHeaderProtocols::Ipv6Frag => SkbIpv6ExtType::Fragment,
+            IpNextHeaderProtocols::Ipv6Opts => SkbIpv6ExtType::DestOpts,
+            IpNextHeaderProtocols::Ah => SkbIpv6ExtType::Auth,
+            _ => break Some(offset),
+        };
+
+        // All extension headers start with the next header (1B) and, but for
+        // fragment ones, their length (1B).
+        let hdr = &payload[offset..];
+        if hdr.len() < 2 {
+            break None;
+        }
+        let len = match r#type {
+            SkbIpv6ExtType::Fragment => 8,
+            // AH length is in 4-byte units, minus 2.
+            SkbIpv6ExtType::Auth => (hdr[1] as usize + 2) * 4,
+            _ => (hdr[1] as usize + 1) * 8,
+        };
+        if hdr.len() < len {
+            break None;
+        }
+
+        let mut ext = SkbIpv6ExtHeader {
+            r#type,
+            len: len as u16,
+            routing_type: None,
+            segments_left: None,
+            segments: None,
+        };
+
+        match ext.r#type {
+            // Routing type (1B), segments left (1B). Segment routing headers
+            // (type 4) then have the last entry index (1B), flags (1B), tag
+            // (2B) and the segment list.
+            SkbIpv6ExtType::Routing => {
+                ext.routing_type = Some(hdr[2]);
+                ext.segments_left = Some(hdr[3]);
+                if hdr[2] == 4 {
+                    ext.segments = Some(
+                        hdr[8..len]
+                            .chunks_exact(16)
+                            .take(hdr[4] as usize + 1)
+                            .map(|seg| {
+                                let mut raw = [0; 16];
+                                raw.copy_from_slice(seg);
+                                Ipv6Addr::from(raw).to_string()
+                            })
+                            .collect(),
+                    );
+                }
+            }
+            // Reserved (1B), offset (13b), reserved (2b), more fragments (1b),
+            // identification (4B).
+            SkbIpv6ExtType::Fragment => {
+                frag = Some(SkbIpv6FragEvent {
+                    id: u32::from_be_bytes(hdr[4..8].try_into()?),
+                    offset: u16::from_be_bytes([hdr[2], hdr[3]]) >> 3,
+                    more: hdr[3] & 1 != 0,
+                });
+            }
+            _ => (),
+        }
+
+        ext_headers.push(ext);
+        protocol = IpNextHeaderProtocol(hdr[0]);
+        offset += len;
+
+        // Only the first fragment holds the upper-layer header.
+        if frag.as_ref().is_some_and(|f| f.offset != 0) {
+            break None;
+        }
+    };
+
+    Ok((
+        SkbIpEvent {
+            saddr: ip.get_source().to_string(),
+            daddr: ip.get_destination().to_string(),
+            version: SkbIpVersion::V6 {
+                v6: SkbIpv6Event {
+                    flow_label: ip.get_flow_label(),
+                    ext_headers: (!ext_headers.is_empty()).then_some(ext_headers),
+                    frag,
+                },
             },
+            protocol: protocol.0,
+            len: ip.get_payload_length(),
+            ttl: ip.get_hop_limit(),
+            ecn: ip.get_traffic_class() & 0x3,
         },
-        protocol: ip.get_next_header().0,
-        len: ip.get_payload_length(),
-        ttl: ip.get_hop_limit(),
-        ecn: ip.get_traffic_class() & 0x3,
-    })
+        l4,
+    ))
 }
 
 pub(super) fn unmarshal_tcp(tcp: &TcpPacket) -> Result<SkbTcpEvent> {
-    Ok(SkbTcpEvent {
+    let mut event = SkbTcpEvent {
         sport: tcp.get_source(),
         dport: tcp.get_destination(),
         seq: tcp.get_sequence(),
@@ -98,7 +288,75 @@ pub(super) fn unmarshal_tcp(tcp: &TcpPacket) -> Result<SkbTcpEvent> {
         window: tcp.get_window(),
         doff: tcp.get_data_offset(),
         flags: tcp.get_flags(),
-    })
+        mss: None,
+        wscale: None,
+        sack_permitted: false,
+        sack: None,
+        tsval: None,
+        tsecr: None,
+        options: None,
+    };
+
+    unmarshal_tcp_options(&mut event, tcp.get_options_raw());
+    Ok(event)
+}
+
+/// Parses the TCP options (as found after the fixed header, up to the data
+/// offset) into the TCP event. Unknown options are skipped and parsing stops at
+/// the first malformed or truncated one.
+fn unmarshal_tcp_options(event: &mut SkbTcpEvent, mut data: &[u8]) {
+    let mut kinds = Vec::new();
+
+    while let Some(&kind) = data.first() {
+        match kind {
+            // End of options list.
+            0 => break,
+            // No operation.
+            1 => {
+                data = &data[1..];
+                continue;
+            }
+            _ => (),
+        }
+
+        // Kind (1B), length (1B), including both.
+        if data.len() < 2 || data[1] < 2 || data.len() < data[1] as usize {
+            break;
+        }
+        let (opt, rest) = data.split_at(data[1] as usize);
+        data = rest;
+
+        let be32 = |b: &[u8]| u32::from_be_bytes([b[0], b[1], b[2], b[3]]);
+        match (kind, opt.len()) {
+            // Maximum segment size (2B).
+            (2, 4) => event.mss = Some(u16::from_be_bytes([opt[2], opt[3]])),
+            // Window scale shift count (1B).
+            (3, 3) => event.wscale = Some(opt[2]),
+            // SACK permitted.
+            (4, 2) => event.sack_permitted = true,
+            // SACK blocks, left and right edges (4B each).
+            (5, len) if len > 2 && (len - 2) % 8 == 0 => {
+                event.sack = Some(
+                    opt[2..]
+                        .chunks_exact(8)
+                        .map(|b| SkbTcpSackBlock {
+                            left: be32(&b[..4]),
+                            right: be32(&b[4..]),
+                        })
+                        .collect(),
+                );
+            }
+            // Timestamps, value and echo reply (4B each).
+            (8, 10) => {
+                event.tsval = Some(be32(&opt[2..6]));
+                event.tsecr = Some(be32(&opt[6..10]));
+            }
+            _ => continue,
+        }
+        kinds.push(kind);
+    }
+
+    event.options = (!kinds.is_empty()).then_some(kinds);
 }
 
 pub(super) fn unmarshal_udp(udp: &UdpPacket) -> Result<SkbUdpEvent> {
@@ -109,18 +367,212 @@ pub(super) fn unmarshal_udp(udp: &UdpPacket) -> Result<SkbUdpEvent> {
     })
 }
 
+/// Parses an SCTP packet: common header and chunk types. Returns None if the
+/// common header is truncated.
+pub(super) fn unmarshal_sctp(payload: &[u8]) -> Result<Option<SkbSctpEvent>> {
+    // Source port (2B), destination port (2B), verification tag (4B) and
+    // checksum (4B).
+    if payload.len() < 12 {
+        return Ok(None);
+    }
+
+    // Chunks: type (1B), flags (1B) and length (2B, not including the padding
+    // to 4B).
+    let mut chunks = Vec::new();
+    let mut data = &payload[12..];
+    while data.len() >= 4 {
+        chunks.push(data[0]);
+
+        let len = u16::from_be_bytes([data[2], data[3]]) as usize;
+        if len < 4 {
+            break;
+        }
+        data = &data[len.next_multiple_of(4).min(data.len())..];
+    }
+
+    Ok(Some(SkbSctpEvent {
+        sport: u16::from_be_bytes([payload[0], payload[1]]),
+        dport: u16::from_be_bytes([payload[2], payload[3]]),
+        vtag: u32::from_be_bytes(payload[4..8].try_into()?),
+        chunks,
+    }))
+}
+
+/// Parses a DCCP packet. Returns None if the generic header is truncated.
+pub(super) fn unmarshal_dccp(payload: &[u8]) -> Result<Option<SkbDccpEvent>> {
+    // Source port (2B), destination port (2B), data offset (1B), CCVal &
+    // CsCov (1B), checksum (2B), reserved (3b), type (4b), extended sequence
+    // numbers (1b) and the sequence number: 24 bits or, if extended, 48 bits
+    // after a reserved byte.
+    if payload.len() < 12 {
+        return Ok(None);
+    }
+
+    let seq = match payload[8] & 0x1 {
+        0 => u64::from_be_bytes([0, 0, 0, 0, 0, payload[9], payload[10], payload[11]]),
+        _ => {
+            if payload.len() < 16 {
+                return Ok(None);
+            }
+            let mut seq = [0; 8];
+            seq[2..].copy_from_slice(&payload[10..16]);
+            u64::from_be_bytes(seq)
+        }
+    };
+
+    Ok(Some(SkbDccpEvent {
+        sport: u16::from_be_bytes([payload[0], payload[1]]),
+        dport: u16::from_be_bytes([payload[2], payload[3]]),
+        r#type: (payload[8] >> 1) & 0xf,
+        seq,
+    }))
+}
+
+/// Parses a GRE header. Returns None if it is truncated.
+pub(super) fn unmarshal_gre(payload: &[u8]) -> Result<Option<SkbGreEvent>> {
+    // Flags (1B), flags & version (1B), protocol (2B), followed by the
+    // optional checksum & reserved (4B), key (4B) and sequence number (4B).
+    if payload.len() < 4 {
+        return Ok(None);
+    }
+
+    let mut gre = SkbGreEvent {
+        flags: payload[0],
+        version: payload[1] & 0x7,
+        protocol: u16::from_be_bytes([payload[2], payload[3]]),
+        key: None,
+        seq: None,
+    };
+
+    let mut offset = 4;
+    if gre.flags & 0x80 != 0 {
+        offset += 4;
+    }
+    if gre.flags & 0x20 != 0 {
+        if payload.len() < offset + 4 {
+            return Ok(None);
+        }
+        gre.key = Some(u32::from_be_bytes(
+            payload[offset..(offset + 4)].try_into()?,
+        ));
+        offset += 4;
+    }
+    if gre.flags & 0x10 != 0 {
+        if payload.len() < offset + 4 {
+            return Ok(None);
+        }
+        gre.seq = Some(u32::from_be_bytes(
+            payload[offset..(offset + 4)].try_into()?,
+        ));
+    }
+
+    Ok(Some(gre))
+}
+
 pub(super) fn unmarshal_icmp(icmp: &IcmpPacket) -> Result<SkbIcmpEvent> {
-    Ok(SkbIcmpEvent {
+    let mut event = SkbIcmpEvent {
         r#type: icmp.get_icmp_type().0,
         code: icmp.get_icmp_code().0,
-    })
+        mtu: None,
+        orig: None,
+    };
+
+    // The payload starts with the rest of the header (4B), followed for
+    // error messages by the beginning of the original packet.
+    let payload = icmp.payload();
+    if payload.len() < 4 {
+        return Ok(event);
+    }
+
+    match event.r#type {
+        // Destination unreachable (next-hop MTU for fragmentation needed
+        // errors), redirect, time exceeded and parameter problem.
+        3 | 5 | 11 | 12 => {
+            if event.r#type == 3 && event.code == 4 {
+                event.mtu = Some(u16::from_be_bytes([payload[2], payload[3]]));
+            }
+            event.orig = unmarshal_icmp_orig(&payload[4..])?;
+        }
+        _ => (),
+    }
+
+    Ok(event)
 }
 
 pub(super) fn unmarshal_icmpv6(icmp: &Icmpv6Packet) -> Result<SkbIcmpV6Event> {
-    Ok(SkbIcmpV6Event {
+    let mut event = SkbIcmpV6Event {
         r#type: icmp.get_icmpv6_type().0,
         code: icmp.get_icmpv6_code().0,
-    })
+        mtu: None,
+        target: None,
+        orig: None,
+    };
+
+    // The payload starts with the rest of the header (4B), followed by the
+    // message body.
+    let payload = icmp.payload();
+    if payload.len() < 4 {
+        return Ok(event);
+    }
+
+    match event.r#type {
+        // Error messages, followed by the beginning of the original packet.
+        // Packet too big ones report the MTU.
+        1..=4 => {
+            if event.r#type == 2 {
+                event.mtu = Some(u32::from_be_bytes(payload[..4].try_into()?));
+            }
+            event.orig = unmarshal_icmp_orig(&payload[4..])?;
+        }
+        // Neighbor solicitations & advertisements and redirects: target
+        // address (16B).
+        135..=137 if payload.len() >= 20 => {
+            let mut target = [0; 16];
+            target.copy_from_slice(&payload[4..20]);
+            event.target = Some(Ipv6Addr::from(target).to_string());
+        }
+        _ => (),
+    }
+
+    Ok(event)
+}
+
+/// Parses the beginning of the original packet embedded in ICMP and ICMPv6
+/// error messages: its IP header and, if present, the L4 ports.
+fn unmarshal_icmp_orig(data: &[u8]) -> Result<Option<SkbIcmpOrigEvent>> {
+    let (ip, l4) = match data.first().map(|b| b >> 4) {
+        Some(4) => {
+            let Some(ip) = Ipv4Packet::new(data) else {
+                return Ok(None);
+            };
+            let l4 = (ip.get_fragment_offset() == 0).then_some(ip.get_header_length() as usize * 4);
+            (unmarshal_ipv4(&ip)?, l4)
+        }
+        Some(6) => {
+            let Some(ip) = Ipv6Packet::new(data) else {
+                return Ok(None);
+            };
+            let (event, l4) = unmarshal_ipv6(&ip)?;
+            // Make the offset relative to the IPv6 header.
+            (event, l4.map(|offset| offset + 40))
+        }
+        _ => return Ok(None),
+    };
+
+    // TCP, UDP, SCTP and DCCP all start with the source and destination ports.
+    let ports = match (ip.protocol, l4) {
+        (6 | 17 | 33 | 132, Some(offset)) if data.len() >= offset + 4 => Some((
+            u16::from_be_bytes([data[offset], data[offset + 1]]),
+            u16::from_be_bytes([data[offset + 2], data[offset + 3]]),
+        )),
+        _ => None,
+    };
+
+    Ok(Some(SkbIcmpOrigEvent {
+        ip,
+        sport: ports.map(|p| p.0),
+        dport: ports.map(|p| p.1),
+    }))
 }
 
 /// Unmarshal net device info. Can return Ok(None) in case the info does not
@@ -178,6 +630,7 @@ pub(super) fn unmarshal_vlan(raw_section: &BpfRawSection) -> Result<SkbVlanEvent
         dei: raw.dei == 1,
         vid: raw.vid,
         acceleration: raw.acceleration == 1,
+        tpid: None,
     })
 }
 
@@ -205,22 +658,48 @@ pub(super) fn unmarshal_gso(raw_section: &BpfRawSection) -> Result<SkbGsoEvent>
     })
 }
 
+/// Unmarshals the packet metadata section and its data section.
 pub(super) fn unmarshal_packet(
     event: &mut SkbEvent,
     raw_section: &BpfRawSection,
+    data_section: &BpfRawSection,
     report_eth: bool,
 ) -> Result<()> {
     let raw = parse_raw_section::<skb_packet_event>(raw_section)?;
+    let packet = data_section
+        .data
+        .get(..(raw.capture_len as usize))
+        .ok_or_else(|| {
+            anyhow!(
+                "Packet data is shorter than its capture length ({} < {})",
+                data_section.data.len(),
+                raw.capture_len
+            )
+        })?;
+
+    unmarshal_raw_packet(event, packet, raw.len, raw.fake_eth != 0, report_eth)
+}
 
+/// Fills an skb event from raw packet data starting at the Ethernet header
+/// (which can be a fake one): the raw packet itself and the sections that can
+/// be parsed from it. `len` is the original length of the packet, which can be
+/// larger than the captured data.
+pub(crate) fn unmarshal_raw_packet(
+    event: &mut SkbEvent,
+    packet: &[u8],
+    len: u32,
+    fake_eth: bool,
+    report_eth: bool,
+) -> Result<()> {
     // First add the raw packet part in the event.
     event.packet = Some(SkbPacketEvent {
-        len: raw.len,
-        capture_len: raw.capture_len,
-        packet: RawPacket(raw.packet[..(raw.capture_len as usize)].to_vec()),
+        len,
+        capture_len: packet.len() as u32,
+        packet: RawPacket(packet.to_vec()),
     });
 
     // Then start parsing the raw packet to generate other sections.
-    let eth = EthernetPacket::new(&raw.packet[..(raw.capture_len as usize)]).ok_or_else(|| {
+    let eth = EthernetPacket::new(packet).ok_or_else(|| {
         anyhow!("Could not parse Ethernet packet (buffer size less than minimal)")
     })?;
 
@@ -231,38 +710,269 @@ pub(super) fn unmarshal_packet(
         return Ok(());
     }
 
-    if report_eth && raw.fake_eth == 0 {
+    if report_eth && !fake_eth {
         event.eth = Some(unmarshal_eth(&eth)?);
     }
 
-    match eth.get_ethertype() {
+    let (etype, payload) = unmarshal_tags(event, eth.get_ethertype(), eth.payload());
+
+    // If we did not generate any data in the skb section, this means we do
+    // not support yet the protocol used. At least provide the ethertype (we
+    // already checked it looked valid).
+    if !unmarshal_l3(event, etype, payload, true)? && event.eth.is_none() {
+        event.eth = Some(unmarshal_eth(&eth)?);
+    }
+
+    Ok(())
+}
+
+/// Parses the VLAN tags and the MPLS label stack following the Ethernet
+/// header, if any. Returns the ethertype and the data of the payload behind
+/// them. MPLS payloads carry no type information and are guessed from the IP
+/// version field.
+fn unmarshal_tags<'a>(
+    event: &mut SkbEvent,
+    mut etype: EtherType,
+    mut payload: &'a [u8],
+) -> (EtherType, &'a [u8]) {
+    // TCI (2B): PCP (3b), DEI (1b) and VID (12b); followed by the ethertype
+    // (2B).
+    let mut tags = Vec::new();
+    while matches!(
+        etype,
+        EtherTypes::Vlan | EtherTypes::PBridge | EtherTypes::QinQ
+    ) && payload.len() >= 4
+    {
+        let tci = u16::from_be_bytes([payload[0], payload[1]]);
+        tags.push(SkbVlanEvent {
+            pcp: (tci >> 13) as u8,
+            dei: tci & 0x1000 != 0,
+            vid: tci & 0xfff,
+            acceleration: false,
+            tpid: Some(etype.0),
+        });
+
+        etype = EtherType(u16::from_be_bytes([payload[2], payload[3]]));
+        payload = &payload[4..];
+    }
+
+    if let Some(first) = tags.first() {
+        // The kernel reports a single tag: the accelerated one or the first
+        // one in the packet. The full stack is only reported if there is
+        // more than one tag.
+        let mut vlans = Vec::new();
+        match &event.vlan {
+            Some(vlan) if vlan.acceleration => vlans.push(vlan.clone()),
+            Some(_) => (),
+            None => event.vlan = Some(first.clone()),
+        }
+        vlans.extend(tags);
+
+        if vlans.len() > 1 {
+            event.vlans = Some(vlans);
+        }
+    }
+
+    if matches!(etype, EtherTypes::Mpls | EtherTypes::MplsMcast) {
+        // Label (20b), traffic class (3b), bottom of stack (1b) and TTL (1B).
+        let mut mpls = Vec::new();
+        while payload.len() >= 4 {
+            let entry = u32::from_be_bytes([payload[0], payload[1], payload[2], payload[3]]);
+            let bos = entry & 0x100 != 0;
+            mpls.push(SkbMplsEvent {
+                label: entry >> 12,
+                tc: ((entry >> 9) & 0x7) as u8,
+                bos,
+                ttl: entry as u8,
+            });
+
+            payload = &payload[4..];
+            if bos {
+                break;
+            }
+        }
+
+        etype = match payload.first().map(|b| b >> 4) {
+            Some(4) if mpls.last().is_some_and(|l| l.bos) => EtherTypes::Ipv4,
+            Some(6) if mpls.last().is_some_and(|l| l.bos) => EtherTypes::Ipv6,
+            _ => EtherType(0),
+        };
+
+        if !mpls.is_empty() {
+            event.mpls = Some(mpls);
+        }
+    }
+
+    (etype, payload)
+}
+
+/// Parses the L3 (and above) headers of a packet. Encapsulated packets are
+/// parsed and reported in `event.inner` if `encap` is true. Returns false if
+/// the ethertype is not supported.
+fn unmarshal_l3(
+    event: &mut SkbEvent,
+    etype: EtherType,
+    payload: &[u8],
+    encap: bool,
+) -> Result<bool> {
+    match etype {
         EtherTypes::Arp => {
-            if let Some(eth) = ArpPacket::new(eth.payload()) {
+            if let Some(eth) = ArpPacket::new(payload) {
                 event.arp = unmarshal_arp(&eth)?;
             };
         }
         EtherTypes::Ipv4 => {
-            if let Some(ip) = Ipv4Packet::new(eth.payload()) {
+            if let Some(ip) = Ipv4Packet::new(payload) {
                 event.ip = Some(unmarshal_ipv4(&ip)?);
-                unmarshal_l4(event, ip.get_next_level_protocol(), ip.payload())?;
+
+                // Only the first fragment holds the L4 header.
+                if ip.get_fragment_offset() == 0 {
+                    unmarshal_l4(event, ip.get_next_level_protocol(), ip.payload())?;
+                    if encap {
+                        unmarshal_encap(event, ip.get_next_level_protocol(), ip.payload())?;
+                    }
+                }
             };
         }
         EtherTypes::Ipv6 => {
-            if let Some(ip) = Ipv6Packet::new(eth.payload()) {
-                event.ip = Some(unmarshal_ipv6(&ip)?);
-                unmarshal_l4(event, ip.get_next_header(), ip.payload())?;
+            if let Some(ip) = Ipv6Packet::new(payload) {
+                let (ipv6, l4) = unmarshal_ipv6(&ip)?;
+                let protocol = IpNextHeaderProtocol(ipv6.protocol);
+                event.ip = Some(ipv6);
+
+                if let Some(offset) = l4 {
+                    unmarshal_l4(event, protocol, &ip.payload()[offset..])?;
+                    if encap {
+                        unmarshal_encap(event, protocol, &ip.payload()[offset..])?;
+                    }
+                }
             };
         }
-        // If we did not generate any data in the skb section, this means we do
-        // not support yet the protocol used. At least provide the ethertype (we
-        // already checked it looked valid).
-        _ => {
-            if event.eth.is_none() {
-                event.eth = Some(unmarshal_eth(&eth)?);
+        _ => return Ok(false),
+    }
+
+    Ok(true)
+}
+
+/// Transparent Ethernet bridging ethertype, used by GRE and Geneve to carry
+/// Ethernet frames.
+const ETH_P_TEB: u16 = 0x6558;
+/// IANA assigned VXLAN UDP port.
+const VXLAN_PORT: u16 = 4789;
+/// IANA assigned Geneve UDP port.
+const GENEVE_PORT: u16 = 6081;
+
+/// Parses the encapsulated packet of well-known tunnel protocols (VXLAN,
+/// Geneve, GRE and IP in IP) and reports its headers in `event.inner`. Only
+/// one level of encapsulation is parsed.
+fn unmarshal_encap(
+    event: &mut SkbEvent,
+    protocol: IpNextHeaderProtocol,
+    payload: &[u8],
+) -> Result<()> {
+    let mut inner = SkbInnerEvent {
+        encap: SkbEncapType::Ipip,
+        vni: None,
+        key: None,
+        eth: None,
+        vlan: None,
+        vlans: None,
+        mpls: None,
+        ip: None,
+        tcp: None,
+        udp: None,
+        icmp: None,
+        icmpv6: None,
+        sctp: None,
+        dccp: None,
+    };
+
+    // Find the encapsulation type, its identifier and the inner packet
+    // protocol & data.
+    let (etype, data) = match protocol {
+        IpNextHeaderProtocols::Udp => {
+            let Some(udp) = UdpPacket::new(payload) else {
+                return Ok(());
+            };
+            // Skip the UDP header (8B).
+            let data = &payload[8..];
+
+            match udp.get_destination() {
+                // Flags (I bit must be set), reserved (3B), VNI (3B), reserved.
+                VXLAN_PORT if data.len() >= 8 && data[0] & 0x08 != 0 => {
+                    inner.encap = SkbEncapType::Vxlan;
+                    inner.vni = Some(u32::from_be_bytes([0, data[4], data[5], data[6]]));
+                    (ETH_P_TEB, &data[8..])
+                }
+                // Version & options length, flags, protocol (2B), VNI (3B),
+                // reserved; followed by options.
+                GENEVE_PORT if data.len() >= 8 && data[0] >> 6 == 0 => {
+                    let hlen = 8 + (data[0] & 0x3f) as usize * 4;
+                    if data.len() < hlen {
+                        return Ok(());
+                    }
+                    inner.encap = SkbEncapType::Geneve;
+                    inner.vni = Some(u32::from_be_bytes([0, data[4], data[5], data[6]]));
+                    (u16::from_be_bytes([data[2], data[3]]), &data[hlen..])
+                }
+                _ => return Ok(()),
             }
         }
+        IpNextHeaderProtocols::Gre => {
+            let Some(gre) = unmarshal_gre(payload)? else {
+                return Ok(());
+            };
+            if gre.version != 0 {
+                return Ok(());
+            }
+
+            // Checksum & reserved, key and sequence number are optional (4B
+            // each).
+            let hlen = 4 + [0x80, 0x20, 0x10]
+                .iter()
+                .filter(|flag| gre.flags & *flag != 0)
+                .count()
+                * 4;
+            if payload.len() < hlen {
+                return Ok(());
+            }
+
+            inner.encap = SkbEncapType::Gre;
+            inner.key = gre.key;
+            (gre.protocol, &payload[hlen..])
+        }
+        IpNextHeaderProtocols::Ipv4 => (EtherTypes::Ipv4.0, payload),
+        IpNextHeaderProtocols::Ipv6 => (EtherTypes::Ipv6.0, payload),
+        _ => return Ok(()),
+    };
+
+    // Parse the inner packet in its own skb event, to reuse the existing
+    // logic, and move its headers in the inner section.
+    let mut packet = SkbEvent::default();
+    if etype == ETH_P_TEB {
+        let Some(eth) = EthernetPacket::new(data) else {
+            return Ok(());
+        };
+        packet.eth = Some(unmarshal_eth(&eth)?);
+        let (etype, payload) = unmarshal_tags(&mut packet, eth.get_ethertype(), eth.payload());
+        unmarshal_l3(&mut packet, etype, payload, false)?;
+    } else if !unmarshal_l3(&mut packet, EtherType(etype), data, false)? {
+        return Ok(());
     }
 
+    inner.eth = packet.eth;
+    inner.vlan = packet.vlan;
+    inner.vlans = packet.vlans;
+    inner.mpls = packet.mpls;
+    inner.ip = packet.ip;
+    inner.tcp = packet.tcp;
+    inner.udp = packet.udp;
+    inner.icmp = packet.icmp;
+    inner.icmpv6 = packet.icmpv6;
+    inner.sctp = packet.sctp;
+    inner.dccp = packet.dccp;
+    event.inner = Some(inner);
+
     Ok(())
 }
 
@@ -292,6 +1002,9 @@ fn unmarshal_l4(
                 event.icmpv6 = Some(unmarshal_icmpv6(&icmpv6)?);
             }
         }
+        IpNextHeaderProtocols::Sctp => event.sctp = unmarshal_sctp(payload)?,
+        IpNextHeaderProtocols::Dccp => event.dccp = unmarshal_dccp(payload)?,
+        IpNextHeaderProtocols::Gre => event.gre = unmarshal_gre(payload)?,
         _ => (),
     }
 
@@ -314,6 +1027,7 @@ impl SkbEventFactory {
 impl RawEventSectionFactory for SkbEventFactory {
     fn create(&mut self, raw_sections: Vec<BpfRawSection>) -> Result<Box<dyn EventSection>> {
         let mut event = SkbEvent::default();
+        let (mut packet, mut packet_data) = (None, None);
 
         for section in raw_sections.iter() {
             match section.header.data_type as u32 {
@@ -323,11 +1037,22 @@ impl RawEventSectionFactory for SkbEventFactory {
                 SECTION_META => event.meta = Some(unmarshal_meta(section)?),
                 SECTION_DATA_REF => event.data_ref = Some(unmarshal_data_ref(section)?),
                 SECTION_GSO => event.gso = Some(unmarshal_gso(section)?),
-                SECTION_PACKET => unmarshal_packet(&mut event, section, self.report_eth)?,
+                SECTION_PACKET => packet = Some(section),
+                SECTION_PACKET_DATA => packet_data = Some(section),
                 x => bail!("Unknown data type ({x})"),
             }
         }
 
+        // The packet is parsed last, as its VLAN tags complete the VLAN
+        // section.
+        match (packet, packet_data) {
+            (Some(section), Some(data)) => {
+                unmarshal_packet(&mut event, section, data, self.report_eth)?
+            }
+            (None, None) => (),
+            _ => bail!("Packet metadata or data section is missing"),
+        }
+
         Ok(Box::new(event))
     }
 }
@@ -375,19 +1100,6 @@ pub(crate) mod benchmark {
             let data = Self {
                 len: 66,
                 capture_len: 66,
-                packet: [
-                    46, 137, 59, 254, 34, 122, 42, 186, 90, 193, 129, 79, 8, 0, 69, 0, 0, 52, 32,
-                    32, 64, 0, 55, 6, 237, 160, 1, 1, 1, 1, 10, 0, 42, 2, 1, 187, 157, 12, 31, 149,
-                    22, 86, 145, 251, 180, 241, 128, 17, 0, 8, 17, 72, 0, 0, 1, 1, 8, 10, 28, 109,
-                    231, 120, 127, 134, 144, 92, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                ],
                 ..Default::default()
             };
             build_raw_section(
@@ -396,7 +1108,417 @@ pub(crate) mod benchmark {
                 SECTION_PACKET as u8,
                 &mut as_u8_vec(&data),
             );
+            build_raw_section(
+                out,
+                FactoryId::Skb as u8,
+                SECTION_PACKET_DATA as u8,
+                &mut vec![
+                    46, 137, 59, 254, 34, 122, 42, 186, 90, 193, 129, 79, 8, 0, 69, 0, 0, 52, 32,
+                    32, 64, 0, 55, 6, 237, 160, 1, 1, 1, 1, 10, 0, 42, 2, 1, 187, 157, 12, 31, 149,
+                    22, 86, 145, 251, 180, 241, 128, 17, 0, 8, 17, 72, 0, 0, 1, 1, 8, 10, 28, 109,
+                    231, 120, 127, 134, 144, 92,
+                ],
+            );
             Ok(())
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eth(etype: u16, payload: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0x02, 0, 0, 0, 0, 0x01, 0x02, 0, 0, 0, 0, 0x02];
+        packet.extend_from_slice(&etype.to_be_bytes());
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    fn ipv4(protocol: u8, saddr: [u8; 4], daddr: [u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0x45, 0];
+        packet.extend_from_slice(&(20 + payload.len() as u16).to_be_bytes());
+        packet.extend_from_slice(&[0, 1, 0x40, 0, 64, protocol, 0, 0]);
+        packet.extend_from_slice(&saddr);
+        packet.extend_from_slice(&daddr);
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    fn ipv6(next_header: u8, payload: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0x60, 0, 0, 0];
+        packet.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+        packet.extend_from_slice(&[next_header, 64]);
+        // 2001:db8::1 > 2001:db8::2
+        for last in [1, 2] {
+            packet.extend_from_slice(&[0x20, 0x01, 0x0d, 0xb8]);
+            packet.extend_from_slice(&[0; 11]);
+            packet.push(last);
+        }
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    fn udp(sport: u16, dport: u16, payload: &[u8]) -> Vec<u8> {
+        let mut packet = Vec::new();
+        packet.extend_from_slice(&sport.to_be_bytes());
+        packet.extend_from_slice(&dport.to_be_bytes());
+        packet.extend_from_slice(&(8 + payload.len() as u16).to_be_bytes());
+        packet.extend_from_slice(&[0, 0]);
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    fn tcp_syn(sport: u16, dport: u16) -> Vec<u8> {
+        let mut packet = Vec::new();
+        packet.extend_from_slice(&sport.to_be_bytes());
+        packet.extend_from_slice(&dport.to_be_bytes());
+        packet.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 0, 0x50, 0x02, 0xff, 0xff, 0, 0, 0, 0]);
+        packet
+    }
+
+    fn parse(packet: &[u8]) -> SkbEvent {
+        let mut event = SkbEvent::default();
+        unmarshal_raw_packet(&mut event, packet, packet.len() as u32, false, true).unwrap();
+        event
+    }
+
+    #[test]
+    fn encap_vxlan() {
+        let inner = eth(
+            0x0800,
+            &ipv4(6, [10, 0, 0, 1], [10, 0, 0, 2], &tcp_syn(1234, 80)),
+        );
+        let mut vxlan = vec![0x08, 0, 0, 0, 0, 0, 42, 0];
+        vxlan.extend_from_slice(&inner);
+        let packet = eth(
+            0x0800,
+            &ipv4(
+                17,
+                [192, 168, 0, 1],
+                [192, 168, 0, 2],
+                &udp(5000, 4789, &vxlan),
+            ),
+        );
+
+        let event = parse(&packet);
+        assert_eq!(event.udp.as_ref().unwrap().dport, 4789);
+
+        let inner = event.inner.unwrap();
+        assert_eq!(inner.encap, SkbEncapType::Vxlan);
+        assert_eq!(inner.vni, Some(42));
+        assert!(inner.eth.is_some());
+        assert_eq!(inner.ip.as_ref().unwrap().saddr, "10.0.0.1");
+        assert_eq!(inner.tcp.as_ref().unwrap().dport, 80);
+
+        // Inner QinQ tags are reported in the inner section.
+        let tags = [0, 100, 0x81, 0, 0, 200, 0x08, 0];
+        let mut vxlan = vec![0x08, 0, 0, 0, 0, 0, 42, 0];
+        vxlan.extend_from_slice(&eth(
+            0x88a8,
+            &[
+                &tags[..],
+                &ipv4(6, [10, 0, 0, 1], [10, 0, 0, 2], &tcp_syn(1234, 80)),
+            ]
+            .concat(),
+        ));
+        let packet = eth(
+            0x0800,
+            &ipv4(
+                17,
+                [192, 168, 0, 1],
+                [192, 168, 0, 2],
+                &udp(5000, 4789, &vxlan),
+            ),
+        );
+
+        let event = parse(&packet);
+        assert!(event.vlan.is_none());
+        let inner = event.inner.unwrap();
+        assert_eq!(inner.vlan.as_ref().unwrap().vid, 100);
+        assert_eq!(inner.vlans.as_ref().unwrap()[1].vid, 200);
+        assert_eq!(inner.tcp.as_ref().unwrap().dport, 80);
+    }
+
+    #[test]
+    fn encap_gre_ipip() {
+        // GRE with a key, carrying IPv4.
+        let mut gre = vec![0x20, 0, 0x08, 0, 0, 0, 0x12, 0x34];
+        gre.extend_from_slice(&ipv4(17, [10, 0, 0, 1], [10, 0, 0, 2], &udp(1, 53, &[])));
+        let event = parse(&eth(0x0800, &ipv4(47, [1, 1, 1, 1], [2, 2, 2, 2], &gre)));
+
+        let gre = event.gre.as_ref().unwrap();
+        assert_eq!(
+            (gre.protocol, gre.key, gre.seq),
+            (0x0800, Some(0x1234), None)
+        );
+
+        let inner = event.inner.unwrap();
+        assert_eq!(inner.encap, SkbEncapType::Gre);
+        assert_eq!(inner.key, Some(0x1234));
+        assert!(inner.eth.is_none());
+        assert_eq!(inner.udp.as_ref().unwrap().dport, 53);
+
+        // IPv4 in IPv4.
+        let ipip = ipv4(6, [10, 0, 0, 1], [10, 0, 0, 2], &tcp_syn(1, 2));
+        let event = parse(&eth(0x0800, &ipv4(4, [1, 1, 1, 1], [2, 2, 2, 2], &ipip)));
+        let inner = event.inner.unwrap();
+        assert_eq!(inner.encap, SkbEncapType::Ipip);
+        assert_eq!(inner.ip.as_ref().unwrap().daddr, "10.0.0.2");
+
+        // Not an encapsulation.
+        let event = parse(&eth(
+            0x0800,
+            &ipv4(17, [1, 1, 1, 1], [2, 2, 2, 2], &udp(1, 53, &[0; 8])),
+        ));
+        assert!(event.inner.is_none());
+    }
+
+    #[test]
+    fn icmp() {
+        // Port unreachable, embedding a UDP packet.
+        let mut icmp = vec![3, 3, 0, 0, 0, 0, 0, 0];
+        icmp.extend_from_slice(&ipv4(17, [2, 2, 2, 2], [1, 1, 1, 1], &udp(1234, 53, &[])));
+        let event = parse(&eth(0x0800, &ipv4(1, [1, 1, 1, 1], [2, 2, 2, 2], &icmp)));
+        let icmp = event.icmp.as_ref().unwrap();
+        assert_eq!(icmp.code_str(), Some("port unreachable"));
+        let orig = icmp.orig.as_ref().unwrap();
+        assert_eq!((orig.ip.saddr.as_str(), orig.ip.protocol), ("2.2.2.2", 17));
+        assert_eq!((orig.sport, orig.dport), (Some(1234), Some(53)));
+
+        // Fragmentation needed, embedding a truncated TCP header.
+        let mut icmp = vec![3, 4, 0, 0, 0, 0, 0x05, 0xdc];
+        icmp.extend_from_slice(&ipv4(6, [2, 2, 2, 2], [1, 1, 1, 1], &tcp_syn(1, 2))[..28]);
+        let event = parse(&eth(0x0800, &ipv4(1, [1, 1, 1, 1], [2, 2, 2, 2], &icmp)));
+        let icmp = event.icmp.as_ref().unwrap();
+        assert_eq!(icmp.mtu, Some(1500));
+        assert_eq!(icmp.orig.as_ref().unwrap().dport, Some(2));
+
+        // Echo requests have no embedded packet.
+        let icmp = [8, 0, 0, 0, 0, 1, 0, 1, 0x45, 0, 0, 0];
+        let event = parse(&eth(0x0800, &ipv4(1, [1, 1, 1, 1], [2, 2, 2, 2], &icmp)));
+        assert!(event.icmp.as_ref().unwrap().orig.is_none());
+
+        // Neighbor solicitation.
+        let mut icmpv6 = vec![135, 0, 0, 0, 0, 0, 0, 0, 0xfe, 0x80];
+        icmpv6.extend_from_slice(&[0; 13]);
+        icmpv6.push(1);
+        let event = parse(&eth(0x86dd, &ipv6(58, &icmpv6)));
+        let icmpv6 = event.icmpv6.as_ref().unwrap();
+        assert_eq!(icmpv6.type_str(), Some("neighbor solicitation"));
+        assert_eq!(icmpv6.target.as_deref(), Some("fe80::1"));
+
+        // Packet too big, embedding a TCP packet.
+        let mut icmpv6 = vec![2, 0, 0, 0, 0, 0, 0x05, 0x00];
+        icmpv6.extend_from_slice(&ipv6(6, &tcp_syn(1234, 443)));
+        let event = parse(&eth(0x86dd, &ipv6(58, &icmpv6)));
+        let icmpv6 = event.icmpv6.as_ref().unwrap();
+        assert_eq!(icmpv6.mtu, Some(1280));
+        let orig = icmpv6.orig.as_ref().unwrap();
+        assert_eq!(orig.ip.daddr, "2001:db8::2");
+        assert_eq!((orig.sport, orig.dport), (Some(1234), Some(443)));
+    }
+
+    #[test]
+    fn vlan_mpls() {
+        // QinQ: S-tag 100 (prio 3), C-tag 200.
+        let mut tags = vec![0x60, 100, 0x81, 0, 0, 200, 0x08, 0];
+        tags.extend_from_slice(&ipv4(17, [1, 1, 1, 1], [2, 2, 2, 2], &udp(1, 53, &[])));
+        let event = parse(&eth(0x88a8, &tags));
+        let vlans = event.vlans.as_ref().unwrap();
+        assert_eq!(vlans.len(), 2);
+        assert_eq!(
+            (vlans[0].vid, vlans[0].pcp, vlans[0].tpid),
+            (100, 3, Some(0x88a8))
+        );
+        assert_eq!((vlans[1].vid, vlans[1].tpid), (200, Some(0x8100)));
+        assert_eq!(event.vlan.as_ref().unwrap().vid, 100);
+        assert_eq!(event.udp.as_ref().unwrap().dport, 53);
+
+        // A single in-packet tag, below an accelerated one.
+        let mut event = SkbEvent {
+            vlan: Some(SkbVlanEvent {
+                pcp: 0,
+                dei: false,
+                vid: 10,
+                acceleration: true,
+                tpid: None,
+            }),
+            ..Default::default()
+        };
+        let packet = eth(0x8100, &tags[4..]);
+        unmarshal_raw_packet(&mut event, &packet, packet.len() as u32, false, true).unwrap();
+        let vlans = event.vlans.as_ref().unwrap();
+        assert_eq!((vlans[0].vid, vlans[0].acceleration), (10, true));
+        assert_eq!((vlans[1].vid, vlans[1].acceleration), (200, false));
+
+        // Two MPLS labels.
+        let mut labels = vec![0, 0x06, 0x40, 64, 0, 0x0c, 0x83, 63];
+        labels.extend_from_slice(&ipv4(6, [1, 1, 1, 1], [2, 2, 2, 2], &tcp_syn(1, 2)));
+        let event = parse(&eth(0x8847, &labels));
+        let mpls = event.mpls.as_ref().unwrap();
+        assert_eq!(mpls.len(), 2);
+        assert_eq!((mpls[0].label, mpls[0].bos, mpls[0].ttl), (100, false, 64));
+        assert_eq!((mpls[1].label, mpls[1].tc, mpls[1].bos), (200, 1, true));
+        assert_eq!(event.tcp.as_ref().unwrap().dport, 2);
+    }
+
+    #[test]
+    fn sctp_dccp() {
+        // SCTP with an INIT chunk (padded) followed by a truncated DATA one.
+        let mut sctp_init = vec![0x0b, 0x59, 0x0b, 0x59, 0xde, 0xad, 0xbe, 0xef, 0, 0, 0, 0];
+        sctp_init.extend_from_slice(&[1, 0, 0, 6, 0, 0, 0, 0]);
+        sctp_init.extend_from_slice(&[0, 0, 0, 20]);
+        let event = parse(&eth(
+            0x0800,
+            &ipv4(132, [1, 1, 1, 1], [2, 2, 2, 2], &sctp_init),
+        ));
+        let sctp = event.sctp.as_ref().unwrap();
+        assert_eq!(
+            (sctp.sport, sctp.dport, sctp.vtag),
+            (2905, 2905, 0xdeadbeef)
+        );
+        assert_eq!(sctp.chunks, vec![1, 0]);
+
+        // DCCP request, with extended sequence numbers.
+        let dccp = [
+            0x13, 0x88, 0, 80, 5, 0, 0, 0, 0x01, 0, 0, 0, 0, 0, 0x12, 0x34,
+        ];
+        let event = parse(&eth(0x0800, &ipv4(33, [1, 1, 1, 1], [2, 2, 2, 2], &dccp)));
+        let dccp = event.dccp.as_ref().unwrap();
+        assert_eq!((dccp.sport, dccp.dport), (5000, 80));
+        assert_eq!((dccp.r#type, dccp.seq), (0, 0x1234));
+
+        // DCCP data, with short sequence numbers.
+        let dccp = [0x13, 0x88, 0, 80, 3, 0, 0, 0, 0x04, 0, 0x12, 0x34];
+        let event = parse(&eth(0x0800, &ipv4(33, [1, 1, 1, 1], [2, 2, 2, 2], &dccp)));
+        let dccp = event.dccp.as_ref().unwrap();
+        assert_eq!((dccp.r#type, dccp.seq), (2, 0x1234));
+
+        // SCTP and DCCP in IPv4, their ports are reported for the inner
+        // packet too.
+        let mut ipip = ipv4(132, [10, 0, 0, 1], [10, 0, 0, 2], &sctp_init);
+        let event = parse(&eth(0x0800, &ipv4(4, [1, 1, 1, 1], [2, 2, 2, 2], &ipip)));
+        let inner = event.inner.as_ref().unwrap();
+        assert_eq!(inner.sctp.as_ref().unwrap().vtag, 0xdeadbeef);
+        assert_eq!(inner.ports(), Some((2905, 2905)));
+
+        let dccp = [0x13, 0x88, 0, 80, 3, 0, 0, 0, 0x04, 0, 0x12, 0x34];
+        ipip = ipv4(33, [10, 0, 0, 1], [10, 0, 0, 2], &dccp);
+        let event = parse(&eth(0x0800, &ipv4(4, [1, 1, 1, 1], [2, 2, 2, 2], &ipip)));
+        assert_eq!(event.inner.as_ref().unwrap().ports(), Some((5000, 80)));
+    }
+
+    #[test]
+    fn ipv4_options() {
+        // Router alert, record route (one address recorded) and end of list.
+        let options = [148, 4, 0, 0, 7, 11, 8, 10, 0, 0, 1, 0, 0, 0, 0, 0];
+        let mut ip = ipv4(17, [1, 1, 1, 1], [2, 2, 2, 2], &udp(1, 53, &[]));
+        ip.splice(20..20, options);
+        let len = ip.len() as u16;
+        ip[0] = 0x49;
+        ip[2..4].copy_from_slice(&len.to_be_bytes());
+        let event = parse(&eth(0x0800, &ip));
+
+        let ip = event.ip.as_ref().unwrap();
+        let SkbIpVersion::V4 { v4 } = &ip.version else {
+            panic!("Not an IPv4 packet");
+        };
+        assert_eq!(v4.ihl, Some(9));
+        assert_eq!(v4.header_len(), 36);
+        let options = v4.options.as_ref().unwrap();
+        assert_eq!(options.len(), 2);
+        assert_eq!(options[0].r#type, SkbIpv4Option::RA);
+        assert_eq!(options[0].value, Some(0));
+        assert_eq!(options[1].r#type, SkbIpv4Option::RR);
+        assert_eq!(options[1].addrs.as_ref().unwrap(), &["10.0.0.1"]);
+        assert_eq!(event.udp.as_ref().unwrap().dport, 53);
+
+        // Non-first fragments do not hold the L4 header.
+        let mut ip = ipv4(17, [1, 1, 1, 1], [2, 2, 2, 2], &udp(1, 53, &[]));
+        ip[6..8].copy_from_slice(&[0, 0x10]);
+        let event = parse(&eth(0x0800, &ip));
+        assert!(event.ip.is_some());
+        assert!(event.udp.is_none());
+    }
+
+    #[test]
+    fn tcp_options() {
+        // SYN with MSS, SACK permitted, timestamps, NOP and window scale.
+        let mut tcp = tcp_syn(1234, 443);
+        tcp[12] = 0xa0;
+        tcp.extend_from_slice(&[2, 4, 0x05, 0xb4, 4, 2, 8, 10, 0, 0, 0, 42, 0, 0, 0, 0]);
+        tcp.extend_from_slice(&[1, 3, 3, 7]);
+        let event = parse(&eth(0x0800, &ipv4(6, [1, 1, 1, 1], [2, 2, 2, 2], &tcp)));
+
+        let tcp = event.tcp.as_ref().unwrap();
+        assert_eq!(tcp.doff, 10);
+        assert_eq!(tcp.mss, Some(1460));
+        assert!(tcp.sack_permitted);
+        assert_eq!((tcp.tsval, tcp.tsecr), (Some(42), Some(0)));
+        assert_eq!(tcp.wscale, Some(7));
+        assert!(tcp.sack.is_none());
+        assert_eq!(tcp.options.as_deref(), Some(&[2, 4, 8, 3][..]));
+
+        // ACK with two SACK blocks, then a truncated option.
+        let mut tcp = tcp_syn(1234, 443);
+        tcp[12] = 0xa0;
+        tcp.extend_from_slice(&[1, 1, 5, 18, 0, 0, 0, 10, 0, 0, 0, 20, 0, 0, 0, 30, 0, 0]);
+        tcp.extend_from_slice(&[0, 40, 2, 4]);
+        let event = parse(&eth(0x0800, &ipv4(6, [1, 1, 1, 1], [2, 2, 2, 2], &tcp)));
+
+        let tcp = event.tcp.as_ref().unwrap();
+        let sack = tcp.sack.as_ref().unwrap();
+        assert_eq!(sack.len(), 2);
+        assert_eq!((sack[0].left, sack[0].right), (10, 20));
+        assert_eq!((sack[1].left, sack[1].right), (30, 40));
+        assert!(tcp.mss.is_none());
+        assert_eq!(tcp.options.as_deref(), Some(&[5][..]));
+    }
+
+    #[test]
+    fn ipv6_ext_headers() {
+        // Hop-by-hop (padding), SRH with two segments, fragment and TCP.
+        let mut ext = vec![43, 0, 1, 4, 0, 0, 0, 0];
+        ext.extend_from_slice(&[44, 4, 4, 1, 1, 0, 0, 0]);
+        for last in [3, 4] {
+            ext.extend_from_slice(&[0x20, 0x01, 0x0d, 0xb8]);
+            ext.extend_from_slice(&[0; 11]);
+            ext.push(last);
+        }
+        ext.extend_from_slice(&[6, 0, 0, 1, 0, 0, 0x12, 0x34]);
+        ext.extend_from_slice(&tcp_syn(1234, 443));
+        let event = parse(&eth(0x86dd, &ipv6(0, &ext)));
+
+        let ip = event.ip.as_ref().unwrap();
+        assert_eq!(ip.protocol, 6);
+        let SkbIpVersion::V6 { v6 } = &ip.version else {
+            panic!("Not an IPv6 packet");
+        };
+        let ext_headers = v6.ext_headers.as_ref().unwrap();
+        assert_eq!(ext_headers.len(), 3);
+        assert_eq!(ext_headers[0].r#type, SkbIpv6ExtType::HopByHop);
+        assert_eq!(ext_headers[1].r#type, SkbIpv6ExtType::Routing);
+        assert_eq!(ext_headers[1].len, 40);
+        assert_eq!(ext_headers[1].segments_left, Some(1));
+        assert_eq!(
+            ext_headers[1].segments.as_ref().unwrap(),
+            &["2001:db8::3", "2001:db8::4"]
+        );
+        let frag = v6.frag.as_ref().unwrap();
+        assert_eq!((frag.id, frag.offset, frag.more), (0x1234, 0, true));
+        assert_eq!(event.tcp.as_ref().unwrap().dport, 443);
+
+        // Non-first fragments do not hold the upper-layer header.
+        let mut ext = vec![17, 0, 0, 0x10, 0, 0, 0x12, 0x34];
+        ext.extend_from_slice(&udp(1, 53, &[]));
+        let event = parse(&eth(0x86dd, &ipv6(44, &ext)));
+        assert_eq!(event.ip.as_ref().unwrap().protocol, 17);
+        assert!(event.udp.is_none());
+
+        // Truncated extension headers.
+        let event = parse(&eth(0x86dd, &ipv6(60, &[6, 1, 0, 0, 0, 0, 0, 0])));
+        assert_eq!(event.ip.as_ref().unwrap().protocol, 60);
+        assert!(event.tcp.is_none());
+    }
+}
@@ -20,6 +20,7 @@ enum skb_sections {
 	SECTION_META,
 	SECTION_DATA_REF,
 	SECTION_GSO,
+	SECTION_PACKET_DATA,
 } __binding;
 
 /* Skb hook configuration. A map is used to set the config from
@@ -27,6 +28,7 @@ enum skb_sections {
  */
 struct skb_config {
 	u64 sections;
+	u32 capture_len;
 } __binding;
 struct {
 	__uint(type, BPF_MAP_TYPE_ARRAY);
@@ -68,14 +70,30 @@ struct skb_gso_event {
 	u32 gso_segs;
 	u32 gso_type;
 } __binding;
+/* Maximum number of packet bytes that can be reported. Packet data is reported
+ * in the trailing section of events, please keep it a power of 2 and lower or
+ * equal to EVENT_TAIL_MAX in events.h.
+ */
+BINDING_DEF(PACKET_CAPTURE_MAX, 2048)
+
+/* Packet metadata, the packet data (SECTION_PACKET_DATA) being capture_len
+ * bytes long.
+ */
 struct skb_packet_event {
 	u32 len;
 	u32 capture_len;
-#define PACKET_CAPTURE_SIZE	255
-	u8 packet[PACKET_CAPTURE_SIZE];
 	u8 fake_eth;
 } __binding;
 
+#define MAX_SKB_FRAGS	17
+
+#ifdef __TARGET_ARCH_x86
+#define PAGE_SIZE	4096
+
+extern const void vmemmap_base __ksym __weak;
+extern const void page_offset_base __ksym __weak;
+#endif
+
 /* Retrieve an skb linear len */
 static __always_inline int skb_linear_len(struct sk_buff *skb)
 {
@@ -124,15 +142,136 @@ static __always_inline u16 skb_protocol(struct sk_buff *skb)
 	return 0;
 }
 
+/* Retrieve the virtual address of a page in the direct mapping, see
+ * page_address(). Only supported on x86 for now, returns NULL otherwise.
+ */
+static __always_inline void *skb_page_address(struct page *page)
+{
+#ifdef __TARGET_ARCH_x86
+	u64 vmemmap, page_offset;
+
+	if (!&vmemmap_base || !&page_offset_base)
+		return NULL;
+
+	bpf_probe_read_kernel(&vmemmap, sizeof(vmemmap), &vmemmap_base);
+	bpf_probe_read_kernel(&page_offset, sizeof(page_offset),
+			      &page_offset_base);
+
+	return (void *)(page_offset + ((u64)page - vmemmap) /
+			bpf_core_type_size(struct page) * PAGE_SIZE);
+#else
+	return NULL;
+#endif
+}
+
+/* Bound a non-zero size to PACKET_CAPTURE_MAX in a way the verifier can follow,
+ * its value being left untouched if already in bounds.
+ */
+static __always_inline u32 packet_bound(u32 size)
+{
+	return ((size - 1) & (PACKET_CAPTURE_MAX - 1)) + 1;
+}
+
+/* Copy size bytes from src to buf + off, buf being PACKET_CAPTURE_MAX bytes
+ * long. The copy is split in power of 2 chunks, so the verifier can bound each
+ * of them. Returns 0 on success.
+ */
+static __always_inline int packet_read(u8 *buf, u32 off, u32 size,
+				       const u8 *src)
+{
+	u32 chunk;
+
+	if (off + size > PACKET_CAPTURE_MAX)
+		return -1;
+
+#pragma unroll
+	for (chunk = PACKET_CAPTURE_MAX; chunk; chunk >>= 1) {
+		if (!(size & chunk))
+			continue;
+		if (off > PACKET_CAPTURE_MAX - chunk)
+			return -1;
+		if (bpf_probe_read_kernel(buf + off, chunk, src))
+			return -1;
+
+		off += chunk;
+		src += chunk;
+	}
+
+	return 0;
+}
+
+/* Copy up to size bytes of the paged data of an skb (its frags) into
+ * buf + off, buf being PACKET_CAPTURE_MAX bytes long. Returns the number of
+ * bytes copied.
+ */
+static __always_inline u32 skb_read_frags(struct sk_buff *skb, u8 *buf,
+					  u32 off, u32 size)
+{
+	struct skb_shared_info *si;
+	u32 read = 0;
+	u8 nr_frags;
+	int i;
+
+	/* See skb_shinfo */
+	si = (void *)(BPF_CORE_READ(skb, head) + BPF_CORE_READ(skb, end));
+	nr_frags = BPF_CORE_READ(si, nr_frags);
+
+	for (i = 0; i < MAX_SKB_FRAGS && i < nr_frags; i++) {
+		skb_frag_t *frag = &si->frags[i];
+		u32 frag_len, frag_off, chunk;
+		struct page *page;
+		void *addr;
+
+		/* Since v6.12 frags hold a netmem reference, which can be a
+		 * net_iov (e.g. devmem TCP) instead of a page. Before that
+		 * skb_frag_t was a bio_vec.
+		 */
+		if (bpf_core_field_exists(frag->netmem)) {
+			netmem_ref netmem = BPF_CORE_READ(frag, netmem);
+
+			if (netmem & 1UL)
+				break;
+
+			page = (struct page *)netmem;
+			frag_len = BPF_CORE_READ(frag, len);
+			frag_off = BPF_CORE_READ(frag, offset);
+		} else {
+			struct bio_vec *bvec = (void *)frag;
+
+			page = BPF_CORE_READ(bvec, bv_page);
+			frag_len = BPF_CORE_READ(bvec, bv_len);
+			frag_off = BPF_CORE_READ(bvec, bv_offset);
+		}
+
+		addr = skb_page_address(page);
+		if (!addr)
+			break;
+
+		chunk = MIN(frag_len, size - read);
+		if (!chunk ||
+		    packet_read(buf, off + read, chunk, addr + frag_off))
+			break;
+
+		read += chunk;
+		if (read >= size)
+			break;
+	}
+
+	return read;
+}
+
 static __always_inline int process_packet(struct retis_raw_event *event,
-					  struct sk_buff *skb)
+					  struct sk_buff *skb, u32 capture_len)
 {
 	/* Use int instead of the underlying (smaller) unsigned type to allow
 	 * signed arithmetic operations.
 	 */
 	int mac, headroom, linear_len;
+	struct retis_event_tail *tail;
 	struct skb_packet_event *e;
 	unsigned char *head;
+	u8 fake_eth;
+	long size;
 	u16 network;
 	u32 len;
 
@@ -148,30 +287,34 @@ static __always_inline int process_packet(struct retis_raw_event *event,
 	if (!linear_len)
 		return 0;
 
+	capture_len = MIN(capture_len, PACKET_CAPTURE_MAX);
+	if (capture_len <= sizeof(struct ethhdr))
+		return 0;
+
+	/* The packet data is reported in the trailing section of the event. */
+	tail = get_event_tail(event, COLLECTOR_SKB, SECTION_PACKET_DATA);
+	if (!tail)
+		return 0;
+
 	/* Best case: mac offset is set and valid */
 	if (is_mac_data_valid(skb)) {
-		long mac_offset, size;
+		long mac_offset;
 
 		mac_offset = mac - headroom;
-		size = MIN(linear_len - mac_offset, PACKET_CAPTURE_SIZE);
+		size = MIN(linear_len - mac_offset, capture_len);
 		if (size <= 0)
 			return 0;
 
-		e = get_event_section(event, COLLECTOR_SKB, SECTION_PACKET,
-				      sizeof(*e));
-		if (!e)
-			return 0;
-
-		e->len = len - mac_offset;
-		e->capture_len = size;
-		e->fake_eth = 0;
-		bpf_probe_read_kernel(e->packet, size, head + mac);
+		size = packet_bound(size);
+		len -= mac_offset;
+		fake_eth = 0;
+		bpf_probe_read_kernel(tail->data, size, head + mac);
 	/* Valid network offset with an unset or invalid mac offset: we can fake
 	 * the eth header.
 	 */
 	} else if (is_network_data_valid(skb)) {
 		u16 etype = skb_protocol(skb);
-		long network_offset, size;
+		long network_offset;
 		struct ethhdr *eth;
 
 		/* We do need the ethertype to be set at the skb level here,
@@ -182,30 +325,42 @@ static __always_inline int process_packet(struct retis_raw_event *event,
 
 		network_offset = network - headroom;
 		size = MIN(linear_len - network_offset,
-			   PACKET_CAPTURE_SIZE - sizeof(struct ethhdr));
+			   capture_len - sizeof(struct ethhdr));
 		if (size <= 0)
 			return 0;
 
-		e = get_event_section(event, COLLECTOR_SKB, SECTION_PACKET,
-				      sizeof(*e));
-		if (!e)
-			return 0;
-
 		/* Fake eth header */
-		eth = (struct ethhdr *)e->packet;
+		eth = (struct ethhdr *)tail->data;
 		__builtin_memset(eth, 0, sizeof(*eth));
 		eth->h_proto = etype;
 
-		e->len = len - network_offset + sizeof(*eth);
-		e->capture_len = size + sizeof(struct ethhdr);
-		e->fake_eth = 1;
-		bpf_probe_read_kernel(e->packet + sizeof(*eth), size,
+		size = packet_bound(size);
+		if (size > PACKET_CAPTURE_MAX - sizeof(*eth))
+			size = PACKET_CAPTURE_MAX - sizeof(*eth);
+		len += sizeof(*eth) - network_offset;
+		fake_eth = 1;
+		bpf_probe_read_kernel(tail->data + sizeof(*eth), size,
 				      head + network);
+		size += sizeof(*eth);
 	/* Can't guess any useful packet offset */
 	} else {
 		return 0;
 	}
 
+	/* The linear part was fully copied but is shorter than requested: get
+	 * the rest from the paged data, if any.
+	 */
+	if (size < capture_len && BPF_CORE_READ(skb, data_len))
+		size += skb_read_frags(skb, tail->data, size, capture_len - size);
+
+	e = get_event_section(event, COLLECTOR_SKB, SECTION_PACKET, sizeof(*e));
+	if (!e)
+		return 0;
+
+	e->len = len;
+	e->capture_len = size;
+	e->fake_eth = fake_eth;
+	tail->header.size = size;
 	return 0;
 }
 
@@ -225,7 +380,7 @@ static __always_inline int process_skb(struct retis_raw_event *event,
 	dev = BPF_CORE_READ(skb, dev);
 
 	/* Always retrieve the raw packet */
-	process_packet(event, skb);
+	process_packet(event, skb, cfg->capture_len);
 
 	if (cfg->sections & BIT(SECTION_DEV) && dev) {
 		int ifindex = BPF_CORE_READ(dev, ifindex);
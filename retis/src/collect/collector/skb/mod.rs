@@ -8,7 +8,7 @@ pub(crate) mod skb;
 pub(crate) use skb::*;
 
 pub(crate) mod bpf;
-pub(crate) use bpf::SkbEventFactory;
+pub(crate) use bpf::{unmarshal_raw_packet, SkbEventFactory};
 
 mod skb_hook {
     include!("bpf/.out/skb_hook.rs");
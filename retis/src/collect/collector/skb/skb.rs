@@ -46,6 +46,17 @@ The following values are now always retrieved and their use is deprecated:
 packet, arp, ip, tcp, udp, icmp."
     )]
     pub(crate) skb_sections: Vec<String>,
+
+    #[arg(
+        long,
+        default_value = "255",
+        value_parser = clap::value_parser!(u32).range(64..=PACKET_CAPTURE_MAX as i64),
+        help = "Maximum number of bytes of packet data to capture, from the start of the
+Ethernet header. Data stored in the non-linear part of skbs (page fragments) is
+retrieved when the linear one is shorter (x86 only). The original packet
+length is always reported. Maximum value is 2048."
+    )]
+    pub(crate) skb_capture_len: u32,
 }
 
 #[derive(Default)]
@@ -98,7 +109,10 @@ impl Collector for SkbCollector {
         let config_map = Self::config_map()?;
 
         // Set the config.
-        let cfg = skb_config { sections };
+        let cfg = skb_config {
+            sections,
+            capture_len: args.collector_args.skb.skb_capture_len,
+        };
         let cfg = unsafe { plain::as_bytes(&cfg) };
 
         let key = 0_u32.to_ne_bytes();
@@ -135,3 +149,29 @@ impl SkbCollector {
         .or_else(|e| bail!("Could not create the skb config map: {}", e))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capture_len() {
+        let parse = |args: &[&str]| {
+            SkbCollectorArgs::try_parse_from(["skb"].iter().chain(args))
+                .map(|args| args.skb_capture_len)
+        };
+
+        assert_eq!(parse(&[]).unwrap(), 255);
+        assert_eq!(parse(&["--skb-capture-len", "64"]).unwrap(), 64);
+        assert_eq!(
+            parse(&["--skb-capture-len", &PACKET_CAPTURE_MAX.to_string()]).unwrap(),
+            PACKET_CAPTURE_MAX
+        );
+
+        // Out of range or invalid values.
+        assert!(parse(&["--skb-capture-len", "63"]).is_err());
+        assert!(parse(&["--skb-capture-len", &(PACKET_CAPTURE_MAX + 1).to_string()]).is_err());
+        assert!(parse(&["--skb-capture-len", "-1"]).is_err());
+        assert!(parse(&["--skb-capture-len", "1k"]).is_err());
+    }
+}
@@ -109,7 +109,8 @@ impl BpfEventsFactory {
             Some("events_map"),
             0,
             0,
-            mem::size_of::<RawEvent>() as u32 * BPF_EVENTS_MAX,
+            // Ring buffer sizes must be a power of 2.
+            (mem::size_of::<RawEvent>() as u32 * BPF_EVENTS_MAX).next_power_of_two(),
             &opts,
         )
         .or_else(|e| bail!("Failed to create events map: {}", e))?;
@@ -378,6 +379,36 @@ pub(crate) fn parse_raw_event<'a>(
             .push(raw_section);
     }
 
+    // Then retrieve the trailing section, if any. It follows the raw event data
+    // buffer and is only used when its size is not 0.
+    if let Some(tail) = data.get(mem::size_of::<u16>() + BPF_RAW_EVENT_DATA_SIZE..) {
+        let mut raw_section = BpfRawSection::default();
+        if plain::copy_from_bytes(&mut raw_section.header, tail).is_ok()
+            && raw_section.header.size != 0
+        {
+            let start = mem::size_of_val(&raw_section.header);
+            let end = start + raw_section.header.size as usize;
+
+            match (
+                FactoryId::from_u8(raw_section.header.owner),
+                tail.get(start..end),
+            ) {
+                (Ok(owner), Some(data)) => {
+                    raw_section.data = data;
+                    raw_sections
+                        .entry(owner)
+                        .or_insert(Vec::new())
+                        .push(raw_section);
+                }
+                (Err(e), _) => error!("Could not convert the raw owner: {e}"),
+                (_, None) => error!(
+                    "Trailing section goes past the buffer: {end} > {}",
+                    tail.len()
+                ),
+            }
+        }
+    }
+
     let mut event = Event::new();
     raw_sections.drain().try_for_each(|(owner, sections)| {
         let factory = factories
@@ -508,16 +539,39 @@ pub(super) const BPF_EVENTS_MAX: u32 = 8 * 1024;
 /// counterpart.
 pub(crate) const BPF_RAW_EVENT_DATA_SIZE: usize = 1024 - 2 /* remove the size field */;
 
+/// Max size of the trailing section data of a BPF event. Please keep synced with
+/// its BPF counterpart.
+pub(crate) const BPF_EVENT_TAIL_MAX: usize = 2048;
+
 /// Raw event format shared between the Rust and BPF part. Please keep in sync
 /// with its BPF counterpart.
 #[repr(C, packed)]
 pub(crate) struct RawEvent {
     pub(crate) size: u16,
     pub(crate) data: [u8; BPF_RAW_EVENT_DATA_SIZE],
+    pub(crate) tail: RawEventTail,
 }
 
 unsafe impl Plain for RawEvent {}
 
+/// Trailing section of a raw event, for data not fitting in its data buffer
+/// (e.g. packet data). Only used when its size is not 0. Please keep in sync
+/// with its BPF counterpart.
+#[repr(C, packed)]
+pub(crate) struct RawEventTail {
+    pub(crate) header: BpfRawSectionHeader,
+    pub(crate) data: [u8; BPF_EVENT_TAIL_MAX],
+}
+
+impl Default for RawEventTail {
+    fn default() -> Self {
+        Self {
+            header: BpfRawSectionHeader::default(),
+            data: [0; BPF_EVENT_TAIL_MAX],
+        }
+    }
+}
+
 /// Raw event section format shared between the Rust and BPF part. Please keep
 /// in sync with its BPF counterpart.
 #[derive(Clone, Default)]
@@ -808,5 +862,32 @@ mod tests {
         let section = event.get_section::<TestEvent>(SectionId::Common).unwrap();
         assert!(section.field1 == Some(42));
         assert!(section.field2 == Some(1337));
+
+        // Valid event, with a trailing section.
+        let mut raw = RawEvent {
+            size: 12,
+            data: [0; BPF_RAW_EVENT_DATA_SIZE],
+            tail: RawEventTail::default(),
+        };
+        raw.data[..4].copy_from_slice(&[SectionId::Common as u8, DATA_TYPE_U64, 8, 0]);
+        raw.data[4] = 42;
+        raw.tail.header.owner = SectionId::Common as u8;
+        raw.tail.header.data_type = DATA_TYPE_U128;
+        raw.tail.header.size = 16;
+        raw.tail.data[0] = 42;
+        raw.tail.data[8..10].copy_from_slice(&[57, 5]);
+        let data = unsafe { plain::as_bytes(&raw) };
+        let event = super::parse_raw_event(data, &mut factories).unwrap();
+        let section = event.get_section::<TestEvent>(SectionId::Common).unwrap();
+        assert!(section.field0 == Some(42));
+        assert!(section.field1 == Some(42));
+        assert!(section.field2 == Some(1337));
+
+        // Trailing section going past the buffer. Section is ignored.
+        raw.tail.header.size = (BPF_EVENT_TAIL_MAX + 1) as u16;
+        let data = unsafe { plain::as_bytes(&raw) };
+        let event = super::parse_raw_event(data, &mut factories).unwrap();
+        let section = event.get_section::<TestEvent>(SectionId::Common).unwrap();
+        assert!(section.field1.is_none());
     }
 }
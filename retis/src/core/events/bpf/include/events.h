@@ -8,6 +8,7 @@
 /* Please keep the below in sync with its Rust counterpart. */
 #define EVENTS_MAX		8 * 1024
 #define RAW_EVENT_DATA_SIZE	1024 - 2 /* Remove the size field */
+#define EVENT_TAIL_MAX		2048
 #define RETIS_MAX_COMM		64
 
 /* Please keep the below in sync with its Rust counterpart. */
@@ -34,11 +35,6 @@ enum retis_event_owners {
 	COLLECTOR_CT = 9,
 };
 
-struct retis_raw_event {
-	u16 size;
-	u8 data[RAW_EVENT_DATA_SIZE];
-} __packed;
-
 /* Please keep synced with its Rust counterpart. */
 struct retis_raw_event_section_header {
 	u8 owner;
@@ -46,6 +42,18 @@ struct retis_raw_event_section_header {
 	u16 size;
 } __packed;
 
+/* Trailing section of an event, see get_event_tail(). */
+struct retis_event_tail {
+	struct retis_raw_event_section_header header;
+	u8 data[EVENT_TAIL_MAX];
+} __packed;
+
+struct retis_raw_event {
+	u16 size;
+	u8 data[RAW_EVENT_DATA_SIZE];
+	struct retis_event_tail tail;
+} __packed;
+
 /* Please keep synced with its Rust counterpart. */
 struct {
 	__uint(type, BPF_MAP_TYPE_RINGBUF);
@@ -67,6 +75,7 @@ static __always_inline struct retis_raw_event *get_event()
 		return NULL;
 
 	event->size = 0;
+	event->tail.header.size = 0;
 	return event;
 }
 
@@ -114,6 +123,22 @@ static __always_inline void *get_event_section(struct retis_raw_event *event,
 	return section;
 }
 
+/* Get the trailing section of an event, for data not fitting in the raw event
+ * data (e.g. packet data). It holds up to EVENT_TAIL_MAX bytes and is only
+ * reported if its size is set. A single trailing section can be used per
+ * event.
+ */
+static __always_inline struct retis_event_tail *
+get_event_tail(struct retis_raw_event *event, u8 owner, u8 data_type)
+{
+	if (event->tail.header.size)
+		return NULL;
+
+	event->tail.header.owner = owner;
+	event->tail.header.data_type = data_type;
+	return &event->tail;
+}
+
 /* Similar to get_event_section but initialize the section data to 0s. */
 static __always_inline void *get_event_zsection(struct retis_raw_event *event,
 						u8 owner, u8 data_type, const u16 size)
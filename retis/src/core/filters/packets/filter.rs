@@ -9,7 +9,7 @@
 use std::mem;
 
 use anyhow::{bail, Result};
-use pcap::{Capture, Linktype};
+use pcap::{BpfProgram, Capture, Linktype};
 
 use super::ebpfinsn::{eBpfInsn, MovInfo};
 
@@ -21,6 +21,21 @@ use crate::{
     },
 };
 
+/// Compiles a pcap-filter string to a cBPF program using libpcap.
+fn compile(fstring: &str, layer_type: packet_filter_uapi::filter_type) -> Result<BpfProgram> {
+    let link_type = match layer_type {
+        packet_filter_uapi::L3 => Linktype(12), // DLT_RAW
+        packet_filter_uapi::L2 => Linktype::ETHERNET,
+        _ => bail!("Unsupported filter type"),
+    };
+
+    let bpf_capture = Capture::dead(link_type)?;
+    match bpf_capture.compile(fstring, true) {
+        Ok(program) => Ok(program),
+        Err(e) => bail!("Could not compile the filter: {e}"),
+    }
+}
+
 #[derive(Clone)]
 pub(crate) struct FilterPacket(eBpfProg);
 
@@ -29,17 +44,7 @@ impl FilterPacket {
         fstring: String,
         layer_type: packet_filter_uapi::filter_type,
     ) -> Result<Self> {
-        let link_type = match layer_type {
-            packet_filter_uapi::L3 => Linktype(12), // DLT_RAW
-            packet_filter_uapi::L2 => Linktype::ETHERNET,
-            _ => bail!("Unsupported filter type"),
-        };
-
-        let bpf_capture = Capture::dead(link_type)?;
-        let program = match bpf_capture.compile(fstring.as_str(), true) {
-            Ok(program) => program,
-            Err(e) => bail!("Could not compile the filter: {e}"),
-        };
+        let program = compile(fstring.as_str(), layer_type)?;
         let insns = program.get_instructions();
         let filter =
             BpfProg::try_from(unsafe { mem::transmute::<&[pcap::BpfInstruction], &[u8]>(insns) })?;
@@ -69,3 +74,18 @@ impl FilterPacket {
         Ok(self.0.to_bytes())
     }
 }
+
+/// Matches raw packets (starting at the Ethernet header) against a pcap-filter
+/// string, in userspace. Used to filter stored events at post-processing time.
+pub(crate) struct PacketMatcher(BpfProgram);
+
+impl PacketMatcher {
+    pub(crate) fn from_string(fstring: &str) -> Result<Self> {
+        Ok(PacketMatcher(compile(fstring, packet_filter_uapi::L2)?))
+    }
+
+    /// Returns true if the packet matches the filter.
+    pub(crate) fn matches(&self, packet: &[u8]) -> bool {
+        self.0.filter(packet)
+    }
+}
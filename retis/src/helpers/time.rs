@@ -1,4 +1,6 @@
-use anyhow::{bail, Result};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
 use nix::time::{clock_gettime, ClockId};
 
 use crate::events::TimeSpec;
@@ -23,3 +25,75 @@ pub(crate) fn monotonic_clock_offset() -> Result<TimeSpec> {
 
     Ok(TimeSpec::new(offset.tv_sec(), offset.tv_nsec()))
 }
+
+/// Parses a duration from a string. The duration is made of an integer,
+/// optionally followed by a unit: "ms", "s" (the default), "m" or "h".
+pub(crate) fn parse_duration(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    let pos = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (value, unit) = s.split_at(pos);
+
+    let value = value
+        .parse::<u64>()
+        .map_err(|_| anyhow!("Invalid duration '{s}'"))?;
+
+    let secs = |mult: u64| {
+        value
+            .checked_mul(mult)
+            .map(Duration::from_secs)
+            .ok_or_else(|| anyhow!("Duration '{s}' is too large"))
+    };
+
+    Ok(match unit.trim() {
+        "ms" => Duration::from_millis(value),
+        "" | "s" => Duration::from_secs(value),
+        "m" => secs(60)?,
+        "h" => secs(3600)?,
+        unit => bail!("Invalid duration unit '{unit}' (expected ms, s, m or h)"),
+    })
+}
+
+/// Formats a duration in nanoseconds using the most appropriate unit.
+pub(crate) fn format_ns(ns: u64) -> String {
+    match ns {
+        0..=999 => format!("{ns}ns"),
+        1_000..=999_999 => format!("{:.3}us", ns as f64 / 1e3),
+        1_000_000..=999_999_999 => format!("{:.3}ms", ns as f64 / 1e6),
+        _ => format!("{:.3}s", ns as f64 / 1e9),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    #[test]
+    fn parse_duration() {
+        assert_eq!(
+            super::parse_duration("10").unwrap(),
+            Duration::from_secs(10)
+        );
+        assert_eq!(
+            super::parse_duration("10s").unwrap(),
+            Duration::from_secs(10)
+        );
+        assert_eq!(
+            super::parse_duration("500ms").unwrap(),
+            Duration::from_millis(500)
+        );
+        assert_eq!(
+            super::parse_duration("5m").unwrap(),
+            Duration::from_secs(300)
+        );
+        assert_eq!(
+            super::parse_duration("2h").unwrap(),
+            Duration::from_secs(7200)
+        );
+        assert!(super::parse_duration("").is_err());
+        assert!(super::parse_duration("s").is_err());
+        assert!(super::parse_duration("10d").is_err());
+        assert!(super::parse_duration("-1s").is_err());
+        assert!(super::parse_duration(&format!("{}m", u64::MAX / 60 + 1)).is_err());
+        assert!(super::parse_duration(&format!("{}h", u64::MAX)).is_err());
+    }
+}
@@ -0,0 +1,1283 @@
+//! # Anonymize
+//!
+//! Anonymize is a post-processing command rewriting identifying information
+//! (addresses, task names, paths) of events, so event files can be shared
+//! outside of the environment they were collected in.
+
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    fs::OpenOptions,
+    hash::{BuildHasher, Hasher},
+    io::BufWriter,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Result};
+use clap::Parser;
+
+use crate::{
+    cli::*,
+    events::{
+        file::{FileEventsFactory, FileType},
+        *,
+    },
+    helpers::{net::parse_eth_addr, signals::Running},
+    process::display::*,
+};
+
+/// Updates an Internet checksum (RFC 1624) for data changing from `old` to
+/// `new`. Both must have the same length and start at an even offset of the
+/// checksummed data.
+fn checksum_adjust(csum: u16, old: &[u8], new: &[u8]) -> u16 {
+    let words = |data: &[u8]| {
+        data.chunks(2)
+            .map(|c| u16::from_be_bytes([c[0], *c.get(1).unwrap_or(&0)]))
+            .collect::<Vec<_>>()
+    };
+
+    let mut sum = !csum as u32;
+    for (old, new) in words(old).into_iter().zip(words(new)) {
+        sum += !old as u32 + new as u32;
+    }
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// SipHash-2-4 of `data` using the 128-bit key (`k0`, `k1`). A fully specified
+/// keyed hash is used (unlike std's `DefaultHasher`) so a given key maps
+/// addresses the same way regardless of the Rust version Retis is built with.
+fn siphash24((k0, k1): (u64, u64), data: &[u8]) -> u64 {
+    let mut v = [
+        k0 ^ 0x736f6d6570736575,
+        k1 ^ 0x646f72616e646f6d,
+        k0 ^ 0x6c7967656e657261,
+        k1 ^ 0x7465646279746573,
+    ];
+    let round = |v: &mut [u64; 4]| {
+        v[0] = v[0].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(13) ^ v[0];
+        v[0] = v[0].rotate_left(32);
+        v[2] = v[2].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(16) ^ v[2];
+        v[0] = v[0].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(21) ^ v[0];
+        v[2] = v[2].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(17) ^ v[2];
+        v[2] = v[2].rotate_left(32);
+    };
+    let compress = |v: &mut [u64; 4], m: u64| {
+        v[3] ^= m;
+        round(v);
+        round(v);
+        v[0] ^= m;
+    };
+
+    let chunks = data.chunks_exact(8);
+    let mut last = [0; 8];
+    last[..chunks.remainder().len()].copy_from_slice(chunks.remainder());
+    last[7] = data.len() as u8;
+
+    for chunk in chunks {
+        compress(&mut v, u64::from_le_bytes(chunk.try_into().unwrap()));
+    }
+    compress(&mut v, u64::from_le_bytes(last));
+
+    v[2] ^= 0xff;
+    (0..4).for_each(|_| round(&mut v));
+    v[0] ^ v[1] ^ v[2] ^ v[3]
+}
+
+fn read_u16(buf: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([buf[offset], buf[offset + 1]])
+}
+
+/// Rewrites addresses and other identifying information of events.
+///
+/// Addresses are mapped consistently: the same address is always rewritten
+/// the same way. IP addresses are anonymized in a prefix-preserving way (two
+/// addresses sharing an n-bit prefix are mapped to addresses sharing an n-bit
+/// prefix), so subnets can still be told apart.
+struct Anonymizer {
+    /// Key of the mapping.
+    key: (u64, u64),
+    /// Strip task names.
+    strip_tasks: bool,
+    /// Strip the directories of userspace binaries.
+    strip_paths: bool,
+    ipv4: HashMap<u32, u32>,
+    ipv6: HashMap<u128, u128>,
+    macs: HashMap<[u8; 6], [u8; 6]>,
+}
+
+impl Anonymizer {
+    /// Creates an Anonymizer. Without a key, a random one is used and the
+    /// mapping differs between runs.
+    fn new(key: Option<&str>, strip_tasks: bool, strip_paths: bool) -> Self {
+        let key = match key {
+            Some(key) => (
+                siphash24((0, 0), key.as_bytes()),
+                siphash24((0, 1), key.as_bytes()),
+            ),
+            None => (
+                RandomState::new().build_hasher().finish(),
+                RandomState::new().build_hasher().finish(),
+            ),
+        };
+
+        Self {
+            key,
+            strip_tasks,
+            strip_paths,
+            ipv4: HashMap::new(),
+            ipv6: HashMap::new(),
+            macs: HashMap::new(),
+        }
+    }
+
+    /// Keyed pseudo-random function.
+    fn prf(&self, data: &[u8]) -> u64 {
+        siphash24(self.key, data)
+    }
+
+    /// Prefix-preserving anonymization of an address of `bits` bits: each bit
+    /// is flipped depending on the bits preceding it only.
+    fn prefix_preserving(&self, addr: u128, bits: u32) -> u128 {
+        let mut anon = 0;
+        for i in 0..bits {
+            let prefix = match i {
+                0 => 0,
+                _ => addr >> (bits - i),
+            };
+            let mut data = [0; 24];
+            data[..4].copy_from_slice(&bits.to_be_bytes());
+            data[4..8].copy_from_slice(&i.to_be_bytes());
+            data[8..].copy_from_slice(&prefix.to_be_bytes());
+            let flip = (self.prf(&data) & 1) as u128;
+            anon = (anon << 1) | (((addr >> (bits - 1 - i)) & 1) ^ flip);
+        }
+        anon
+    }
+
+    fn ipv4(&mut self, addr: Ipv4Addr) -> Ipv4Addr {
+        if addr.is_unspecified() || addr.is_broadcast() {
+            return addr;
+        }
+
+        let raw = u32::from(addr);
+        let anon = match self.ipv4.get(&raw) {
+            Some(anon) => *anon,
+            None => {
+                let anon = self.prefix_preserving(raw as u128, 32) as u32;
+                self.ipv4.insert(raw, anon);
+                anon
+            }
+        };
+        Ipv4Addr::from(anon)
+    }
+
+    fn ipv6(&mut self, addr: Ipv6Addr) -> Ipv6Addr {
+        if addr.is_unspecified() {
+            return addr;
+        }
+
+        let raw = u128::from(addr);
+        let anon = match self.ipv6.get(&raw) {
+            Some(anon) => *anon,
+            None => {
+                let anon = self.prefix_preserving(raw, 128);
+                self.ipv6.insert(raw, anon);
+                anon
+            }
+        };
+        Ipv6Addr::from(anon)
+    }
+
+    /// Anonymizes an Ethernet address. The all-zeros (e.g. fake Ethernet
+    /// headers) and broadcast addresses are kept, the multicast bit is kept and
+    /// the result is a locally administered address.
+    fn mac(&mut self, mac: [u8; 6]) -> [u8; 6] {
+        if mac == [0; 6] || mac == [0xff; 6] {
+            return mac;
+        }
+
+        if let Some(anon) = self.macs.get(&mac) {
+            return *anon;
+        }
+
+        let hash = self.prf(&[b"mac".as_slice(), &mac].concat()).to_be_bytes();
+        let mut anon = [0; 6];
+        anon.copy_from_slice(&hash[2..]);
+        anon[0] = (anon[0] & 0xfc) | 0x02 | (mac[0] & 0x01);
+
+        self.macs.insert(mac, anon);
+        anon
+    }
+
+    /// Anonymizes an IP address stored as a string. Invalid addresses are
+    /// left untouched.
+    fn ip_str(&mut self, addr: &mut String) {
+        if let Ok(ip) = addr.parse::<IpAddr>() {
+            *addr = match ip {
+                IpAddr::V4(ip) => self.ipv4(ip).to_string(),
+                IpAddr::V6(ip) => self.ipv6(ip).to_string(),
+            };
+        }
+    }
+
+    /// Anonymizes an Ethernet address stored as a string. Invalid addresses
+    /// are left untouched.
+    fn mac_str(&mut self, addr: &mut String) {
+        let bytes = addr
+            .split(':')
+            .map(|b| u8::from_str_radix(b, 16))
+            .collect::<Result<Vec<_>, _>>();
+
+        if let Ok(Ok(mac)) = bytes.map(<[u8; 6]>::try_from) {
+            if let Ok(anon) = parse_eth_addr(&self.mac(mac)) {
+                *addr = anon;
+            }
+        }
+    }
+
+    fn skb_ip(&mut self, ip: &mut SkbIpEvent) {
+        self.ip_str(&mut ip.saddr);
+        self.ip_str(&mut ip.daddr);
+
+        match &mut ip.version {
+            // Record route and timestamp options hold addresses too.
+            SkbIpVersion::V4 { v4 } => v4
+                .options
+                .iter_mut()
+                .flatten()
+                .filter_map(|o| o.addrs.as_mut())
+                .flatten()
+                .for_each(|addr| self.ip_str(addr)),
+            // Segment routing headers hold addresses too.
+            SkbIpVersion::V6 { v6 } => v6
+                .ext_headers
+                .iter_mut()
+                .flatten()
+                .filter_map(|h| h.segments.as_mut())
+                .flatten()
+                .for_each(|seg| self.ip_str(seg)),
+        }
+    }
+
+    /// Anonymizes the addresses of ICMP and ICMPv6 sections: the packet
+    /// embedded in errors and the target of neighbor discovery messages.
+    fn skb_icmp(&mut self, icmp: Option<&mut SkbIcmpEvent>, icmpv6: Option<&mut SkbIcmpV6Event>) {
+        if let Some(orig) = icmp.and_then(|icmp| icmp.orig.as_mut()) {
+            self.skb_ip(&mut orig.ip);
+        }
+
+        if let Some(icmpv6) = icmpv6 {
+            if let Some(orig) = &mut icmpv6.orig {
+                self.skb_ip(&mut orig.ip);
+            }
+            if let Some(target) = &mut icmpv6.target {
+                self.ip_str(target);
+            }
+        }
+    }
+
+    /// Anonymizes the address a reverse DNS lookup name (in-addr.arpa or
+    /// ip6.arpa) is made of. Other names are left untouched.
+    fn dns_name(&mut self, name: &mut String) {
+        let lower = name.to_ascii_lowercase();
+        let (lower, dot) = match lower.strip_suffix('.') {
+            Some(lower) => (lower, "."),
+            None => (lower.as_str(), ""),
+        };
+
+        let anon = if let Some(rev) = lower.strip_suffix(".in-addr.arpa") {
+            let octets = rev
+                .split('.')
+                .rev()
+                .map(|o| o.parse::<u8>().ok())
+                .collect::<Option<Vec<_>>>();
+            let Some(Ok(octets)) = octets.map(<[u8; 4]>::try_from) else {
+                return;
+            };
+
+            let anon = self.ipv4(Ipv4Addr::from(octets)).octets();
+            format!(
+                "{}.{}.{}.{}.in-addr.arpa",
+                anon[3], anon[2], anon[1], anon[0]
+            )
+        } else if let Some(rev) = lower.strip_suffix(".ip6.arpa") {
+            let nibbles = rev
+                .split('.')
+                .rev()
+                .map(|n| match n.len() {
+                    1 => u8::from_str_radix(n, 16).ok(),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>();
+            let Some(nibbles) = nibbles.filter(|n| n.len() == 32) else {
+                return;
+            };
+
+            let raw = nibbles.iter().fold(0, |raw, n| (raw << 4) | *n as u128);
+            let anon = u128::from(self.ipv6(Ipv6Addr::from(raw)));
+            (0..32)
+                .map(|i| format!("{:x}.", (anon >> (i * 4)) & 0xf))
+                .collect::<String>()
+                + "ip6.arpa"
+        } else {
+            return;
+        };
+
+        *name = anon + dot;
+    }
+
+    fn ct_conn(&mut self, conn: &mut CtConnEvent) {
+        for tuple in [&mut conn.orig, &mut conn.reply] {
+            self.ip_str(&mut tuple.ip.src);
+            self.ip_str(&mut tuple.ip.dst);
+        }
+    }
+
+    /// Anonymizes an event in place.
+    fn event(&mut self, event: &mut Event) {
+        if let Some(skb) = event.get_section_mut::<SkbEvent>(SectionId::Skb) {
+            if let Some(eth) = &mut skb.eth {
+                self.mac_str(&mut eth.src);
+                self.mac_str(&mut eth.dst);
+            }
+            if let Some(arp) = &mut skb.arp {
+                self.mac_str(&mut arp.sha);
+                self.ip_str(&mut arp.spa);
+                self.mac_str(&mut arp.tha);
+                self.ip_str(&mut arp.tpa);
+            }
+            if let Some(ip) = &mut skb.ip {
+                self.skb_ip(ip);
+            }
+            self.skb_icmp(skb.icmp.as_mut(), skb.icmpv6.as_mut());
+            if let Some(inner) = &mut skb.inner {
+                if let Some(eth) = &mut inner.eth {
+                    self.mac_str(&mut eth.src);
+                    self.mac_str(&mut eth.dst);
+                }
+                if let Some(ip) = &mut inner.ip {
+                    self.skb_ip(ip);
+                }
+                self.skb_icmp(inner.icmp.as_mut(), inner.icmpv6.as_mut());
+            }
+            if let Some(packet) = &mut skb.packet {
+                self.packet(&mut packet.packet.0);
+            }
+            if let Some(qname) = skb
+                .app
+                .as_mut()
+                .and_then(|app| app.dns.as_mut())
+                .and_then(|dns| dns.qname.as_mut())
+            {
+                self.dns_name(qname);
+            }
+        }
+
+        if let Some(ct) = event.get_section_mut::<CtEvent>(SectionId::Ct) {
+            self.ct_conn(&mut ct.base);
+            if let Some(parent) = &mut ct.parent {
+                self.ct_conn(parent);
+            }
+        }
+
+        if let Some(OvsEvent::Action { action_execute }) =
+            event.get_section_mut::<OvsEvent>(SectionId::Ovs)
+        {
+            if let Some(OvsAction::Ct {
+                ct: OvsActionCt { nat: Some(nat), .. },
+            }) = &mut action_execute.action
+            {
+                for addr in [&mut nat.min_addr, &mut nat.max_addr].into_iter().flatten() {
+                    self.ip_str(addr);
+                }
+            }
+        }
+
+        if self.strip_tasks {
+            if let Some(task) = event
+                .get_section_mut::<CommonEvent>(SectionId::Common)
+                .and_then(|c| c.task.as_mut())
+            {
+                task.comm = "-".to_string();
+            }
+        }
+
+        if self.strip_paths {
+            if let Some(user) = event.get_section_mut::<UserEvent>(SectionId::Userspace) {
+                if let Some(name) = Path::new(&user.path).file_name() {
+                    user.path = name.to_string_lossy().to_string();
+                }
+            }
+        }
+    }
+
+    /// Anonymizes a raw packet starting at its Ethernet header, fixing
+    /// checksums.
+    fn packet(&mut self, data: &mut [u8]) {
+        if data.len() < 14 {
+            return;
+        }
+
+        for range in [0..6, 6..12] {
+            let mut mac = [0; 6];
+            mac.copy_from_slice(&data[range.clone()]);
+            data[range].copy_from_slice(&self.mac(mac));
+        }
+
+        let mut etype = read_u16(data, 12);
+        let mut offset = 14;
+        while matches!(etype, 0x8100 | 0x88a8 | 0x9100) && data.len() >= offset + 4 {
+            etype = read_u16(data, offset + 2);
+            offset += 4;
+        }
+
+        // MPLS payloads are guessed from the IP version field.
+        if matches!(etype, 0x8847 | 0x8848) {
+            while data.len() >= offset + 4 {
+                let bos = data[offset + 2] & 0x1 != 0;
+                offset += 4;
+                if bos {
+                    etype = match data.get(offset).map(|b| b >> 4) {
+                        Some(4) => 0x0800,
+                        Some(6) => 0x86dd,
+                        _ => 0,
+                    };
+                    break;
+                }
+            }
+        }
+
+        match etype {
+            0x0800 | 0x86dd => self.ip_packet(&mut data[offset..]),
+            0x0806 => self.arp_packet(&mut data[offset..]),
+            _ => (),
+        }
+    }
+
+    fn arp_packet(&mut self, data: &mut [u8]) {
+        // Only Ethernet/IPv4 ARP packets are supported.
+        if data.len() < 28 || read_u16(data, 0) != 1 || read_u16(data, 2) != 0x0800 {
+            return;
+        }
+
+        for (mac, ip) in [(8, 14), (18, 24)] {
+            let mut raw = [0; 6];
+            raw.copy_from_slice(&data[mac..(mac + 6)]);
+            data[mac..(mac + 6)].copy_from_slice(&self.mac(raw));
+
+            let mut raw = [0; 4];
+            raw.copy_from_slice(&data[ip..(ip + 4)]);
+            data[ip..(ip + 4)].copy_from_slice(&self.ipv4(Ipv4Addr::from(raw)).octets());
+        }
+    }
+
+    fn ip_packet(&mut self, data: &mut [u8]) {
+        match data.first().map(|b| b >> 4) {
+            Some(4) => self.ipv4_packet(data),
+            Some(6) => self.ipv6_packet(data),
+            _ => (),
+        }
+    }
+
+    fn ipv4_packet(&mut self, data: &mut [u8]) {
+        let ihl = (data[0] & 0xf) as usize * 4;
+        if ihl < 20 || data.len() < ihl {
+            return;
+        }
+
+        let header = data[..ihl].to_vec();
+        let mut addrs = vec![12, 16];
+        addrs.extend(Self::ipv4_options_addrs(&data[20..ihl]).map(|o| o + 20));
+        for offset in addrs {
+            let mut raw = [0; 4];
+            raw.copy_from_slice(&data[offset..(offset + 4)]);
+            data[offset..(offset + 4)].copy_from_slice(&self.ipv4(Ipv4Addr::from(raw)).octets());
+        }
+
+        let csum = checksum_adjust(read_u16(data, 10), &header, &data[..ihl]);
+        data[10..12].copy_from_slice(&csum.to_be_bytes());
+
+        let (old, new) = (&header[12..20], data[12..20].to_vec());
+
+        // Only the first fragment holds the L4 header.
+        if read_u16(data, 6) & 0x1fff == 0 {
+            self.l4_packet(data[9], &mut data[ihl..], old, &new);
+        }
+    }
+
+    /// Returns the offsets of the addresses found in IPv4 options: record
+    /// route, source routes and timestamps with addresses.
+    fn ipv4_options_addrs(mut data: &[u8]) -> impl Iterator<Item = usize> {
+        let mut addrs = Vec::new();
+        let mut offset = 0;
+
+        while let Some(&r#type) = data.first() {
+            let len = match r#type {
+                0 => break,
+                1 => 1,
+                _ if data.len() < 2 || data[1] < 2 || data.len() < data[1] as usize => break,
+                _ => data[1] as usize,
+            };
+
+            // Addresses (4B) follow the pointer (1B), or are interleaved
+            // with the timestamps (4B) after the overflow/flags (1B).
+            let (start, step) = match r#type {
+                7 | 131 | 137 => (3, 4),
+                68 if len >= 4 && matches!(data[3] & 0xf, 1 | 3) => (4, 8),
+                _ => (len, 4),
+            };
+            addrs.extend(
+                (start..len)
+                    .step_by(step)
+                    .filter(|o| o + 4 <= len)
+                    .map(|o| offset + o),
+            );
+
+            data = &data[len..];
+            offset += len;
+        }
+
+        addrs.into_iter()
+    }
+
+    fn ipv6_packet(&mut self, data: &mut [u8]) {
+        if data.len() < 40 {
+            return;
+        }
+
+        // Walk the extension headers, looking for addresses in segment
+        // routing headers and for the L4 header.
+        let (mut addrs, mut dst) = (vec![8, 24], 24);
+        let (mut next, mut offset, mut l4) = (data[6], 40, true);
+        while matches!(next, 0 | 43 | 44 | 51 | 60) && data.len() >= offset + 8 {
+            let len = match next {
+                44 => 8,
+                51 => (data[offset + 1] as usize + 2) * 4,
+                _ => (data[offset + 1] as usize + 1) * 8,
+            };
+            if data.len() < offset + len {
+                l4 = false;
+                break;
+            }
+
+            match next {
+                // Only the first fragment holds the L4 header.
+                44 if read_u16(data, offset + 2) & 0xfff8 != 0 => l4 = false,
+                // Segments (16B) follow the first 8B of the segment routing
+                // header, the first one being the final destination.
+                43 if data[offset + 2] == 4 => {
+                    let segments = (data[offset + 4] as usize + 1).min((len - 8) / 16);
+                    if segments > 0 {
+                        dst = offset + 8;
+                    }
+                    addrs.extend((0..segments).map(|i| offset + 8 + i * 16));
+                }
+                _ => (),
+            }
+
+            next = data[offset];
+            offset += len;
+        }
+
+        // The L4 pseudo-header is made of the source and final destination.
+        let pseudo_header = |data: &[u8]| [&data[8..24], &data[dst..(dst + 16)]].concat();
+        let old = pseudo_header(data);
+        for addr in addrs {
+            let mut raw = [0; 16];
+            raw.copy_from_slice(&data[addr..(addr + 16)]);
+            data[addr..(addr + 16)].copy_from_slice(&self.ipv6(Ipv6Addr::from(raw)).octets());
+        }
+        let new = pseudo_header(data);
+
+        if l4 {
+            self.l4_packet(next, &mut data[offset..], &old, &new);
+        }
+    }
+
+    /// Fixes the checksum of an L4 packet after its IP addresses changed from
+    /// `old` to `new`. The embedded packet of ICMP errors and the encapsulated
+    /// packet of tunnels are anonymized too.
+    fn l4_packet(&mut self, protocol: u8, data: &mut [u8], old: &[u8], new: &[u8]) {
+        // Offset of the checksum and whether it covers the IP addresses.
+        let (offset, pseudo_header) = match protocol {
+            1 => (2, false),
+            6 => (16, true),
+            17 => (6, true),
+            58 => (2, true),
+            // IP in IP.
+            4 | 41 => return self.ip_packet(data),
+            47 => return self.gre_packet(data),
+            _ => return,
+        };
+        if data.len() < offset + 2 {
+            return;
+        }
+
+        // ICMP errors embed the beginning of the packet that triggered them.
+        let orig = data.to_vec();
+        match protocol {
+            1 if matches!(data[0], 3 | 4 | 5 | 11 | 12) && data.len() > 8 => {
+                self.ip_packet(&mut data[8..])
+            }
+            58 if (1..=4).contains(&data[0]) && data.len() > 8 => self.ip_packet(&mut data[8..]),
+            58 if (133..=137).contains(&data[0]) => self.ndisc_packet(data),
+            17 if data.len() > 8 => self.udp_payload(read_u16(data, 2), &mut data[8..]),
+            _ => (),
+        }
+
+        let mut csum = read_u16(data, offset);
+        // A zero UDP checksum means no checksum was computed.
+        if protocol == 17 && csum == 0 {
+            return;
+        }
+
+        if pseudo_header {
+            csum = checksum_adjust(csum, old, new);
+        }
+        csum = checksum_adjust(csum, &orig, data);
+
+        if protocol == 17 && csum == 0 {
+            csum = 0xffff;
+        }
+        data[offset..(offset + 2)].copy_from_slice(&csum.to_be_bytes());
+    }
+
+    /// Anonymizes the target (and destination) addresses of neighbor discovery
+    /// messages and the link-layer addresses found in their options.
+    fn ndisc_packet(&mut self, data: &mut [u8]) {
+        // Offsets of the addresses and of the options, per message type.
+        let (addrs, options): (&[usize], usize) = match data[0] {
+            // Router solicitation and advertisement.
+            133 => (&[], 8),
+            134 => (&[], 16),
+            // Neighbor solicitation and advertisement.
+            135 | 136 => (&[8], 24),
+            // Redirect.
+            _ => (&[8, 24], 40),
+        };
+        if data.len() < options {
+            return;
+        }
+
+        for &offset in addrs {
+            let mut raw = [0; 16];
+            raw.copy_from_slice(&data[offset..(offset + 16)]);
+            data[offset..(offset + 16)].copy_from_slice(&self.ipv6(Ipv6Addr::from(raw)).octets());
+        }
+
+        // Options: type (1B), length (1B, in 8B units) and data. Source and
+        // target link-layer address options hold a MAC address.
+        let mut offset = options;
+        while data.len() >= offset + 8 && data[offset + 1] != 0 {
+            if matches!(data[offset], 1 | 2) && data[offset + 1] == 1 {
+                let mut mac = [0; 6];
+                mac.copy_from_slice(&data[(offset + 2)..(offset + 8)]);
+                data[(offset + 2)..(offset + 8)].copy_from_slice(&self.mac(mac));
+            }
+            offset += data[offset + 1] as usize * 8;
+        }
+    }
+
+    /// Anonymizes the packet encapsulated in UDP tunnels (VXLAN, Geneve).
+    fn udp_payload(&mut self, dport: u16, data: &mut [u8]) {
+        match dport {
+            4789 if data.len() > 8 => self.packet(&mut data[8..]),
+            6081 if data.len() > 8 => {
+                let hlen = 8 + (data[0] & 0x3f) as usize * 4;
+                if data.len() > hlen {
+                    self.encap_payload(read_u16(data, 2), &mut data[hlen..]);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Anonymizes the packet encapsulated in a GRE packet, fixing the GRE
+    /// checksum if any.
+    fn gre_packet(&mut self, data: &mut [u8]) {
+        if data.len() < 4 || data[1] & 0x7 != 0 {
+            return;
+        }
+
+        // Checksum, key and sequence number are optional.
+        let hlen = 4 + [0x80, 0x20, 0x10]
+            .iter()
+            .filter(|flag| data[0] & *flag != 0)
+            .count()
+            * 4;
+        if data.len() <= hlen {
+            return;
+        }
+
+        let orig = data.to_vec();
+        self.encap_payload(read_u16(data, 2), &mut data[hlen..]);
+
+        if data[0] & 0x80 != 0 {
+            let csum = checksum_adjust(read_u16(data, 4), &orig, data);
+            data[4..6].copy_from_slice(&csum.to_be_bytes());
+        }
+    }
+
+    /// Anonymizes a packet encapsulated in a tunnel, given its ethertype.
+    fn encap_payload(&mut self, etype: u16, data: &mut [u8]) {
+        match etype {
+            // Transparent Ethernet bridging.
+            0x6558 => self.packet(data),
+            0x0800 | 0x86dd => self.ip_packet(data),
+            _ => (),
+        }
+    }
+}
+
+/// Anonymize events
+///
+/// Reads events from the INPUT file (sorted or not) and rewrites their MAC and
+/// IP addresses, in the event sections and in the raw packets (fixing
+/// checksums). Addresses are rewritten consistently and IP addresses in a
+/// prefix-preserving way. Task names and userspace paths can optionally be
+/// stripped too.
+#[derive(Parser, Debug, Default)]
+#[command(name = "anonymize")]
+pub(crate) struct Anonymize {
+    /// File from which to read events.
+    #[arg(default_value = "retis.data")]
+    pub(super) input: PathBuf,
+
+    /// File to which the anonymized events are written.
+    #[arg(short, long)]
+    pub(super) out: PathBuf,
+
+    /// Key used to map addresses. Using the same key gives the same mapping
+    /// across runs (e.g. to anonymize multiple files consistently); a random
+    /// key is used otherwise. Anyone knowing the key can try to recover the
+    /// original addresses.
+    #[arg(long)]
+    pub(super) key: Option<String>,
+
+    /// Strip task names.
+    #[arg(long)]
+    pub(super) strip_tasks: bool,
+
+    /// Strip the directories of userspace binaries, only keeping their names.
+    #[arg(long)]
+    pub(super) strip_paths: bool,
+}
+
+impl SubCommandParserRunner for Anonymize {
+    fn run(&mut self, _: &MainConfig) -> Result<()> {
+        // If the file doesn't exist it is not the input.
+        let canonical = self.out.canonicalize().ok();
+        if canonical.is_some() && self.input.canonicalize().ok() == canonical {
+            bail!("Cannot write the anonymized events to the input file.");
+        }
+
+        let writer = Box::new(BufWriter::new(
+            OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(&self.out)
+                .or_else(|_| bail!("Could not create or open '{}'", self.out.display()))?,
+        ));
+
+        let mut anonymizer =
+            Anonymizer::new(self.key.as_deref(), self.strip_tasks, self.strip_paths);
+
+        // Create running instance that will handle signal termination.
+        let run = Running::new();
+        run.register_term_signals()?;
+
+        let mut factory = FileEventsFactory::new(self.input.as_path())?;
+        match factory.file_type() {
+            FileType::Event => {
+                let mut printer = PrintEvent::new(writer, PrintEventFormat::Json);
+                while run.running() {
+                    match factory.next_event()? {
+                        Some(mut event) => {
+                            anonymizer.event(&mut event);
+                            printer.process_one(&event)?;
+                        }
+                        None => break,
+                    }
+                }
+                printer.flush()?;
+            }
+            FileType::Series => {
+                let mut printer = PrintSeries::new(writer, PrintEventFormat::Json);
+                while run.running() {
+                    match factory.next_series()? {
+                        Some(mut series) => {
+                            series.events.iter_mut().for_each(|e| anonymizer.event(e));
+                            printer.process_one(&series)?;
+                        }
+                        None => break,
+                    }
+                }
+                printer.flush()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pnet_packet::{
+        ethernet::EthernetPacket, icmp::IcmpPacket, icmpv6::Icmpv6Packet, ipv4::Ipv4Packet,
+        ipv6::Ipv6Packet, tcp::TcpPacket, udp::UdpPacket, Packet,
+    };
+
+    use super::*;
+
+    #[test]
+    fn siphash() {
+        // Reference vectors from the SipHash paper.
+        let key = (0x0706050403020100, 0x0f0e0d0c0b0a0908);
+        assert_eq!(siphash24(key, &[]), 0x726fdb47dd0e0e31);
+        assert_eq!(
+            siphash24(key, &(0..15).collect::<Vec<u8>>()),
+            0xa129ca6149be45e5
+        );
+
+        // The mapping for a given key must not change across builds.
+        let mut anon = Anonymizer::new(Some("retis"), false, false);
+        assert_eq!(
+            anon.ipv4(Ipv4Addr::new(10, 0, 0, 1)),
+            Ipv4Addr::new(64, 248, 133, 52)
+        );
+    }
+
+    fn events(path: &str) -> Vec<Event> {
+        let mut factory = FileEventsFactory::new(Path::new(path)).unwrap();
+        let mut events = Vec::new();
+        while let Some(event) = factory.next_event().unwrap() {
+            events.push(event);
+        }
+        events
+    }
+
+    #[test]
+    fn anonymize_addresses() {
+        let mut anonymizer = Anonymizer::new(Some("key"), false, false);
+
+        let a = anonymizer.ipv4(Ipv4Addr::new(192, 168, 125, 10));
+        let b = anonymizer.ipv4(Ipv4Addr::new(192, 168, 125, 11));
+        let c = anonymizer.ipv4(Ipv4Addr::new(10, 0, 0, 1));
+        assert_ne!(a, Ipv4Addr::new(192, 168, 125, 10));
+        assert_eq!(a, anonymizer.ipv4(Ipv4Addr::new(192, 168, 125, 10)));
+        // Prefixes are preserved.
+        assert_eq!(u32::from(a) >> 1, u32::from(b) >> 1);
+        assert_ne!(u32::from(a) >> 24, u32::from(c) >> 24);
+        assert!(anonymizer.ipv4(Ipv4Addr::BROADCAST).is_broadcast());
+
+        let a = anonymizer.ipv6("2001:db8::1".parse().unwrap());
+        let b = anonymizer.ipv6("2001:db8::2".parse().unwrap());
+        assert_eq!(u128::from(a) >> 2, u128::from(b) >> 2);
+
+        let mac = anonymizer.mac([0xfa, 0x5c, 0xbd, 0x8e, 0xcc, 0x01]);
+        assert_eq!(mac[0] & 0x03, 0x02);
+        assert_eq!(anonymizer.mac([0x01, 0, 0x5e, 0, 0, 1])[0] & 0x03, 0x03);
+        assert_eq!(anonymizer.mac([0; 6]), [0; 6]);
+
+        // Same key, same mapping.
+        let mut other = Anonymizer::new(Some("key"), false, false);
+        assert_eq!(
+            other.ipv4(Ipv4Addr::new(10, 0, 0, 1)),
+            anonymizer.ipv4(Ipv4Addr::new(10, 0, 0, 1))
+        );
+    }
+
+    #[test]
+    fn anonymize_dns_names() {
+        let mut anonymizer = Anonymizer::new(Some("key"), false, false);
+
+        let anon = anonymizer.ipv4(Ipv4Addr::new(192, 168, 125, 10)).octets();
+        let mut name = "10.125.168.192.in-addr.arpa.".to_string();
+        anonymizer.dns_name(&mut name);
+        assert_eq!(
+            name,
+            format!(
+                "{}.{}.{}.{}.in-addr.arpa.",
+                anon[3], anon[2], anon[1], anon[0]
+            )
+        );
+
+        let anon = anonymizer.ipv6("2001:db8::1".parse().unwrap());
+        let mut name =
+            "1.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.B.D.0.1.0.0.2.IP6.ARPA".to_string();
+        anonymizer.dns_name(&mut name);
+        let nibbles = name.strip_suffix(".ip6.arpa").unwrap().replace('.', "");
+        assert_eq!(
+            u128::from_str_radix(&nibbles.chars().rev().collect::<String>(), 16).unwrap(),
+            u128::from(anon)
+        );
+
+        // Other names, including partial reverse names, are left untouched.
+        for name in ["example.com.", "168.192.in-addr.arpa.", "foo.in-addr.arpa"] {
+            let mut anon = name.to_string();
+            anonymizer.dns_name(&mut anon);
+            assert_eq!(anon, name);
+        }
+    }
+
+    #[test]
+    fn anonymize_events() {
+        let original = events("test_data/test_events_packets.json");
+        let mut anonymized = events("test_data/test_events_packets.json");
+        let mut anonymizer = Anonymizer::new(None, true, true);
+        anonymized.iter_mut().for_each(|e| anonymizer.event(e));
+
+        let mut packets = 0;
+        for (original, event) in original.iter().zip(anonymized.iter()) {
+            if let Some(task) = event
+                .get_section::<CommonEvent>(SectionId::Common)
+                .and_then(|c| c.task.as_ref())
+            {
+                assert_eq!(task.comm, "-");
+            }
+
+            let (Some(orig_skb), Some(skb)) = (
+                original.get_section::<SkbEvent>(SectionId::Skb),
+                event.get_section::<SkbEvent>(SectionId::Skb),
+            ) else {
+                continue;
+            };
+
+            let (orig_ip, ip) = (orig_skb.ip.as_ref().unwrap(), skb.ip.as_ref().unwrap());
+            assert_ne!(orig_ip.saddr, ip.saddr);
+            assert_ne!(orig_ip.daddr, ip.daddr);
+
+            if let Some(ct) = event.get_section::<CtEvent>(SectionId::Ct) {
+                assert!([&ip.saddr, &ip.daddr].contains(&&ct.base.orig.ip.src));
+            }
+
+            // Raw packets match the sections and have valid checksums.
+            let Some(packet) = &skb.packet else {
+                continue;
+            };
+            packets += 1;
+
+            let eth = EthernetPacket::new(&packet.packet.0).unwrap();
+            let ipv4 = Ipv4Packet::new(eth.payload()).unwrap();
+            assert_eq!(ipv4.get_source().to_string(), ip.saddr);
+            assert_eq!(ipv4.get_destination().to_string(), ip.daddr);
+            assert_eq!(ipv4.get_checksum(), pnet_packet::ipv4::checksum(&ipv4));
+
+            let icmp = IcmpPacket::new(ipv4.payload()).unwrap();
+            assert_eq!(icmp.get_checksum(), pnet_packet::icmp::checksum(&icmp));
+        }
+        assert!(packets > 0);
+    }
+
+    #[test]
+    fn anonymize_icmp_error() {
+        // ICMP port unreachable embedding a UDP packet, with valid checksums.
+        let mut inner = vec![
+            0x45, 0, 0, 28, 0, 1, 0, 0, 64, 17, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2, 0x9c, 0x40, 0, 53,
+            0, 8, 0, 0,
+        ];
+        let csum = pnet_packet::ipv4::checksum(&Ipv4Packet::new(&inner).unwrap());
+        inner[10..12].copy_from_slice(&csum.to_be_bytes());
+
+        let mut icmp = vec![3, 3, 0, 0, 0, 0, 0, 0];
+        icmp.extend_from_slice(&inner);
+        let csum = pnet_packet::icmp::checksum(&IcmpPacket::new(&icmp).unwrap());
+        icmp[2..4].copy_from_slice(&csum.to_be_bytes());
+
+        let mut outer = vec![
+            0x45, 0, 0, 56, 0, 2, 0, 0, 64, 1, 0, 0, 10, 0, 0, 2, 10, 0, 0, 1,
+        ];
+        outer.extend_from_slice(&icmp);
+        let csum = pnet_packet::ipv4::checksum(&Ipv4Packet::new(&outer).unwrap());
+        outer[10..12].copy_from_slice(&csum.to_be_bytes());
+
+        let mut anonymizer = Anonymizer::new(None, false, false);
+        anonymizer.ip_packet(&mut outer);
+
+        let ip = Ipv4Packet::new(&outer).unwrap();
+        assert_eq!(ip.get_checksum(), pnet_packet::ipv4::checksum(&ip));
+        let icmp = IcmpPacket::new(ip.payload()).unwrap();
+        assert_eq!(icmp.get_checksum(), pnet_packet::icmp::checksum(&icmp));
+
+        // Embedded addresses are consistent with the outer ones.
+        let inner = Ipv4Packet::new(&icmp.payload()[4..]).unwrap();
+        assert_eq!(inner.get_source(), ip.get_destination());
+        assert_eq!(inner.get_destination(), ip.get_source());
+        assert_eq!(inner.get_checksum(), pnet_packet::ipv4::checksum(&inner));
+    }
+
+    #[test]
+    fn anonymize_ipv4_options() {
+        // Record route (one recorded address and a free slot), timestamps with
+        // addresses (one entry) and end of list.
+        let mut ip = vec![
+            0x4b, 0, 0, 44, 0, 1, 0, 0, 64, 253, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2,
+        ];
+        ip.extend_from_slice(&[7, 11, 8, 10, 0, 0, 3, 0, 0, 0, 0]);
+        ip.extend_from_slice(&[68, 12, 13, 1, 10, 0, 0, 4, 0, 0, 0, 42, 0]);
+        let csum = pnet_packet::ipv4::checksum(&Ipv4Packet::new(&ip).unwrap());
+        ip[10..12].copy_from_slice(&csum.to_be_bytes());
+
+        let mut anonymizer = Anonymizer::new(None, false, false);
+        anonymizer.ip_packet(&mut ip);
+
+        let anon = |a: &mut Anonymizer, last| a.ipv4(Ipv4Addr::new(10, 0, 0, last)).octets();
+        let pkt = Ipv4Packet::new(&ip).unwrap();
+        assert_eq!(pkt.get_checksum(), pnet_packet::ipv4::checksum(&pkt));
+        assert_eq!(&ip[23..27], anon(&mut anonymizer, 3));
+        assert_eq!(&ip[27..31], &[0; 4]);
+        assert_eq!(&ip[35..39], anon(&mut anonymizer, 4));
+        assert_eq!(&ip[39..43], &[0, 0, 0, 42]);
+
+        // Addresses in the event section use the same mapping.
+        let mut event = SkbIpEvent {
+            saddr: "10.0.0.1".to_string(),
+            daddr: "10.0.0.2".to_string(),
+            version: SkbIpVersion::V4 {
+                v4: SkbIpv4Event {
+                    tos: 0,
+                    id: 1,
+                    flags: 0,
+                    offset: 0,
+                    ihl: Some(11),
+                    options: Some(vec![SkbIpv4Option {
+                        r#type: SkbIpv4Option::RR,
+                        len: 11,
+                        addrs: Some(vec!["10.0.0.3".to_string()]),
+                        timestamps: None,
+                        value: None,
+                    }]),
+                },
+            },
+            protocol: 253,
+            len: 44,
+            ttl: 64,
+            ecn: 0,
+        };
+        anonymizer.skb_ip(&mut event);
+        let SkbIpVersion::V4 { v4 } = &event.version else {
+            panic!("Not an IPv4 packet");
+        };
+        assert_eq!(
+            v4.options.as_ref().unwrap()[0].addrs.as_ref().unwrap()[0],
+            Ipv4Addr::from(anon(&mut anonymizer, 3)).to_string()
+        );
+    }
+
+    #[test]
+    fn anonymize_icmp_sections() {
+        let ip = |saddr: &str, daddr: &str| SkbIpEvent {
+            saddr: saddr.to_string(),
+            daddr: daddr.to_string(),
+            version: SkbIpVersion::V6 {
+                v6: SkbIpv6Event {
+                    flow_label: 0,
+                    ext_headers: None,
+                    frag: None,
+                },
+            },
+            protocol: 17,
+            len: 8,
+            ttl: 64,
+            ecn: 0,
+        };
+        let icmpv6 = || SkbIcmpV6Event {
+            r#type: 1,
+            code: 4,
+            mtu: None,
+            target: Some("2001:db8::3".to_string()),
+            orig: Some(SkbIcmpOrigEvent {
+                ip: ip("2001:db8::1", "2001:db8::2"),
+                sport: Some(1234),
+                dport: Some(53),
+            }),
+        };
+
+        let mut skb = SkbEvent {
+            icmpv6: Some(icmpv6()),
+            inner: Some(SkbInnerEvent {
+                encap: SkbEncapType::Vxlan,
+                vni: Some(42),
+                key: None,
+                eth: None,
+                vlan: None,
+                vlans: None,
+                mpls: None,
+                ip: None,
+                tcp: None,
+                udp: None,
+                icmp: None,
+                icmpv6: Some(icmpv6()),
+                sctp: None,
+                dccp: None,
+            }),
+            ..Default::default()
+        };
+        let mut event = Event::new();
+        event
+            .insert_section(SectionId::Skb, Box::new(std::mem::take(&mut skb)))
+            .unwrap();
+
+        let mut anonymizer = Anonymizer::new(None, false, false);
+        anonymizer.event(&mut event);
+
+        let anon = |a: &mut Anonymizer, addr: &str| a.ipv6(addr.parse().unwrap()).to_string();
+        let skb = event.get_section::<SkbEvent>(SectionId::Skb).unwrap();
+        for icmpv6 in [
+            skb.icmpv6.as_ref().unwrap(),
+            skb.inner.as_ref().unwrap().icmpv6.as_ref().unwrap(),
+        ] {
+            let orig = &icmpv6.orig.as_ref().unwrap().ip;
+            assert_eq!(orig.saddr, anon(&mut anonymizer, "2001:db8::1"));
+            assert_eq!(orig.daddr, anon(&mut anonymizer, "2001:db8::2"));
+            assert_eq!(
+                icmpv6.target.as_deref().unwrap(),
+                anon(&mut anonymizer, "2001:db8::3")
+            );
+        }
+    }
+
+    #[test]
+    fn anonymize_ndisc() {
+        // Neighbor solicitation with a source link-layer address option.
+        let (saddr, daddr): (Ipv6Addr, Ipv6Addr) = (
+            "fe80::1".parse().unwrap(),
+            "ff02::1:ff00:2".parse().unwrap(),
+        );
+        let target: Ipv6Addr = "2001:db8::2".parse().unwrap();
+        let mut icmp = vec![135, 0, 0, 0, 0, 0, 0, 0];
+        icmp.extend_from_slice(&target.octets());
+        icmp.extend_from_slice(&[1, 1, 0x02, 0, 0, 0, 0, 1]);
+
+        let mut ip = vec![0x60, 0, 0, 0, 0, icmp.len() as u8, 58, 255];
+        ip.extend_from_slice(&saddr.octets());
+        ip.extend_from_slice(&daddr.octets());
+        ip.extend_from_slice(&icmp);
+        let csum =
+            pnet_packet::icmpv6::checksum(&Icmpv6Packet::new(&ip[40..]).unwrap(), &saddr, &daddr);
+        ip[42..44].copy_from_slice(&csum.to_be_bytes());
+
+        let mut anonymizer = Anonymizer::new(None, false, false);
+        anonymizer.ip_packet(&mut ip);
+
+        let pkt = Ipv6Packet::new(&ip).unwrap();
+        let icmp = Icmpv6Packet::new(pkt.payload()).unwrap();
+        assert_eq!(
+            icmp.get_checksum(),
+            pnet_packet::icmpv6::checksum(&icmp, &pkt.get_source(), &pkt.get_destination())
+        );
+        assert_eq!(&ip[48..64], &anonymizer.ipv6(target).octets());
+        assert_eq!(&ip[66..72], &anonymizer.mac([0x02, 0, 0, 0, 0, 1]));
+    }
+
+    #[test]
+    fn anonymize_ipv6_ext_headers() {
+        let addr = |a: &str| a.parse::<Ipv6Addr>().unwrap();
+        let (saddr, daddr) = (addr("2001:db8::1"), addr("2001:db8::10"));
+        let segments = [addr("2001:db8::2"), addr("2001:db8::3")];
+
+        // Hop-by-hop options (8B, padding only).
+        let mut ext = vec![43, 0, 1, 4, 0, 0, 0, 0];
+        // Segment routing header: 2 segments, the first one being the final
+        // destination.
+        ext.extend_from_slice(&[6, 4, 4, 1, 1, 0, 0, 0]);
+        for segment in segments.iter() {
+            ext.extend_from_slice(&segment.octets());
+        }
+        let tcp = [
+            0x04, 0xd2, 0x00, 0x50, 0, 0, 0, 1, 0, 0, 0, 0, 0x50, 0x02, 0xff, 0xff, 0, 0, 0, 0,
+        ];
+
+        let mut ip = vec![0x60, 0, 0, 0, 0, (ext.len() + tcp.len()) as u8, 0, 64];
+        ip.extend_from_slice(&saddr.octets());
+        ip.extend_from_slice(&daddr.octets());
+        ip.extend_from_slice(&ext);
+        ip.extend_from_slice(&tcp);
+        let l4 = 40 + ext.len();
+        let csum = pnet_packet::tcp::ipv6_checksum(
+            &TcpPacket::new(&ip[l4..]).unwrap(),
+            &saddr,
+            &segments[0],
+        );
+        ip[(l4 + 16)..(l4 + 18)].copy_from_slice(&csum.to_be_bytes());
+
+        let mut anonymizer = Anonymizer::new(None, false, false);
+        anonymizer.ip_packet(&mut ip);
+
+        let pkt = Ipv6Packet::new(&ip).unwrap();
+        assert_eq!(pkt.get_source(), anonymizer.ipv6(saddr));
+        assert_eq!(pkt.get_destination(), anonymizer.ipv6(daddr));
+        for (i, segment) in segments.iter().enumerate() {
+            let offset = 56 + i * 16;
+            assert_eq!(
+                &ip[offset..(offset + 16)],
+                &anonymizer.ipv6(*segment).octets()
+            );
+        }
+
+        let tcp = TcpPacket::new(&ip[l4..]).unwrap();
+        assert_eq!(
+            tcp.get_checksum(),
+            pnet_packet::tcp::ipv6_checksum(
+                &tcp,
+                &anonymizer.ipv6(saddr),
+                &anonymizer.ipv6(segments[0])
+            )
+        );
+    }
+
+    #[test]
+    fn anonymize_vxlan() {
+        // Ethernet + IPv4 + UDP inner packet, in VXLAN.
+        let mut inner = vec![0x02, 0, 0, 0, 0, 1, 0x02, 0, 0, 0, 0, 2, 0x08, 0];
+        inner.extend_from_slice(&[
+            0x45, 0, 0, 28, 0, 1, 0, 0, 64, 17, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2, 0x9c, 0x40, 0, 53,
+            0, 8, 0, 0,
+        ]);
+        let csum = pnet_packet::ipv4::checksum(&Ipv4Packet::new(&inner[14..]).unwrap());
+        inner[24..26].copy_from_slice(&csum.to_be_bytes());
+
+        let mut udp = vec![
+            0x13, 0x88, 0x12, 0xb5, 0, 0, 0, 0, 0x08, 0, 0, 0, 0, 0, 42, 0,
+        ];
+        udp.extend_from_slice(&inner);
+        let len = udp.len() as u16;
+        udp[4..6].copy_from_slice(&len.to_be_bytes());
+
+        let mut outer = vec![
+            0x45, 0, 0, 0, 0, 2, 0, 0, 64, 17, 0, 0, 192, 168, 0, 1, 192, 168, 0, 2,
+        ];
+        outer[2..4].copy_from_slice(&(20 + len).to_be_bytes());
+        outer.extend_from_slice(&udp);
+        let csum = pnet_packet::ipv4::checksum(&Ipv4Packet::new(&outer).unwrap());
+        outer[10..12].copy_from_slice(&csum.to_be_bytes());
+        let (saddr, daddr) = (Ipv4Addr::new(192, 168, 0, 1), Ipv4Addr::new(192, 168, 0, 2));
+        let csum =
+            pnet_packet::udp::ipv4_checksum(&UdpPacket::new(&outer[20..]).unwrap(), &saddr, &daddr);
+        outer[26..28].copy_from_slice(&csum.to_be_bytes());
+
+        let mut anonymizer = Anonymizer::new(None, false, false);
+        anonymizer.ip_packet(&mut outer);
+
+        let ip = Ipv4Packet::new(&outer).unwrap();
+        let udp = UdpPacket::new(ip.payload()).unwrap();
+        assert_eq!(
+            udp.get_checksum(),
+            pnet_packet::udp::ipv4_checksum(&udp, &ip.get_source(), &ip.get_destination())
+        );
+
+        // The inner packet is anonymized, with a valid checksum.
+        let inner = Ipv4Packet::new(&udp.payload()[22..]).unwrap();
+        assert_ne!(inner.get_source(), Ipv4Addr::new(10, 0, 0, 1));
+        assert_eq!(inner.get_checksum(), pnet_packet::ipv4::checksum(&inner));
+        assert_ne!(&udp.payload()[8..14], &[0x02, 0, 0, 0, 0, 1]);
+    }
+}
@@ -0,0 +1,529 @@
+//! # Diff
+//!
+//! Diff is a post-processing command comparing the paths packets took in two
+//! event files (e.g. a "good" and a "bad" capture of the same traffic), flow by
+//! flow.
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use clap::Parser;
+use serde_json::json;
+
+use crate::{
+    cli::*,
+    events::*,
+    helpers::signals::Running,
+    process::{
+        flow::FlowKey,
+        series::{nft_verdict, ovs_action, SeriesReader},
+    },
+};
+
+use super::DEFAULT_BUFFER;
+
+/// Aspects of the path of a packet being compared, in display order.
+const ASPECTS: [(&str, &str); 4] = [
+    ("probes", "Probes"),
+    ("drop_reasons", "Drop reasons"),
+    ("nft_verdicts", "Nft verdicts"),
+    ("ovs_actions", "OvS actions"),
+];
+
+/// Path of a single packet (series), as a list of values per aspect.
+#[derive(Default)]
+struct PacketPath {
+    probes: Vec<String>,
+    drop_reasons: Vec<String>,
+    nft_verdicts: Vec<String>,
+    ovs_actions: Vec<String>,
+}
+
+impl PacketPath {
+    fn from_series(series: &EventSeries) -> Self {
+        let mut path = PacketPath::default();
+
+        for event in series.events.iter() {
+            if let Some(probe) = event.probe_name() {
+                path.probes.push(probe);
+            }
+            if let Some(reason) = event.drop_reason() {
+                path.drop_reasons.push(reason);
+            }
+            if let Some(verdict) = nft_verdict(event) {
+                path.nft_verdicts.push(verdict);
+            }
+            if let Some(action) = ovs_action(event) {
+                path.ovs_actions.push(action);
+            }
+        }
+
+        path
+    }
+
+    /// Returns the aspects of the path as strings, following `ASPECTS` order.
+    fn aspects(&self) -> [String; 4] {
+        let join = |values: &Vec<String>, sep: &str| match values.is_empty() {
+            true => "(none)".to_string(),
+            false => values.join(sep),
+        };
+
+        [
+            join(&self.probes, " > "),
+            join(&self.drop_reasons, ", "),
+            join(&self.nft_verdicts, ", "),
+            join(&self.ovs_actions, ", "),
+        ]
+    }
+}
+
+/// Number of packets per distinct value of an aspect.
+type AspectCount = BTreeMap<String, u64>;
+
+/// Paths taken by the packets of a flow.
+#[derive(Default)]
+struct FlowPaths {
+    packets: u64,
+    aspects: [AspectCount; 4],
+}
+
+/// Packet paths found in a single file.
+#[derive(Default)]
+struct DiffSide {
+    /// Number of series processed.
+    packets: u64,
+    /// Number of series that could not be associated to a flow.
+    unidentified: u64,
+    flows: BTreeMap<FlowKey, FlowPaths>,
+}
+
+impl DiffSide {
+    fn add_series(&mut self, series: &EventSeries) {
+        self.packets += 1;
+
+        let flow = match series.events.iter().find_map(FlowKey::from_event) {
+            Some(flow) => flow,
+            None => {
+                self.unidentified += 1;
+                return;
+            }
+        };
+
+        let paths = self.flows.entry(flow).or_default();
+        paths.packets += 1;
+        for (count, value) in paths
+            .aspects
+            .iter_mut()
+            .zip(PacketPath::from_series(series).aspects())
+        {
+            *count.entry(value).or_default() += 1;
+        }
+    }
+
+    fn from_file(input: &Path, max_buffer: usize, run: &Running) -> Result<Self> {
+        let mut reader = SeriesReader::new(input, max_buffer)?;
+        let mut side = DiffSide::default();
+
+        while run.running() {
+            match reader.next_series()? {
+                Some(series) => side.add_series(&series),
+                None => break,
+            }
+        }
+
+        Ok(side)
+    }
+}
+
+/// Differences of a single aspect for a flow: values only seen in the first
+/// and in the second file, and values seen in both but by a different
+/// proportion of the flow packets, with their number of packets.
+struct AspectDiff<'a> {
+    aspect: usize,
+    removed: Vec<(&'a str, u64)>,
+    added: Vec<(&'a str, u64)>,
+    changed: Vec<(&'a str, (u64, u64))>,
+}
+
+impl<'a> AspectDiff<'a> {
+    /// Compares the values of an aspect for a flow, `packets` being the number
+    /// of packets of the flow in both files. Returns None if no difference was
+    /// found.
+    fn new(
+        aspect: usize,
+        (a, b): (&'a AspectCount, &'a AspectCount),
+        packets: (u64, u64),
+        threshold: f64,
+    ) -> Option<Self> {
+        let only_in = |x: &'a AspectCount, y: &AspectCount| {
+            x.iter()
+                .filter(|(k, _)| !y.contains_key(*k))
+                .map(|(k, v)| (k.as_str(), *v))
+                .collect::<Vec<_>>()
+        };
+        let percent = |count: u64, total: u64| count as f64 * 100.0 / total.max(1) as f64;
+
+        let diff = AspectDiff {
+            aspect,
+            removed: only_in(a, b),
+            added: only_in(b, a),
+            changed: a
+                .iter()
+                .filter_map(|(k, &x)| b.get(k).map(|&y| (k.as_str(), (x, y))))
+                .filter(|(_, (x, y))| {
+                    (percent(*x, packets.0) - percent(*y, packets.1)).abs() > threshold
+                })
+                .collect(),
+        };
+
+        match diff.removed.is_empty() && diff.added.is_empty() && diff.changed.is_empty() {
+            true => None,
+            false => Some(diff),
+        }
+    }
+}
+
+/// Differences for a flow seen in both files.
+struct FlowDiff<'a> {
+    flow: &'a FlowKey,
+    packets: (u64, u64),
+    aspects: Vec<AspectDiff<'a>>,
+}
+
+/// Result of the comparison of two files.
+struct FileDiff {
+    names: (String, String),
+    sides: (DiffSide, DiffSide),
+    /// Minimum difference, in percentage points, in the proportion of packets
+    /// of a flow having a given value for it to be reported.
+    threshold: f64,
+}
+
+impl FileDiff {
+    /// Flows only seen in one of the two files.
+    fn exclusive_flows(&self) -> (Vec<&FlowKey>, Vec<&FlowKey>) {
+        let (a, b) = (&self.sides.0.flows, &self.sides.1.flows);
+        (
+            a.keys().filter(|f| !b.contains_key(f)).collect(),
+            b.keys().filter(|f| !a.contains_key(f)).collect(),
+        )
+    }
+
+    /// Flows seen in both files and having different paths.
+    fn flow_diffs(&self) -> Vec<FlowDiff<'_>> {
+        let mut diffs = Vec::new();
+
+        for (flow, a) in self.sides.0.flows.iter() {
+            let b = match self.sides.1.flows.get(flow) {
+                Some(b) => b,
+                None => continue,
+            };
+
+            let aspects = a
+                .aspects
+                .iter()
+                .zip(b.aspects.iter())
+                .enumerate()
+                .filter_map(|(aspect, counts)| {
+                    AspectDiff::new(aspect, counts, (a.packets, b.packets), self.threshold)
+                })
+                .collect::<Vec<_>>();
+
+            if !aspects.is_empty() {
+                diffs.push(FlowDiff {
+                    flow,
+                    packets: (a.packets, b.packets),
+                    aspects,
+                });
+            }
+        }
+
+        diffs
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let side = |name: &str, side: &DiffSide| {
+            json!({
+                "file": name,
+                "packets": side.packets,
+                "unidentified_packets": side.unidentified,
+                "flows": side.flows.len(),
+            })
+        };
+        let entries = |values: &Vec<(&str, u64)>| {
+            values
+                .iter()
+                .map(|(key, count)| json!({"key": key, "count": count}))
+                .collect::<Vec<_>>()
+        };
+
+        let (only_a, only_b) = self.exclusive_flows();
+        let flows = self
+            .flow_diffs()
+            .iter()
+            .map(|diff| {
+                let mut aspects = serde_json::Map::new();
+                for aspect in diff.aspects.iter() {
+                    aspects.insert(
+                        ASPECTS[aspect.aspect].0.to_string(),
+                        json!({
+                            "removed": entries(&aspect.removed),
+                            "added": entries(&aspect.added),
+                            "changed": aspect
+                                .changed
+                                .iter()
+                                .map(|(key, (a, b))| json!({"key": key, "count": [a, b]}))
+                                .collect::<Vec<_>>(),
+                        }),
+                    );
+                }
+
+                json!({
+                    "flow": diff.flow.to_string(),
+                    "packets": [diff.packets.0, diff.packets.1],
+                    "differences": aspects,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        json!({
+            "files": [side(&self.names.0, &self.sides.0), side(&self.names.1, &self.sides.1)],
+            "flows_only_in_first": only_a.iter().map(|f| f.to_string()).collect::<Vec<_>>(),
+            "flows_only_in_second": only_b.iter().map(|f| f.to_string()).collect::<Vec<_>>(),
+            "flows": flows,
+        })
+    }
+}
+
+impl fmt::Display for FileDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, side) in [
+            (&self.names.0, &self.sides.0),
+            (&self.names.1, &self.sides.1),
+        ] {
+            write!(
+                f,
+                "{name}: {} packet(s), {} flow(s)",
+                side.packets,
+                side.flows.len()
+            )?;
+            if side.unidentified > 0 {
+                write!(f, ", {} packet(s) not part of a flow", side.unidentified)?;
+            }
+            writeln!(f)?;
+        }
+
+        let (only_a, only_b) = self.exclusive_flows();
+        for (name, flows) in [(&self.names.0, only_a), (&self.names.1, only_b)] {
+            if flows.is_empty() {
+                continue;
+            }
+
+            writeln!(f, "\nFlows only in {name}:")?;
+            for flow in flows {
+                writeln!(f, "  {flow}")?;
+            }
+        }
+
+        let diffs = self.flow_diffs();
+        if diffs.is_empty() {
+            return writeln!(f, "\nNo difference found in flows seen in both files");
+        }
+
+        for diff in diffs {
+            writeln!(
+                f,
+                "\n{} ({} / {} packet(s)):",
+                diff.flow, diff.packets.0, diff.packets.1
+            )?;
+
+            for aspect in diff.aspects {
+                writeln!(f, "  {}:", ASPECTS[aspect.aspect].1)?;
+                for (sign, values) in [('-', &aspect.removed), ('+', &aspect.added)] {
+                    for (value, count) in values {
+                        writeln!(f, "    {sign} {count:>4}  {value}")?;
+                    }
+                }
+                for (value, (a, b)) in aspect.changed.iter() {
+                    writeln!(f, "    ~ {a:>4} -> {b}  {value}")?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Compare the packet paths of two event files
+///
+/// Reads events from the FIRST and SECOND files (sorted or not), groups them by
+/// packet and aligns packets by flow (protocol, addresses and ports). Flows
+/// seen in only one file are listed; for flows seen in both, differences in the
+/// probe sequences, drop reasons, nft verdicts and OvS actions are reported.
+/// Values only seen in the first file are prefixed with '-', the ones only seen
+/// in the second file with '+', along with their number of packets. Values seen
+/// in both files but by a proportion of the flow packets differing by more than
+/// the threshold are prefixed with '~', along with their number of packets in
+/// both files.
+#[derive(Parser, Debug, Default)]
+#[command(name = "diff")]
+pub(crate) struct Diff {
+    /// First file from which to read events (e.g. a known good capture).
+    pub(super) first: PathBuf,
+
+    /// Second file from which to read events.
+    pub(super) second: PathBuf,
+
+    /// Maximum number of events to buffer when sorting events.
+    ///
+    /// See `retis sort --help` for more details. A value of zero means the
+    /// buffer can grow endlessly.
+    #[arg(long, default_value_t = DEFAULT_BUFFER)]
+    pub(super) max_buffer: usize,
+
+    /// Minimum difference, in percentage points, between the proportions of
+    /// packets of a flow having a given value (e.g. a drop reason) in both
+    /// files for it to be reported.
+    #[arg(long, default_value_t = 10.0)]
+    pub(super) threshold: f64,
+
+    /// Output format.
+    #[arg(long)]
+    #[clap(value_enum, default_value_t = ReportFormat::Text)]
+    pub(super) format: ReportFormat,
+}
+
+impl SubCommandParserRunner for Diff {
+    fn run(&mut self, _: &MainConfig) -> Result<()> {
+        let diff = compute_diff(&self.first, &self.second, self.max_buffer, self.threshold)?;
+
+        match self.format {
+            ReportFormat::Text => print!("{diff}"),
+            ReportFormat::Json => println!("{}", diff.to_json()),
+        }
+
+        Ok(())
+    }
+}
+
+/// Compare two event or series files.
+fn compute_diff(
+    first: &Path,
+    second: &Path,
+    max_buffer: usize,
+    threshold: f64,
+) -> Result<FileDiff> {
+    // Create running instance that will handle signal termination.
+    let run = Running::new();
+    run.register_term_signals()?;
+
+    Ok(FileDiff {
+        names: (first.display().to_string(), second.display().to_string()),
+        sides: (
+            DiffSide::from_file(first, max_buffer, &run)?,
+            DiffSide::from_file(second, max_buffer, &run)?,
+        ),
+        threshold,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "test_data/test_events_packets.json";
+
+    #[test]
+    fn diff_same_file() {
+        let diff = compute_diff(Path::new(INPUT), Path::new(INPUT), DEFAULT_BUFFER, 10.0).unwrap();
+
+        assert!(!diff.sides.0.flows.is_empty());
+        assert_eq!(diff.exclusive_flows(), (Vec::new(), Vec::new()));
+        assert!(diff.flow_diffs().is_empty());
+    }
+
+    #[test]
+    fn diff_paths() {
+        let mut reader = SeriesReader::new(Path::new(INPUT), DEFAULT_BUFFER).unwrap();
+        let mut diff = FileDiff {
+            names: ("good".to_string(), "bad".to_string()),
+            sides: (DiffSide::default(), DiffSide::default()),
+            threshold: 10.0,
+        };
+
+        // Remove drop events from the first side.
+        while let Some(mut series) = reader.next_series().unwrap() {
+            diff.sides.1.add_series(&series);
+            series
+                .events
+                .retain(|e| e.get_section::<SkbDropEvent>(SectionId::SkbDrop).is_none());
+            diff.sides.0.add_series(&series);
+        }
+
+        let diffs = diff.flow_diffs();
+        assert!(!diffs.is_empty());
+        for flow in diffs.iter() {
+            let aspects = flow.aspects.iter().map(|a| a.aspect).collect::<Vec<_>>();
+            assert_eq!(aspects, vec![0, 1]);
+
+            let drops = &flow.aspects[1];
+            assert!(drops.added.iter().any(|(v, _)| *v == "NO_SOCKET"));
+        }
+
+        let json = diff.to_json();
+        assert_eq!(
+            json["flows"][0]["differences"]["drop_reasons"]["added"][0]["key"],
+            "NO_SOCKET"
+        );
+    }
+
+    #[test]
+    fn diff_proportions() {
+        let is_drop = |e: &Event| e.get_section::<SkbDropEvent>(SectionId::SkbDrop).is_some();
+        let first_dropped = || {
+            let mut reader = SeriesReader::new(Path::new(INPUT), DEFAULT_BUFFER).unwrap();
+            loop {
+                let series = reader.next_series().unwrap().unwrap();
+                if series.events.iter().any(is_drop) {
+                    break series;
+                }
+            }
+        };
+        let dropped = first_dropped();
+        let mut delivered = first_dropped();
+        delivered.events.retain(|e| !is_drop(e));
+
+        // The same flow drops 1% of its packets in the first file and 95% in
+        // the second one.
+        let mut diff = FileDiff {
+            names: ("good".to_string(), "bad".to_string()),
+            sides: (DiffSide::default(), DiffSide::default()),
+            threshold: 10.0,
+        };
+        for i in 0..100 {
+            diff.sides
+                .0
+                .add_series(if i < 1 { &dropped } else { &delivered });
+            diff.sides
+                .1
+                .add_series(if i < 95 { &dropped } else { &delivered });
+        }
+
+        let diffs = diff.flow_diffs();
+        assert_eq!(diffs.len(), 1);
+        let drops = diffs[0].aspects.iter().find(|a| a.aspect == 1).unwrap();
+        assert!(drops.removed.is_empty() && drops.added.is_empty());
+        assert!(drops
+            .changed
+            .iter()
+            .any(|(v, counts)| *v == "NO_SOCKET" && *counts == (1, 95)));
+
+        // Small variations are not reported.
+        diff.threshold = 95.0;
+        assert!(diff.flow_diffs().is_empty());
+    }
+}
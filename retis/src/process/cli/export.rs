@@ -0,0 +1,291 @@
+//! # Export
+//!
+//! Export is a post-processing command converting events to formats understood
+//! by third-party tools, e.g. traces which can be opened in timeline UIs.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs::OpenOptions,
+    io::{stdout, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Result};
+use clap::{Parser, ValueEnum};
+use serde_json::json;
+
+use crate::{
+    cli::*,
+    events::{
+        file::{FileEventsFactory, FileType},
+        *,
+    },
+    helpers::signals::Running,
+};
+
+/// Format of the exported events.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, ValueEnum)]
+pub(crate) enum ExportFormat {
+    /// Chrome trace JSON, which can be opened in ui.perfetto.dev or
+    /// chrome://tracing.
+    #[default]
+    #[value(alias = "chrome")]
+    Perfetto,
+}
+
+/// Trace process holding one track (thread) per packet.
+const PACKETS_PID: u32 = 1;
+/// Trace process holding one track (thread) per CPU.
+const CPUS_PID: u32 = 2;
+/// Track of the packets process holding events not linked to a packet.
+const UNTRACKED_TID: u32 = 0;
+
+/// Writes events in the Chrome trace JSON format: each packet (tracking id) and
+/// each CPU gets its own track, and each probe hit is an instant event on them.
+struct TraceWriter<W: Write> {
+    writer: W,
+    /// Whether at least one trace event was written.
+    started: bool,
+    /// Track ids of packets, by tracking id.
+    packets: HashMap<u128, u32>,
+    /// CPUs having a track.
+    cpus: HashSet<u32>,
+    untracked: bool,
+}
+
+impl<W: Write> TraceWriter<W> {
+    fn new(writer: W) -> Result<Self> {
+        let mut trace = Self {
+            writer,
+            started: false,
+            packets: HashMap::new(),
+            cpus: HashSet::new(),
+            untracked: false,
+        };
+
+        trace.writer.write_all(b"{\"traceEvents\":[\n")?;
+        trace.metadata("process_name", PACKETS_PID, None, "Packets")?;
+        trace.metadata("process_name", CPUS_PID, None, "CPUs")?;
+        Ok(trace)
+    }
+
+    fn write(&mut self, event: serde_json::Value) -> Result<()> {
+        if self.started {
+            self.writer.write_all(b",\n")?;
+        }
+        self.started = true;
+        serde_json::to_writer(&mut self.writer, &event)?;
+        Ok(())
+    }
+
+    /// Writes a metadata event, naming a process or a track.
+    fn metadata(&mut self, kind: &str, pid: u32, tid: Option<u32>, name: &str) -> Result<()> {
+        let mut event = json!({
+            "name": kind,
+            "ph": "M",
+            "pid": pid,
+            "args": { "name": name },
+        });
+        if let Some(tid) = tid {
+            event["tid"] = json!(tid);
+        }
+        self.write(event)
+    }
+
+    /// Returns the track of a packet, creating it if needed.
+    fn packet_track(&mut self, tracking_id: u128) -> Result<u32> {
+        if let Some(tid) = self.packets.get(&tracking_id) {
+            return Ok(*tid);
+        }
+
+        let tid = self.packets.len() as u32 + 1;
+        self.packets.insert(tracking_id, tid);
+        self.metadata(
+            "thread_name",
+            PACKETS_PID,
+            Some(tid),
+            &format!("packet {tracking_id:#x}"),
+        )?;
+        Ok(tid)
+    }
+
+    /// Returns the track of a CPU, creating it if needed.
+    fn cpu_track(&mut self, cpu: u32) -> Result<u32> {
+        if self.cpus.insert(cpu) {
+            self.metadata("thread_name", CPUS_PID, Some(cpu), &format!("CPU {cpu}"))?;
+        }
+        Ok(cpu)
+    }
+
+    /// Adds an event to the trace, as an instant event on its packet track
+    /// and on its CPU track. Events not generated by a probe (e.g. startup
+    /// events) are ignored.
+    fn add(&mut self, event: &Event) -> Result<()> {
+        let (Some(probe), Some(common)) = (
+            event.probe_name(),
+            event.get_section::<CommonEvent>(SectionId::Common),
+        ) else {
+            return Ok(());
+        };
+
+        let mut args = json!({ "event": event.to_json() });
+        if let Some(task) = &common.task {
+            args["task"] = json!(format!("{} ({}/{})", task.comm, task.pid, task.tgid));
+        }
+        let mut trace_event = json!({
+            "name": probe,
+            "cat": "retis",
+            "ph": "i",
+            "s": "t",
+            "ts": common.timestamp as f64 / 1000.0,
+            "args": args,
+        });
+
+        let packet_tid = match event.tracking_id() {
+            Some(tracking_id) => self.packet_track(tracking_id)?,
+            None => {
+                if !self.untracked {
+                    self.untracked = true;
+                    self.metadata("thread_name", PACKETS_PID, Some(UNTRACKED_TID), "untracked")?;
+                }
+                UNTRACKED_TID
+            }
+        };
+        trace_event["pid"] = json!(PACKETS_PID);
+        trace_event["tid"] = json!(packet_tid);
+        self.write(trace_event.clone())?;
+
+        if let Some(cpu) = common.smp_id {
+            trace_event["pid"] = json!(CPUS_PID);
+            trace_event["tid"] = json!(self.cpu_track(cpu)?);
+            self.write(trace_event)?;
+        }
+
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<()> {
+        self.writer
+            .write_all(b"\n],\"displayTimeUnit\":\"ns\"}\n")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Export events for use in third-party tools
+///
+/// Reads events from the INPUT file (sorted or not) and converts them to the
+/// given format. With the perfetto format (Chrome trace JSON), each packet and
+/// each CPU is shown as a track holding one instant event per probe hit, so
+/// captures can be inspected in timeline UIs such as ui.perfetto.dev.
+#[derive(Parser, Debug, Default)]
+#[command(name = "export")]
+pub(crate) struct Export {
+    /// File from which to read events.
+    #[arg(default_value = "retis.data")]
+    pub(super) input: PathBuf,
+
+    /// Format of the exported events.
+    #[arg(long)]
+    #[clap(value_enum, default_value_t = ExportFormat::Perfetto)]
+    pub(super) format: ExportFormat,
+
+    /// Write the exported events to a file rather than to stdout.
+    #[arg(short, long)]
+    pub(super) out: Option<PathBuf>,
+}
+
+impl SubCommandParserRunner for Export {
+    fn run(&mut self, _: &MainConfig) -> Result<()> {
+        let writer: Box<dyn Write> = match &self.out {
+            Some(out) => Box::new(BufWriter::new(
+                OpenOptions::new()
+                    .create(true)
+                    .write(true)
+                    .truncate(true)
+                    .open(out)
+                    .or_else(|_| bail!("Could not create or open '{}'", out.display()))?,
+            )),
+            None => Box::new(BufWriter::new(stdout())),
+        };
+
+        match self.format {
+            ExportFormat::Perfetto => export_trace(self.input.as_path(), writer),
+        }
+    }
+}
+
+/// Export the events of an event or series file as a Chrome trace.
+fn export_trace<W: Write>(input: &Path, writer: W) -> Result<()> {
+    // Create running instance that will handle signal termination.
+    let run = Running::new();
+    run.register_term_signals()?;
+
+    let mut factory = FileEventsFactory::new(input)?;
+    let mut trace = TraceWriter::new(writer)?;
+
+    match factory.file_type() {
+        FileType::Event => {
+            while run.running() {
+                match factory.next_event()? {
+                    Some(event) => trace.add(&event)?,
+                    None => break,
+                }
+            }
+        }
+        FileType::Series => {
+            while run.running() {
+                match factory.next_series()? {
+                    Some(series) => series.events.iter().try_for_each(|e| trace.add(e))?,
+                    None => break,
+                }
+            }
+        }
+    }
+
+    trace.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn export_perfetto() {
+        let mut out = Vec::new();
+        export_trace(Path::new("test_data/test_events_packets.json"), &mut out).unwrap();
+
+        let trace: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        let events = trace["traceEvents"].as_array().unwrap();
+
+        let named = |pid: u32| {
+            events
+                .iter()
+                .filter(|e| e["ph"] == "M" && e["name"] == "thread_name" && e["pid"] == pid)
+                .map(|e| e["tid"].as_u64().unwrap())
+                .collect::<HashSet<_>>()
+        };
+        let (packets, cpus) = (named(PACKETS_PID), named(CPUS_PID));
+        assert!(!packets.is_empty());
+        assert!(!cpus.is_empty());
+
+        // All instant events are on a named track.
+        let instants = events.iter().filter(|e| e["ph"] == "i").collect::<Vec<_>>();
+        assert!(!instants.is_empty());
+        for event in instants.iter() {
+            let tid = event["tid"].as_u64().unwrap();
+            match event["pid"].as_u64().unwrap() as u32 {
+                PACKETS_PID => assert!(packets.contains(&tid)),
+                CPUS_PID => assert!(cpus.contains(&tid)),
+                _ => panic!("unexpected pid"),
+            }
+            assert!(event["ts"].as_f64().unwrap() > 0.0);
+            assert!(event["args"]["event"]["common"].is_object());
+        }
+
+        // Probe hits are named after their probe.
+        assert!(instants
+            .iter()
+            .any(|e| e["name"] == "raw_tracepoint:skb:kfree_skb"));
+    }
+}
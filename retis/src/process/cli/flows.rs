@@ -0,0 +1,492 @@
+//! # Flows
+//!
+//! Flows is a post-processing command aggregating events per flow (L4
+//! protocol, addresses and ports) and reporting per-flow statistics.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use clap::{Parser, ValueEnum};
+use serde_json::json;
+
+use crate::{
+    cli::*,
+    events::{
+        file::{FileEventsFactory, FileType},
+        *,
+    },
+    helpers::{signals::Running, time::format_ns},
+    process::{
+        flow::FlowKey,
+        series::{ct_state, nft_verdict},
+    },
+};
+
+/// Key used to sort flows.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, ValueEnum)]
+pub(crate) enum FlowSortKey {
+    /// Number of distinct packets, in decreasing order.
+    #[default]
+    Packets,
+    /// Number of bytes, in decreasing order.
+    Bytes,
+    /// Number of events, in decreasing order.
+    Events,
+    /// Number of dropped packets, in decreasing order.
+    Drops,
+    /// Time the flow was first seen, in increasing order.
+    First,
+    /// Time the flow was last seen, in increasing order.
+    Last,
+    /// Time between the first and last events of the flow, in decreasing
+    /// order.
+    Duration,
+}
+
+/// Packets and bytes seen at a given probe.
+#[derive(Default)]
+struct ProbeStats {
+    packets: u64,
+    bytes: u64,
+}
+
+/// Statistics of a single flow.
+#[derive(Default)]
+struct FlowStats {
+    /// Number of events.
+    events: u64,
+    /// Number of distinct packets; events not linked to a packet (no
+    /// tracking id) each count as one.
+    packets: u64,
+    bytes: u64,
+    /// Distinct packets seen, by tracking id.
+    tracked: HashSet<u128>,
+    /// Timestamp of the first and last events.
+    first: Option<u64>,
+    last: Option<u64>,
+    /// Packets and bytes per probe.
+    probes: BTreeMap<String, ProbeStats>,
+    drops: u64,
+    drop_reasons: BTreeMap<String, u64>,
+    ct_states: BTreeSet<String>,
+    nft_verdicts: BTreeMap<String, u64>,
+}
+
+impl FlowStats {
+    /// Account for a single event of the flow.
+    fn process_one(&mut self, event: &Event) {
+        self.events += 1;
+
+        if let Some(common) = event.get_section::<CommonEvent>(SectionId::Common) {
+            self.first = Some(
+                self.first
+                    .map_or(common.timestamp, |t| t.min(common.timestamp)),
+            );
+            self.last = Some(
+                self.last
+                    .map_or(common.timestamp, |t| t.max(common.timestamp)),
+            );
+        }
+
+        let len = packet_len(event);
+        let new_packet = match event.tracking_id() {
+            Some(tracking_id) => self.tracked.insert(tracking_id),
+            None => true,
+        };
+        if new_packet {
+            self.packets += 1;
+            self.bytes += len;
+        }
+
+        if let Some(probe) = event.probe_name() {
+            let probe = self.probes.entry(probe).or_default();
+            probe.packets += 1;
+            probe.bytes += len;
+        }
+
+        if let Some(reason) = event.drop_reason() {
+            self.drops += 1;
+            *self.drop_reasons.entry(reason).or_default() += 1;
+        }
+
+        if let Some(state) = ct_state(event) {
+            self.ct_states.insert(state);
+        }
+
+        if let Some(verdict) = nft_verdict(event) {
+            *self.nft_verdicts.entry(verdict).or_default() += 1;
+        }
+    }
+
+    /// Duration between the first and the last events, in nanoseconds.
+    fn duration(&self) -> u64 {
+        match (self.first, self.last) {
+            (Some(first), Some(last)) => last - first,
+            _ => 0,
+        }
+    }
+
+    fn to_json(&self, flow: &FlowKey) -> serde_json::Value {
+        json!({
+            "flow": {
+                "protocol": flow.protocol,
+                "saddr": flow.saddr,
+                "daddr": flow.daddr,
+                "sport": flow.sport,
+                "dport": flow.dport,
+            },
+            "events": self.events,
+            "packets": self.packets,
+            "bytes": self.bytes,
+            "first_ns": self.first,
+            "last_ns": self.last,
+            "probes": self
+                .probes
+                .iter()
+                .map(|(probe, stats)| {
+                    json!({"probe": probe, "packets": stats.packets, "bytes": stats.bytes})
+                })
+                .collect::<Vec<_>>(),
+            "drops": self.drops,
+            "drop_reasons": self
+                .drop_reasons
+                .iter()
+                .map(|(reason, count)| json!({"key": reason, "count": count}))
+                .collect::<Vec<_>>(),
+            "ct_states": self.ct_states,
+            "nft_verdicts": self
+                .nft_verdicts
+                .iter()
+                .map(|(verdict, count)| json!({"key": verdict, "count": count}))
+                .collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// Returns the length of the packet an event is about, or 0 if unknown.
+fn packet_len(event: &Event) -> u64 {
+    let Some(skb) = event.get_section::<SkbEvent>(SectionId::Skb) else {
+        return 0;
+    };
+
+    if let Some(meta) = &skb.meta {
+        meta.len as u64
+    } else if let Some(packet) = &skb.packet {
+        packet.len as u64
+    } else if let Some(ip) = &skb.ip {
+        ip.len as u64
+    } else {
+        0
+    }
+}
+
+/// Per-flow statistics gathered from events.
+#[derive(Default)]
+pub(crate) struct FlowTable {
+    flows: HashMap<FlowKey, FlowStats>,
+    /// Number of events not linked to a flow.
+    unmatched: u64,
+    /// Whether encapsulated packets are aggregated by their inner flow.
+    inner: bool,
+}
+
+impl FlowTable {
+    pub(crate) fn new(inner: bool) -> Self {
+        Self {
+            inner,
+            ..Default::default()
+        }
+    }
+
+    /// Account for a single event.
+    pub(crate) fn process_one(&mut self, event: &Event) {
+        // Startup events are not related to the collection itself.
+        if event
+            .get_section::<StartupEvent>(SectionId::Startup)
+            .is_some()
+        {
+            return;
+        }
+
+        let inner = match self.inner {
+            true => event
+                .get_section::<SkbEvent>(SectionId::Skb)
+                .and_then(|skb| skb.inner.as_ref())
+                .and_then(FlowKey::from_inner),
+            false => None,
+        };
+
+        match inner.or_else(|| FlowKey::from_event(event)) {
+            Some(flow) => self.flows.entry(flow).or_default().process_one(event),
+            None => self.unmatched += 1,
+        }
+    }
+
+    /// Returns the flows sorted by `key`, up to `top` of them (all of them if
+    /// 0). Ties are broken using the flow key for stable results.
+    fn sorted(&self, key: FlowSortKey, top: usize) -> Vec<(&FlowKey, &FlowStats)> {
+        let mut flows = self.flows.iter().collect::<Vec<_>>();
+        flows.sort_by(|(ka, a), (kb, b)| {
+            match key {
+                FlowSortKey::Packets => b.packets.cmp(&a.packets),
+                FlowSortKey::Bytes => b.bytes.cmp(&a.bytes),
+                FlowSortKey::Events => b.events.cmp(&a.events),
+                FlowSortKey::Drops => b.drops.cmp(&a.drops),
+                FlowSortKey::First => a.first.cmp(&b.first),
+                FlowSortKey::Last => a.last.cmp(&b.last),
+                FlowSortKey::Duration => b.duration().cmp(&a.duration()),
+            }
+            .then_with(|| ka.cmp(kb))
+        });
+
+        if top != 0 {
+            flows.truncate(top);
+        }
+        flows
+    }
+
+    /// Returns the flow table in a JSON representation.
+    pub(crate) fn to_json(&self, key: FlowSortKey, top: usize) -> serde_json::Value {
+        json!({
+            "flows": self
+                .sorted(key, top)
+                .iter()
+                .map(|(flow, stats)| stats.to_json(flow))
+                .collect::<Vec<_>>(),
+            "total_flows": self.flows.len(),
+            "unmatched_events": self.unmatched,
+        })
+    }
+
+    /// Returns an object implementing `Display` for the flow table.
+    pub(crate) fn display(
+        &self,
+        key: FlowSortKey,
+        top: usize,
+        details: bool,
+    ) -> impl fmt::Display + '_ {
+        FlowTableDisplay {
+            table: self,
+            flows: self.sorted(key, top),
+            details,
+        }
+    }
+}
+
+struct FlowTableDisplay<'a> {
+    table: &'a FlowTable,
+    flows: Vec<(&'a FlowKey, &'a FlowStats)>,
+    details: bool,
+}
+
+impl fmt::Display for FlowTableDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} flow(s)", self.table.flows.len())?;
+        if self.flows.len() < self.table.flows.len() {
+            write!(f, " (top {})", self.flows.len())?;
+        }
+        if self.table.unmatched > 0 {
+            write!(f, ", {} event(s) not part of a flow", self.table.unmatched)?;
+        }
+        writeln!(f)?;
+
+        if self.flows.is_empty() {
+            return Ok(());
+        }
+
+        // Start times are reported relative to the first event of all flows.
+        let origin = self
+            .table
+            .flows
+            .values()
+            .filter_map(|s| s.first)
+            .min()
+            .unwrap_or(0);
+
+        let header = ["PACKETS", "BYTES", "EVENTS", "DROPS", "START", "DURATION"];
+        let rows = self
+            .flows
+            .iter()
+            .map(|(_, stats)| {
+                [
+                    stats.packets.to_string(),
+                    stats.bytes.to_string(),
+                    stats.events.to_string(),
+                    stats.drops.to_string(),
+                    stats
+                        .first
+                        .map_or("-".to_string(), |t| format!("+{}", format_ns(t - origin))),
+                    format_ns(stats.duration()),
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        let mut widths = header.map(str::len);
+        for row in rows.iter() {
+            for (width, cell) in widths.iter_mut().zip(row.iter()) {
+                *width = (*width).max(cell.len());
+            }
+        }
+
+        writeln!(f)?;
+        for (width, name) in widths.iter().zip(header.iter()) {
+            write!(f, "{name:>width$}  ")?;
+        }
+        writeln!(f, "FLOW")?;
+
+        for ((flow, stats), row) in self.flows.iter().zip(rows.iter()) {
+            for (width, cell) in widths.iter().zip(row.iter()) {
+                write!(f, "{cell:>width$}  ")?;
+            }
+            writeln!(f, "{flow}")?;
+
+            if !self.details {
+                continue;
+            }
+
+            for (probe, probe_stats) in stats.probes.iter() {
+                writeln!(
+                    f,
+                    "    {probe}: {} packet(s), {} byte(s)",
+                    probe_stats.packets, probe_stats.bytes
+                )?;
+            }
+            for (reason, count) in stats.drop_reasons.iter() {
+                writeln!(f, "    drop {reason}: {count}")?;
+            }
+            if !stats.ct_states.is_empty() {
+                let states = stats.ct_states.iter().cloned().collect::<Vec<_>>();
+                writeln!(f, "    ct {}", states.join(", "))?;
+            }
+            for (verdict, count) in stats.nft_verdicts.iter() {
+                writeln!(f, "    nft {verdict}: {count}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Aggregate events per flow
+///
+/// Reads events from the INPUT file (sorted or not) and groups them by flow (L4
+/// protocol, addresses and ports), using the packet information or, when not
+/// available, the conntrack tuples. For each flow, reports the number of
+/// packets and bytes (in total and per probe), when it was first and last
+/// seen, drops and their reasons, the conntrack states observed and the nft
+/// verdicts.
+#[derive(Parser, Debug, Default)]
+#[command(name = "flows")]
+pub(crate) struct Flows {
+    /// File from which to read events.
+    #[arg(default_value = "retis.data")]
+    pub(super) input: PathBuf,
+
+    /// Output format.
+    #[arg(long)]
+    #[clap(value_enum, default_value_t = ReportFormat::Text)]
+    pub(super) format: ReportFormat,
+
+    /// Key used to sort flows.
+    #[arg(short, long)]
+    #[clap(value_enum, default_value_t = FlowSortKey::Packets)]
+    pub(super) sort_by: FlowSortKey,
+
+    /// Maximum number of flows to report. A value of zero means all flows are
+    /// reported.
+    #[arg(long, default_value_t = 10)]
+    pub(super) top: usize,
+
+    /// Report per-probe, drop, conntrack and nft details below each flow in
+    /// the text output.
+    #[arg(short, long)]
+    pub(super) details: bool,
+
+    /// Aggregate encapsulated packets (VXLAN, Geneve, GRE, IPIP) by their inner
+    /// flow instead of the outer one.
+    #[arg(long)]
+    pub(super) inner: bool,
+}
+
+impl SubCommandParserRunner for Flows {
+    fn run(&mut self, _: &MainConfig) -> Result<()> {
+        let table = compute_flows(self.input.as_path(), self.inner)?;
+
+        match self.format {
+            ReportFormat::Text => print!("{}", table.display(self.sort_by, self.top, self.details)),
+            ReportFormat::Json => println!("{}", table.to_json(self.sort_by, self.top)),
+        }
+
+        Ok(())
+    }
+}
+
+/// Compute the flow table of an event or series file.
+fn compute_flows(input: &Path, inner: bool) -> Result<FlowTable> {
+    // Create running instance that will handle signal termination.
+    let run = Running::new();
+    run.register_term_signals()?;
+
+    let mut factory = FileEventsFactory::new(input)?;
+    let mut table = FlowTable::new(inner);
+
+    match factory.file_type() {
+        FileType::Event => {
+            while run.running() {
+                match factory.next_event()? {
+                    Some(event) => table.process_one(&event),
+                    None => break,
+                }
+            }
+        }
+        FileType::Series => {
+            while run.running() {
+                match factory.next_series()? {
+                    Some(series) => series.events.iter().for_each(|e| table.process_one(e)),
+                    None => break,
+                }
+            }
+        }
+    }
+
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flows_from_file() {
+        let table = compute_flows(Path::new("test_data/test_events_packets.json"), false).unwrap();
+        assert!(!table.flows.is_empty());
+
+        for stats in table.flows.values() {
+            assert!(stats.packets > 0 && stats.packets <= stats.events);
+            assert!(stats.first <= stats.last);
+            assert!(stats.probes.values().map(|p| p.packets).sum::<u64>() <= stats.events);
+        }
+        assert!(table.flows.values().any(|s| s.drops > 0));
+
+        let json = table.to_json(FlowSortKey::Packets, 0);
+        assert_eq!(json["total_flows"], table.flows.len());
+        assert_eq!(json["flows"].as_array().unwrap().len(), table.flows.len());
+    }
+
+    #[test]
+    fn flows_sorting() {
+        let table = compute_flows(Path::new("test_data/test_events_packets.json"), false).unwrap();
+
+        let flows = table.sorted(FlowSortKey::Packets, 0);
+        assert!(flows.windows(2).all(|w| w[0].1.packets >= w[1].1.packets));
+
+        let flows = table.sorted(FlowSortKey::First, 0);
+        assert!(flows.windows(2).all(|w| w[0].1.first <= w[1].1.first));
+
+        assert_eq!(table.sorted(FlowSortKey::Bytes, 1).len(), 1);
+    }
+}
@@ -0,0 +1,540 @@
+//! # Graph
+//!
+//! Graph is a post-processing command rendering the path of packets through
+//! the stack as directed graphs, in the Graphviz (dot) or Mermaid formats.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use clap::{Parser, ValueEnum};
+
+use crate::{
+    cli::*,
+    events::*,
+    helpers::{signals::Running, time::format_ns},
+    process::{
+        filter::{EventMatcher, FilterArgs},
+        flow::FlowKey,
+        series::{interface, nft_verdict, ovs_action, SeriesReader},
+    },
+};
+
+use super::DEFAULT_BUFFER;
+
+/// Output format of the graph command.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, ValueEnum)]
+pub(crate) enum GraphFormat {
+    #[default]
+    Dot,
+    Mermaid,
+}
+
+/// Notable events highlighted in graphs, by increasing priority.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum Highlight {
+    OvsAction,
+    NftVerdict,
+    Drop,
+}
+
+impl Highlight {
+    fn color(&self) -> &'static str {
+        match self {
+            Highlight::OvsAction => "#add8e6",
+            Highlight::NftVerdict => "#ffe699",
+            Highlight::Drop => "#ff9999",
+        }
+    }
+}
+
+/// A probe, in a given network namespace and interface (cluster).
+struct Node {
+    cluster: Option<String>,
+    probe: String,
+    /// Drop reasons, nft verdicts and OvS actions seen at this probe.
+    notes: Vec<String>,
+    highlight: Option<Highlight>,
+}
+
+impl Node {
+    fn note(&mut self, note: String, highlight: Highlight) {
+        if !self.notes.contains(&note) {
+            self.notes.push(note);
+        }
+        self.highlight = self.highlight.max(Some(highlight));
+    }
+
+    fn label(&self) -> Vec<&str> {
+        let mut label = vec![self.probe.as_str()];
+        label.extend(self.notes.iter().map(String::as_str));
+        label
+    }
+}
+
+/// Transition between two nodes.
+struct Edge {
+    from: usize,
+    to: usize,
+    /// Number of times the transition was seen.
+    count: u64,
+    /// Sum of the time deltas of the transition, in nanoseconds.
+    total_ns: u64,
+}
+
+impl Edge {
+    fn label(&self) -> String {
+        match self.count {
+            1 => format_ns(self.total_ns),
+            n => format!("{n}x avg {}", format_ns(self.total_ns / n)),
+        }
+    }
+}
+
+/// Directed graph of the path of one or more packets. Nodes and edges are kept
+/// in insertion order so the output follows the packets' path.
+#[derive(Default)]
+struct PathGraph {
+    title: String,
+    nodes: Vec<Node>,
+    node_ids: HashMap<(Option<String>, String), usize>,
+    edges: Vec<Edge>,
+    edge_ids: HashMap<(usize, usize), usize>,
+}
+
+impl PathGraph {
+    fn new(title: String) -> Self {
+        Self {
+            title,
+            ..Default::default()
+        }
+    }
+
+    fn node(&mut self, cluster: Option<String>, probe: String) -> usize {
+        let key = (cluster, probe);
+        if let Some(id) = self.node_ids.get(&key) {
+            return *id;
+        }
+
+        let id = self.nodes.len();
+        self.nodes.push(Node {
+            cluster: key.0.clone(),
+            probe: key.1.clone(),
+            notes: Vec::new(),
+            highlight: None,
+        });
+        self.node_ids.insert(key, id);
+        id
+    }
+
+    fn edge(&mut self, from: usize, to: usize, delta: u64) {
+        match self.edge_ids.get(&(from, to)) {
+            Some(id) => {
+                let edge = &mut self.edges[*id];
+                edge.count += 1;
+                edge.total_ns += delta;
+            }
+            None => {
+                self.edge_ids.insert((from, to), self.edges.len());
+                self.edges.push(Edge {
+                    from,
+                    to,
+                    count: 1,
+                    total_ns: delta,
+                });
+            }
+        }
+    }
+
+    /// Adds the path of a packet to the graph.
+    fn add_series(&mut self, series: &EventSeries) {
+        let mut prev: Option<(usize, u64)> = None;
+
+        for event in series.events.iter() {
+            let (Some(probe), Some(common)) = (
+                event.probe_name(),
+                event.get_section::<CommonEvent>(SectionId::Common),
+            ) else {
+                continue;
+            };
+
+            let id = self.node(interface(event), probe);
+            let node = &mut self.nodes[id];
+            if let Some(reason) = event.drop_reason() {
+                node.note(format!("drop {reason}"), Highlight::Drop);
+            }
+            if let Some(verdict) = nft_verdict(event) {
+                let highlight = match verdict.ends_with(" drop") {
+                    true => Highlight::Drop,
+                    false => Highlight::NftVerdict,
+                };
+                node.note(format!("nft {verdict}"), highlight);
+            }
+            if let Some(action) = ovs_action(event) {
+                node.note(action, Highlight::OvsAction);
+            }
+
+            if let Some((prev, ts)) = prev {
+                self.edge(prev, id, common.timestamp.saturating_sub(ts));
+            }
+            prev = Some((id, common.timestamp));
+        }
+    }
+
+    /// Returns the clusters of the graph and their nodes, in insertion order.
+    fn clusters(&self) -> Vec<(Option<&str>, Vec<usize>)> {
+        let mut clusters: Vec<(Option<&str>, Vec<usize>)> = Vec::new();
+        for (id, node) in self.nodes.iter().enumerate() {
+            let cluster = node.cluster.as_deref();
+            match clusters.iter_mut().find(|(c, _)| *c == cluster) {
+                Some((_, nodes)) => nodes.push(id),
+                None => clusters.push((cluster, vec![id])),
+            }
+        }
+        clusters
+    }
+
+    /// Width of an edge, proportional to the number of times it was seen.
+    fn edge_width(&self, edge: &Edge) -> f64 {
+        let max = self.edges.iter().map(|e| e.count).max().unwrap_or(1);
+        1.0 + 4.0 * edge.count as f64 / max as f64
+    }
+
+    fn is_weighted(&self) -> bool {
+        self.edges.iter().any(|e| e.count > 1)
+    }
+
+    fn to_dot(&self) -> Result<String> {
+        let esc = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+        let mut out = String::new();
+
+        writeln!(out, "digraph \"{}\" {{", esc(&self.title))?;
+        writeln!(out, "  label=\"{}\";", esc(&self.title))?;
+        writeln!(out, "  labelloc=t;")?;
+        writeln!(out, "  node [shape=box, style=rounded];")?;
+
+        for (i, (cluster, nodes)) in self.clusters().iter().enumerate() {
+            let indent = match cluster {
+                Some(cluster) => {
+                    writeln!(out, "  subgraph cluster_{i} {{")?;
+                    writeln!(out, "    label=\"{}\";", esc(cluster))?;
+                    "    "
+                }
+                None => "  ",
+            };
+
+            for id in nodes.iter() {
+                let node = &self.nodes[*id];
+                let label = node.label().iter().map(|l| esc(l)).collect::<Vec<_>>();
+                write!(out, "{indent}n{id} [label=\"{}\"", label.join("\\n"))?;
+                if let Some(highlight) = node.highlight {
+                    write!(
+                        out,
+                        ", style=\"rounded,filled\", fillcolor=\"{}\"",
+                        highlight.color()
+                    )?;
+                }
+                writeln!(out, "];")?;
+            }
+
+            if cluster.is_some() {
+                writeln!(out, "  }}")?;
+            }
+        }
+
+        let weighted = self.is_weighted();
+        for edge in self.edges.iter() {
+            write!(
+                out,
+                "  n{} -> n{} [label=\"{}\"",
+                edge.from,
+                edge.to,
+                edge.label()
+            )?;
+            if weighted {
+                write!(out, ", penwidth={:.1}", self.edge_width(edge))?;
+            }
+            writeln!(out, "];")?;
+        }
+
+        writeln!(out, "}}")?;
+        Ok(out)
+    }
+
+    fn to_mermaid(&self) -> Result<String> {
+        let esc = |s: &str| s.replace('"', "#quot;");
+        let mut out = String::new();
+
+        writeln!(out, "---")?;
+        writeln!(out, "title: \"{}\"", esc(&self.title))?;
+        writeln!(out, "---")?;
+        writeln!(out, "flowchart TD")?;
+
+        for (i, (cluster, nodes)) in self.clusters().iter().enumerate() {
+            let indent = match cluster {
+                Some(cluster) => {
+                    writeln!(out, "  subgraph c{i} [\"{}\"]", esc(cluster))?;
+                    "    "
+                }
+                None => "  ",
+            };
+
+            for id in nodes.iter() {
+                let label = self.nodes[*id]
+                    .label()
+                    .iter()
+                    .map(|l| esc(l))
+                    .collect::<Vec<_>>();
+                writeln!(out, "{indent}n{id}[\"{}\"]", label.join("<br/>"))?;
+            }
+
+            if cluster.is_some() {
+                writeln!(out, "  end")?;
+            }
+        }
+
+        for edge in self.edges.iter() {
+            writeln!(
+                out,
+                "  n{} -->|\"{}\"| n{}",
+                edge.from,
+                edge.label(),
+                edge.to
+            )?;
+        }
+
+        for (id, node) in self.nodes.iter().enumerate() {
+            if let Some(highlight) = node.highlight {
+                writeln!(out, "  style n{id} fill:{}", highlight.color())?;
+            }
+        }
+
+        if self.is_weighted() {
+            for (i, edge) in self.edges.iter().enumerate() {
+                writeln!(
+                    out,
+                    "  linkStyle {i} stroke-width:{:.1}px",
+                    self.edge_width(edge)
+                )?;
+            }
+        }
+
+        Ok(out)
+    }
+
+    fn render(&self, format: GraphFormat) -> Result<String> {
+        match format {
+            GraphFormat::Dot => self.to_dot(),
+            GraphFormat::Mermaid => self.to_mermaid(),
+        }
+    }
+}
+
+/// Render packet paths as graphs
+///
+/// Reads events from the INPUT file (sorted or not), groups them by packet and
+/// renders the path of each packet through the stack as a directed graph:
+/// probes are nodes, grouped per network namespace and interface, and edges are
+/// labeled with the time spent between probes. Drops, nft verdicts and OvS
+/// actions are highlighted.
+///
+/// With --aggregate all packets of a flow are rendered in a single graph whose
+/// edges are weighted by the number of packets taking them.
+#[derive(Parser, Debug, Default)]
+#[command(name = "graph")]
+pub(crate) struct Graph {
+    /// File from which to read events.
+    #[arg(default_value = "retis.data")]
+    pub(super) input: PathBuf,
+
+    /// Maximum number of events to buffer when sorting events.
+    ///
+    /// See `retis sort --help` for more details. A value of zero means the
+    /// buffer can grow endlessly.
+    #[arg(long, default_value_t = DEFAULT_BUFFER)]
+    pub(super) max_buffer: usize,
+
+    /// Output format. Multiple graphs are written one after the other; dot
+    /// outputs can be rendered directly (e.g. `dot -Tsvg -O`).
+    #[arg(long)]
+    #[clap(value_enum, default_value_t = GraphFormat::Dot)]
+    pub(super) format: GraphFormat,
+
+    /// Render a single graph per flow, aggregating the paths of all its
+    /// packets.
+    #[arg(long)]
+    pub(super) aggregate: bool,
+
+    #[command(flatten)]
+    pub(super) filter: FilterArgs,
+}
+
+impl SubCommandParserRunner for Graph {
+    fn run(&mut self, _: &MainConfig) -> Result<()> {
+        let graphs = build_graphs(
+            self.input.as_path(),
+            self.max_buffer,
+            self.aggregate,
+            &self.filter.matcher()?,
+        )?;
+
+        if graphs.is_empty() {
+            log::info!("No packet path could be found in the events");
+        }
+
+        for graph in graphs.iter() {
+            println!("{}", graph.render(self.format)?);
+        }
+
+        Ok(())
+    }
+}
+
+/// Builds packet path graphs from an event or series file, either one per
+/// packet or one per flow when aggregating.
+fn build_graphs(
+    input: &Path,
+    max_buffer: usize,
+    aggregate: bool,
+    matcher: &EventMatcher,
+) -> Result<Vec<PathGraph>> {
+    // Create running instance that will handle signal termination.
+    let run = Running::new();
+    run.register_term_signals()?;
+
+    let mut reader = SeriesReader::new(input, max_buffer)?;
+    let mut graphs = Vec::new();
+    let mut flows: BTreeMap<Option<FlowKey>, (u64, PathGraph)> = BTreeMap::new();
+
+    while run.running() {
+        let series = match reader.next_series()? {
+            Some(series) => series,
+            None => break,
+        };
+        if series.events.len() < 2 || !matcher.matches_series(&series) {
+            continue;
+        }
+
+        let flow = series.events.iter().find_map(FlowKey::from_event);
+        if aggregate {
+            let (packets, graph) = flows
+                .entry(flow)
+                .or_insert_with(|| (0, PathGraph::default()));
+            *packets += 1;
+            graph.add_series(&series);
+            continue;
+        }
+
+        let mut title = match series.events[0].get_section::<TrackingInfo>(SectionId::Tracking) {
+            Some(tracking) => format!("packet {:#x}", tracking.skb.tracking_id()),
+            None => "packet".to_string(),
+        };
+        if let Some(flow) = flow {
+            title.push_str(&format!(" ({flow})"));
+        }
+
+        let mut graph = PathGraph::new(title);
+        graph.add_series(&series);
+        graphs.push(graph);
+    }
+
+    for (flow, (packets, mut graph)) in flows.into_iter() {
+        let flow = match flow {
+            Some(flow) => flow.to_string(),
+            None => "unidentified flow".to_string(),
+        };
+        graph.title = format!("{flow} ({packets} packets)");
+        graphs.push(graph);
+    }
+
+    Ok(graphs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "test_data/test_events_packets.json";
+
+    fn graphs(aggregate: bool) -> Vec<PathGraph> {
+        build_graphs(
+            Path::new(INPUT),
+            DEFAULT_BUFFER,
+            aggregate,
+            &FilterArgs::default().matcher().unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn graph_per_packet() {
+        let graphs = graphs(false);
+        assert!(!graphs.is_empty());
+
+        for graph in graphs.iter() {
+            assert!(graph.title.starts_with("packet 0x"));
+            assert!(graph.nodes.len() > 1);
+            assert!(graph.edges.iter().all(|e| e.count == 1));
+
+            let dot = graph.to_dot().unwrap();
+            assert!(dot.starts_with("digraph \"packet 0x"));
+            assert!(dot.trim_end().ends_with('}'));
+            assert_eq!(dot.matches(" -> ").count(), graph.edges.len());
+            assert!(!dot.contains("penwidth"));
+
+            let mermaid = graph.to_mermaid().unwrap();
+            assert!(mermaid.contains("flowchart TD"));
+            assert_eq!(mermaid.matches(" -->|").count(), graph.edges.len());
+        }
+    }
+
+    #[test]
+    fn graph_aggregate() {
+        let per_packet = graphs(false);
+        let graphs = graphs(true);
+        assert!(graphs.len() < per_packet.len());
+
+        let edges = |graphs: &Vec<PathGraph>| {
+            graphs
+                .iter()
+                .flat_map(|g| g.edges.iter().map(|e| e.count))
+                .sum::<u64>()
+        };
+        assert_eq!(edges(&graphs), edges(&per_packet));
+        assert!(graphs.iter().all(|g| g.title.ends_with(" packets)")));
+    }
+
+    #[test]
+    fn graph_render() {
+        let mut graph = PathGraph::new("test \"graph\"".to_string());
+        let a = graph.node(Some("ns 1 if 2 (eth0)".to_string()), "kprobe:a".to_string());
+        let b = graph.node(None, "kprobe:kfree_skb_reason".to_string());
+        graph.nodes[b].note("drop NO_SOCKET".to_string(), Highlight::Drop);
+        graph.nodes[b].note("drop NO_SOCKET".to_string(), Highlight::OvsAction);
+        graph.edge(a, b, 1000);
+        graph.edge(a, b, 3000);
+
+        assert_eq!(graph.nodes[b].notes.len(), 1);
+        assert_eq!(graph.nodes[b].highlight, Some(Highlight::Drop));
+        assert_eq!(graph.edges.len(), 1);
+        assert_eq!(graph.edges[0].label(), "2x avg 2.000us");
+
+        let dot = graph.to_dot().unwrap();
+        assert!(dot.contains("digraph \"test \\\"graph\\\"\""));
+        assert!(dot.contains("subgraph cluster_0"));
+        assert!(dot.contains(
+            "n1 [label=\"kprobe:kfree_skb_reason\\ndrop NO_SOCKET\", style=\"rounded,filled\", fillcolor=\"#ff9999\"];"
+        ));
+        assert!(dot.contains("n0 -> n1 [label=\"2x avg 2.000us\", penwidth=5.0];"));
+
+        let mermaid = graph.to_mermaid().unwrap();
+        assert!(mermaid.contains("title: \"test #quot;graph#quot;\""));
+        assert!(mermaid.contains("subgraph c0 [\"ns 1 if 2 (eth0)\"]"));
+        assert!(mermaid.contains("n0 -->|\"2x avg 2.000us\"| n1"));
+        assert!(mermaid.contains("style n1 fill:#ff9999"));
+        assert!(mermaid.contains("linkStyle 0 stroke-width:5.0px"));
+    }
+}
@@ -0,0 +1,481 @@
+//! # Import
+//!
+//! Import is a post-processing command converting data from other tools (e.g.
+//! packet captures) to Retis events, so they can be post-processed without
+//! having to collect events from a live kernel.
+
+use std::{
+    borrow::Cow,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, BufWriter, Read},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{bail, Result};
+use clap::{Args, Parser, Subcommand};
+use log::{info, warn};
+use pcap_file::{
+    pcap::PcapReader,
+    pcapng::{blocks::interface_description::InterfaceDescriptionOption, Block, PcapNgReader},
+    DataLink,
+};
+
+use crate::{
+    cli::*,
+    collect::collector::skb::unmarshal_raw_packet,
+    core::{kernel::Symbol, probe::kernel::utils::*},
+    events::*,
+    helpers::signals::Running,
+    process::display::*,
+};
+
+/// Magic number of pcap-ng files (section header block type).
+const PCAPNG_MAGIC: [u8; 4] = [0x0a, 0x0d, 0x0d, 0x0a];
+
+#[derive(Debug, Subcommand)]
+enum ImportSubCommand {
+    /// Import packets from a pcap or pcap-ng file
+    Pcap(ImportPcap),
+}
+
+impl Default for ImportSubCommand {
+    fn default() -> Self {
+        ImportSubCommand::Pcap(ImportPcap::default())
+    }
+}
+
+#[derive(Args, Debug, Default)]
+struct ImportPcap {
+    /// pcap or pcap-ng file from which to read packets.
+    input: PathBuf,
+
+    /// File to which the events are written.
+    #[arg(short, long, default_value = "retis.data")]
+    out: PathBuf,
+
+    /// Probe the synthetic events are reported as coming from. Probes should
+    /// follow the [TYPE:]TARGET pattern, see `retis collect --help` for more
+    /// details.
+    #[arg(long, default_value = "tp:net:netif_receive_skb")]
+    probe: String,
+
+    /// Add synthetic tracking information to the events, each packet being
+    /// tracked on its own.
+    #[arg(long)]
+    tracking: bool,
+}
+
+/// Import events from other formats
+///
+/// Converts data from other tools to Retis events, which can then be
+/// post-processed as any other event file (e.g. using `print`, `sort`, `pcap`
+/// or Python scripts). Importing data does not require root privileges nor a
+/// live kernel.
+#[derive(Parser, Debug, Default)]
+#[command(name = "import")]
+pub(crate) struct Import {
+    #[command(subcommand)]
+    command: ImportSubCommand,
+}
+
+impl SubCommandParserRunner for Import {
+    fn run(&mut self, _: &MainConfig) -> Result<()> {
+        match &self.command {
+            ImportSubCommand::Pcap(args) => {
+                if matches!(
+                    (args.input.canonicalize(), args.out.canonicalize()),
+                    (Ok(input), Ok(out)) if input == out
+                ) {
+                    bail!("Cannot write the events to the input file.");
+                }
+
+                let mut printer = PrintEvent::new(
+                    Box::new(BufWriter::new(
+                        OpenOptions::new()
+                            .create(true)
+                            .write(true)
+                            .truncate(true)
+                            .open(&args.out)
+                            .or_else(|_| {
+                                bail!("Could not create or open '{}'", args.out.display())
+                            })?,
+                    )),
+                    PrintEventFormat::Json,
+                );
+
+                let mut importer = PcapImporter::new(&args.probe, args.tracking)?;
+                import_pcap(&args.input, &mut importer, |e| printer.process_one(&e))?;
+                printer.flush()?;
+
+                importer.report_stats();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Converts packets to Retis events.
+struct PcapImporter {
+    /// Synthetic kernel section added to all events.
+    kernel: KernelEvent,
+    /// Whether to add synthetic tracking information.
+    tracking: bool,
+    /// Number of packets converted so far.
+    packets: u64,
+    /// Number of packets skipped because of an unsupported link type.
+    unsupported: u64,
+}
+
+impl PcapImporter {
+    fn new(probe: &str, tracking: bool) -> Result<Self> {
+        let (probe_type, target) = parse_cli_probe(probe)?;
+
+        Ok(Self {
+            kernel: KernelEvent {
+                symbol: Symbol::from_name_no_inspect(target).name(),
+                probe_type: probe_type.to_str().to_string(),
+                stack_trace: None,
+            },
+            tracking,
+            packets: 0,
+            unsupported: 0,
+        })
+    }
+
+    /// Returns the startup event to be written before the packet events. As
+    /// capture timestamps are wall-clock ones, the monotonic clock offset is
+    /// zero.
+    fn startup(timestamp: u64) -> Result<Event> {
+        let mut event = Event::new();
+        event.insert_section(
+            SectionId::Common,
+            Box::new(CommonEvent {
+                timestamp,
+                ..Default::default()
+            }),
+        )?;
+        event.insert_section(
+            SectionId::Startup,
+            Box::new(StartupEvent {
+                retis_version: option_env!("RELEASE_VERSION")
+                    .unwrap_or("unspec")
+                    .to_string(),
+                clock_monotonic_offset: TimeSpec::new(0, 0),
+            }),
+        )?;
+        Ok(event)
+    }
+
+    /// Converts a single packet to an event. Returns None if the link type
+    /// of the packet is not supported.
+    fn event(
+        &mut self,
+        timestamp: Duration,
+        len: u32,
+        data: &[u8],
+        linktype: DataLink,
+        dev: Option<SkbDevEvent>,
+    ) -> Result<Option<Event>> {
+        // Packets are parsed starting at the Ethernet header, add a fake one
+        // for raw IP packets as the collector does.
+        let (packet, fake_eth) = match linktype {
+            DataLink::ETHERNET => (Cow::Borrowed(data), false),
+            DataLink::RAW | DataLink::IPV4 | DataLink::IPV6 => {
+                let etype: u16 = match data.first().map(|b| b >> 4) {
+                    Some(4) => 0x0800,
+                    Some(6) => 0x86dd,
+                    _ => {
+                        self.unsupported += 1;
+                        return Ok(None);
+                    }
+                };
+                let mut packet = vec![0; 12];
+                packet.extend_from_slice(&etype.to_be_bytes());
+                packet.extend_from_slice(data);
+                (Cow::Owned(packet), true)
+            }
+            _ => {
+                self.unsupported += 1;
+                return Ok(None);
+            }
+        };
+        let len = match fake_eth {
+            true => len + 14,
+            false => len,
+        };
+
+        let timestamp = timestamp.as_nanos() as u64;
+        self.packets += 1;
+
+        let mut skb = SkbEvent {
+            dev,
+            ..Default::default()
+        };
+        unmarshal_raw_packet(&mut skb, &packet, len, fake_eth, true)?;
+
+        let mut event = Event::new();
+        event.insert_section(
+            SectionId::Common,
+            Box::new(CommonEvent {
+                timestamp,
+                ..Default::default()
+            }),
+        )?;
+        event.insert_section(SectionId::Kernel, Box::new(self.kernel.clone()))?;
+        event.insert_section(SectionId::Skb, Box::new(skb))?;
+        if self.tracking {
+            // Use the packet index as a unique head, so each packet is
+            // tracked on its own.
+            event.insert_section(
+                SectionId::SkbTracking,
+                Box::new(SkbTrackingEvent {
+                    orig_head: self.packets,
+                    timestamp,
+                    skb: self.packets,
+                }),
+            )?;
+        }
+
+        Ok(Some(event))
+    }
+
+    fn report_stats(&self) {
+        info!("{} packet(s) were imported", self.packets);
+
+        if self.unsupported != 0 {
+            warn!(
+                "{} packet(s) were skipped because of an unsupported link type",
+                self.unsupported
+            );
+        }
+    }
+}
+
+/// Reads packets from a pcap or pcap-ng file and converts them to events,
+/// starting with a startup event.
+fn import_pcap<F>(input: &Path, importer: &mut PcapImporter, mut callback: F) -> Result<()>
+where
+    F: FnMut(Event) -> Result<()>,
+{
+    // Create running instance that will handle signal termination.
+    let run = Running::new();
+    run.register_term_signals()?;
+
+    let mut reader = BufReader::new(
+        File::open(input).or_else(|_| bail!("Could not open '{}'", input.display()))?,
+    );
+    let pcapng = reader.fill_buf()?.starts_with(&PCAPNG_MAGIC);
+
+    let mut started = false;
+    let mut process = |event: Option<Event>| -> Result<()> {
+        if let Some(event) = event {
+            if !started {
+                started = true;
+                let timestamp = event
+                    .get_section::<CommonEvent>(SectionId::Common)
+                    .map(|c| c.timestamp)
+                    .unwrap_or_default();
+                callback(PcapImporter::startup(timestamp)?)?;
+            }
+            callback(event)?;
+        }
+        Ok(())
+    };
+
+    match pcapng {
+        true => read_pcapng(reader, importer, &run, &mut process)?,
+        false => read_pcap(reader, importer, &run, &mut process)?,
+    }
+
+    if !started {
+        bail!("No packet could be imported from '{}'", input.display());
+    }
+    Ok(())
+}
+
+fn read_pcap<R: Read>(
+    reader: R,
+    importer: &mut PcapImporter,
+    run: &Running,
+    process: &mut dyn FnMut(Option<Event>) -> Result<()>,
+) -> Result<()> {
+    let mut reader = PcapReader::new(reader)?;
+    let linktype = reader.header().datalink;
+
+    while run.running() {
+        match reader.next_packet() {
+            Some(packet) => {
+                let packet = packet?;
+                process(importer.event(
+                    packet.timestamp,
+                    packet.orig_len,
+                    &packet.data,
+                    linktype,
+                    None,
+                )?)?;
+            }
+            None => break,
+        }
+    }
+
+    Ok(())
+}
+
+fn read_pcapng<R: Read>(
+    reader: R,
+    importer: &mut PcapImporter,
+    run: &Running,
+    process: &mut dyn FnMut(Option<Event>) -> Result<()>,
+) -> Result<()> {
+    let mut reader = PcapNgReader::new(reader)?;
+    // Link type and device of interfaces, by interface id.
+    let mut ifaces: Vec<(DataLink, SkbDevEvent)> = Vec::new();
+
+    while run.running() {
+        let block = match reader.next_block() {
+            Some(block) => block?,
+            None => break,
+        };
+
+        match block {
+            // Interface ids are reset by new sections.
+            Block::SectionHeader(_) => ifaces.clear(),
+            Block::InterfaceDescription(idb) => {
+                let name = idb
+                    .options
+                    .iter()
+                    .find_map(|o| match o {
+                        InterfaceDescriptionOption::IfName(name) => Some(name.to_string()),
+                        _ => None,
+                    })
+                    .unwrap_or_default();
+                ifaces.push((
+                    idb.linktype,
+                    SkbDevEvent {
+                        name,
+                        ifindex: ifaces.len() as u32 + 1,
+                        rx_ifindex: None,
+                    },
+                ));
+            }
+            Block::EnhancedPacket(epb) => {
+                let (linktype, dev) = match ifaces.get(epb.interface_id as usize) {
+                    Some(iface) => iface.clone(),
+                    None => bail!("Packet references unknown interface {}", epb.interface_id),
+                };
+                process(importer.event(
+                    epb.timestamp,
+                    epb.original_len,
+                    &epb.data,
+                    linktype,
+                    Some(dev),
+                )?)?;
+            }
+            // Simple packets have no timestamp and belong to the first
+            // interface.
+            Block::SimplePacket(spb) => {
+                let (linktype, dev) = match ifaces.first() {
+                    Some(iface) => iface.clone(),
+                    None => bail!("Packet found before any interface description"),
+                };
+                process(importer.event(
+                    Duration::ZERO,
+                    spb.original_len,
+                    &spb.data,
+                    linktype,
+                    Some(dev),
+                )?)?;
+            }
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn import(input: &str, tracking: bool) -> Vec<Event> {
+        let mut events = Vec::new();
+        let mut importer = PcapImporter::new("tp:net:netif_receive_skb", tracking).unwrap();
+        import_pcap(Path::new(input), &mut importer, |e| {
+            events.push(e);
+            Ok(())
+        })
+        .unwrap();
+        events
+    }
+
+    #[test]
+    fn import_pcap_file() {
+        let events = import("test_data/test_packets.pcap", false);
+        assert_eq!(events.len(), 3);
+
+        // Startup event, with a zero offset as timestamps are wall-clock ones.
+        let offset = events[0]
+            .get_section::<StartupEvent>(SectionId::Startup)
+            .unwrap()
+            .clock_monotonic_offset;
+        assert_eq!((offset.sec(), offset.nsec()), (0, 0));
+
+        for (i, event) in events[1..].iter().enumerate() {
+            let common = event.get_section::<CommonEvent>(SectionId::Common).unwrap();
+            assert_eq!(
+                common.timestamp,
+                (1742309146 + i as u64) * 1_000_000_000 + 500_000_000
+            );
+
+            let kernel = event.get_section::<KernelEvent>(SectionId::Kernel).unwrap();
+            assert_eq!(kernel.probe_type, "raw_tracepoint");
+            assert_eq!(kernel.symbol, "net:netif_receive_skb");
+
+            let skb = event.get_section::<SkbEvent>(SectionId::Skb).unwrap();
+            assert!(skb.eth.is_some());
+            assert_eq!(skb.ip.as_ref().unwrap().protocol, 1);
+            assert!(skb.icmp.is_some());
+            assert_eq!(skb.packet.as_ref().unwrap().len, 98);
+            assert!(skb.dev.is_none());
+
+            assert!(event
+                .get_section::<SkbTrackingEvent>(SectionId::SkbTracking)
+                .is_none());
+        }
+    }
+
+    #[test]
+    fn import_pcapng_file() {
+        let events = import("test_data/test_packets.pcapng", true);
+        assert_eq!(events.len(), 3);
+
+        let mut ids = Vec::new();
+        for event in events[1..].iter() {
+            let skb = event.get_section::<SkbEvent>(SectionId::Skb).unwrap();
+            // Raw IP packets get a fake Ethernet header, which is not
+            // reported.
+            assert!(skb.eth.is_none());
+            assert_eq!(skb.ip.as_ref().unwrap().protocol, 6);
+            assert_eq!(skb.dev.as_ref().unwrap().name, "eth0");
+            assert_eq!(skb.packet.as_ref().unwrap().len, 54);
+
+            ids.push(
+                event
+                    .get_section::<SkbTrackingEvent>(SectionId::SkbTracking)
+                    .unwrap()
+                    .tracking_id(),
+            );
+        }
+
+        // The first packet is complete, the second one was truncated.
+        let skb = |i: usize| events[i].get_section::<SkbEvent>(SectionId::Skb).unwrap();
+        assert_eq!(skb(1).tcp.as_ref().unwrap().dport, 443);
+        assert_eq!(skb(1).packet.as_ref().unwrap().capture_len, 54);
+        assert!(skb(2).tcp.is_none());
+        assert_eq!(skb(2).packet.as_ref().unwrap().capture_len, 34);
+
+        // Each packet is tracked on its own.
+        assert_ne!(ids[0], ids[1]);
+    }
+}
@@ -0,0 +1,430 @@
+//! # Latency
+//!
+//! Latency is a post-processing command computing the time packets spend
+//! between probes, using the series of events built by the tracking logic.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use clap::Parser;
+use serde_json::json;
+
+use crate::{
+    cli::*,
+    core::{kernel::Symbol, probe::kernel::utils::*},
+    events::*,
+    helpers::{signals::Running, time::format_ns},
+    process::series::SeriesReader,
+};
+
+use super::DEFAULT_BUFFER;
+
+/// Matches events against a probe given as a cli argument.
+struct ProbeMatcher {
+    probe_type: &'static str,
+    symbol: String,
+}
+
+impl ProbeMatcher {
+    /// Creates a ProbeMatcher from a probe following the [TYPE:]TARGET pattern.
+    fn from_cli(probe: &str) -> Result<Self> {
+        let (probe_type, target) = parse_cli_probe(probe)?;
+        Ok(Self {
+            probe_type: probe_type.to_str(),
+            symbol: Symbol::from_name_no_inspect(target).name(),
+        })
+    }
+
+    fn matches(&self, event: &Event) -> bool {
+        event
+            .get_section::<KernelEvent>(SectionId::Kernel)
+            .is_some_and(|k| k.probe_type == self.probe_type && k.symbol == self.symbol)
+    }
+}
+
+/// Latency samples between two probes.
+#[derive(Default)]
+struct PairLatency {
+    /// Deltas between the two probes, in nanoseconds.
+    samples: Vec<u64>,
+}
+
+impl PairLatency {
+    fn add(&mut self, delta: u64) {
+        self.samples.push(delta);
+    }
+
+    /// Returns the p-th percentile, using the nearest-rank method. Samples
+    /// must be sorted.
+    fn percentile(&self, p: u64) -> u64 {
+        let rank = (p * self.samples.len() as u64).div_ceil(100).max(1);
+        self.samples[rank as usize - 1]
+    }
+
+    /// Returns a log2 histogram of the samples as a list of (lower bound,
+    /// upper bound, count). Bounds are in nanoseconds, the upper one being
+    /// excluded. Samples must be sorted.
+    fn histogram(&self) -> Vec<(u64, u64, u64)> {
+        let mut buckets: Vec<(u64, u64, u64)> = Vec::new();
+
+        for delta in self.samples.iter() {
+            let (low, high) = match delta {
+                0 | 1 => (0, 2),
+                _ => {
+                    let order = delta.ilog2();
+                    (1 << order, 1u64.checked_shl(order + 1).unwrap_or(u64::MAX))
+                }
+            };
+
+            match buckets.last_mut() {
+                Some(bucket) if bucket.0 == low => bucket.2 += 1,
+                _ => buckets.push((low, high, 1)),
+            }
+        }
+
+        buckets
+    }
+}
+
+/// Latencies between pairs of probes.
+#[derive(Default)]
+pub(crate) struct LatencyStats {
+    from: Option<ProbeMatcher>,
+    to: Option<ProbeMatcher>,
+    pairs: HashMap<(String, String), PairLatency>,
+}
+
+impl LatencyStats {
+    /// Creates a new LatencyStats. Optional `from` and `to` probes restrict
+    /// the reported pairs to the ones starting and/or ending at them.
+    fn new(from: Option<&str>, to: Option<&str>) -> Result<Self> {
+        Ok(Self {
+            from: from.map(ProbeMatcher::from_cli).transpose()?,
+            to: to.map(ProbeMatcher::from_cli).transpose()?,
+            ..Default::default()
+        })
+    }
+
+    /// Account for the events of a single series. Deltas are computed between
+    /// consecutive events, or when using start and/or end probes:
+    /// - Between a start probe and the event following it.
+    /// - Between an end probe and the event preceding it.
+    /// - Between an end probe and the last start probe preceding it.
+    fn process_series(&mut self, series: &EventSeries) {
+        let mut events = series
+            .events
+            .iter()
+            .filter_map(|e| {
+                let probe = e.probe_name()?;
+                let common = e.get_section::<CommonEvent>(SectionId::Common)?;
+                Some((common.timestamp, probe, e))
+            })
+            .collect::<Vec<_>>();
+        events.sort_by_key(|(ts, _, _)| *ts);
+
+        // A missing probe matches all events.
+        let matches = |m: &Option<ProbeMatcher>, e: &Event| match m {
+            Some(m) => m.matches(e),
+            None => true,
+        };
+        let is_from = |e: &Event| matches(&self.from, e);
+        let is_to = |e: &Event| matches(&self.to, e);
+
+        let mut deltas = Vec::new();
+        match (&self.from, &self.to) {
+            (Some(_), Some(_)) => {
+                let mut start = None;
+                for (i, (_, _, e)) in events.iter().enumerate() {
+                    if is_to(e) {
+                        if let Some(start) = start.take() {
+                            deltas.push((start, i));
+                        }
+                    }
+                    if is_from(e) {
+                        start = Some(i);
+                    }
+                }
+            }
+            _ => events.windows(2).enumerate().for_each(|(i, w)| {
+                if is_from(w[0].2) && is_to(w[1].2) {
+                    deltas.push((i, i + 1));
+                }
+            }),
+        }
+
+        for (start, end) in deltas {
+            let (start_ts, start_probe, _) = &events[start];
+            let (end_ts, end_probe, _) = &events[end];
+
+            self.pairs
+                .entry((start_probe.clone(), end_probe.clone()))
+                .or_default()
+                .add(end_ts - start_ts);
+        }
+    }
+
+    /// Sorts the samples of all pairs. Must be called once all series were
+    /// processed and before reporting the latencies.
+    fn finish(&mut self) {
+        self.pairs
+            .values_mut()
+            .for_each(|p| p.samples.sort_unstable());
+    }
+
+    /// Returns the probe pairs, sorted by number of samples.
+    fn sorted_pairs(&self) -> Vec<(&(String, String), &PairLatency)> {
+        let mut pairs = self.pairs.iter().collect::<Vec<_>>();
+        pairs.sort_by(|a, b| {
+            b.1.samples
+                .len()
+                .cmp(&a.1.samples.len())
+                .then_with(|| a.0.cmp(b.0))
+        });
+        pairs
+    }
+
+    /// Returns the latencies in a JSON representation.
+    pub(crate) fn to_json(&self) -> serde_json::Value {
+        let pairs = self
+            .sorted_pairs()
+            .iter()
+            .map(|((from, to), pair)| {
+                json!({
+                    "from": from,
+                    "to": to,
+                    "count": pair.samples.len(),
+                    "min_ns": pair.samples[0],
+                    "p50_ns": pair.percentile(50),
+                    "p90_ns": pair.percentile(90),
+                    "p99_ns": pair.percentile(99),
+                    "max_ns": pair.samples[pair.samples.len() - 1],
+                    "histogram": pair
+                        .histogram()
+                        .iter()
+                        .map(|(low, high, count)| json!({
+                            "low_ns": low,
+                            "high_ns": high,
+                            "count": count,
+                        }))
+                        .collect::<Vec<_>>(),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        json!({ "pairs": pairs })
+    }
+
+    /// Returns an object implementing `Display` for the latencies.
+    pub(crate) fn display(&self) -> impl fmt::Display + '_ {
+        LatencyDisplay {
+            pairs: self.sorted_pairs(),
+        }
+    }
+}
+
+/// Width of the histogram bars, in characters.
+const BAR_WIDTH: u64 = 40;
+
+struct LatencyDisplay<'a> {
+    pairs: Vec<(&'a (String, String), &'a PairLatency)>,
+}
+
+impl fmt::Display for LatencyDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, ((from, to), pair)) in self.pairs.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+
+            writeln!(f, "{from} -> {to}: {} sample(s)", pair.samples.len())?;
+            writeln!(
+                f,
+                "  min {}  p50 {}  p90 {}  p99 {}  max {}",
+                format_ns(pair.samples[0]),
+                format_ns(pair.percentile(50)),
+                format_ns(pair.percentile(90)),
+                format_ns(pair.percentile(99)),
+                format_ns(pair.samples[pair.samples.len() - 1]),
+            )?;
+
+            let histogram = pair
+                .histogram()
+                .iter()
+                .map(|(low, high, count)| {
+                    (
+                        format!("[{}, {})", format_ns(*low), format_ns(*high)),
+                        *count,
+                    )
+                })
+                .collect::<Vec<_>>();
+            let range_width = histogram.iter().map(|(r, _)| r.len()).max().unwrap_or(0);
+            let count_width = histogram
+                .iter()
+                .map(|(_, c)| c.to_string().len())
+                .max()
+                .unwrap_or(0);
+            let max = histogram.iter().map(|(_, c)| *c).max().unwrap_or(1);
+
+            for (range, count) in histogram.iter() {
+                let bar = "@".repeat((count * BAR_WIDTH).div_ceil(max) as usize);
+                writeln!(
+                    f,
+                    "  {range:<range_width$}  {count:>count_width$} |{bar:<width$}|",
+                    width = BAR_WIDTH as usize
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Compute latencies between probes
+///
+/// Reads events from the INPUT file (sorted or not), groups them by packet and
+/// reports the latency between consecutive probes as percentiles and log2
+/// histograms, per probe pair. Start and end probes can be selected using
+/// --from and --to.
+#[derive(Parser, Debug, Default)]
+#[command(name = "latency")]
+pub(crate) struct Latency {
+    /// File from which to read events.
+    #[arg(default_value = "retis.data")]
+    pub(super) input: PathBuf,
+
+    /// Maximum number of events to buffer when sorting events.
+    ///
+    /// See `retis sort --help` for more details. A value of zero means the
+    /// buffer can grow endlessly.
+    #[arg(long, default_value_t = DEFAULT_BUFFER)]
+    pub(super) max_buffer: usize,
+
+    /// Only report latencies starting at this probe. Probes should follow the
+    /// [TYPE:]TARGET pattern, see `retis collect --help` for more details.
+    #[arg(long)]
+    pub(super) from: Option<String>,
+
+    /// Only report latencies ending at this probe. Probes should follow the
+    /// [TYPE:]TARGET pattern, see `retis collect --help` for more details.
+    ///
+    /// When used with --from, latencies are computed between each occurrence
+    /// of this probe and the last --from probe preceding it.
+    #[arg(long)]
+    pub(super) to: Option<String>,
+
+    /// Output format.
+    #[arg(long)]
+    #[clap(value_enum, default_value_t = ReportFormat::Text)]
+    pub(super) format: ReportFormat,
+}
+
+impl SubCommandParserRunner for Latency {
+    fn run(&mut self, _: &MainConfig) -> Result<()> {
+        let stats = compute_latency(
+            self.input.as_path(),
+            self.max_buffer,
+            self.from.as_deref(),
+            self.to.as_deref(),
+        )?;
+
+        if stats.pairs.is_empty() {
+            log::info!("No latency could be computed from the events");
+        }
+
+        match self.format {
+            ReportFormat::Text => print!("{}", stats.display()),
+            ReportFormat::Json => println!("{}", stats.to_json()),
+        }
+
+        Ok(())
+    }
+}
+
+/// Compute the latencies between probes of an event or series file.
+fn compute_latency(
+    input: &Path,
+    max_buffer: usize,
+    from: Option<&str>,
+    to: Option<&str>,
+) -> Result<LatencyStats> {
+    // Create running instance that will handle signal termination.
+    let run = Running::new();
+    run.register_term_signals()?;
+
+    let mut reader = SeriesReader::new(input, max_buffer)?;
+    let mut stats = LatencyStats::new(from, to)?;
+
+    while run.running() {
+        match reader.next_series()? {
+            Some(series) => stats.process_series(&series),
+            None => break,
+        }
+    }
+    stats.finish();
+
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "test_data/test_events_packets.json";
+
+    #[test]
+    fn latency_from_file() {
+        let stats = compute_latency(Path::new(INPUT), DEFAULT_BUFFER, None, None).unwrap();
+        assert!(!stats.pairs.is_empty());
+
+        let json = stats.to_json();
+        for pair in json["pairs"].as_array().unwrap() {
+            let count = pair["count"].as_u64().unwrap();
+            assert!(pair["min_ns"].as_u64() <= pair["p50_ns"].as_u64());
+            assert!(pair["p50_ns"].as_u64() <= pair["p99_ns"].as_u64());
+            assert!(pair["p99_ns"].as_u64() <= pair["max_ns"].as_u64());
+            assert_eq!(
+                pair["histogram"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|b| b["count"].as_u64().unwrap())
+                    .sum::<u64>(),
+                count
+            );
+        }
+
+        // Filter on start and end probes.
+        let stats = compute_latency(
+            Path::new(INPUT),
+            DEFAULT_BUFFER,
+            Some("tp:net:netif_receive_skb"),
+            Some("tp:net:net_dev_start_xmit"),
+        )
+        .unwrap();
+        assert!(!stats.pairs.is_empty());
+        assert!(stats.pairs.keys().all(|(from, to)| {
+            from == "raw_tracepoint:net:netif_receive_skb"
+                && to == "raw_tracepoint:net:net_dev_start_xmit"
+        }));
+    }
+
+    #[test]
+    fn percentiles() {
+        let mut pair = PairLatency::default();
+        (1..=100).rev().for_each(|d| pair.add(d * 1000));
+        pair.samples.sort_unstable();
+
+        assert_eq!(pair.percentile(50), 50_000);
+        assert_eq!(pair.percentile(90), 90_000);
+        assert_eq!(pair.percentile(99), 99_000);
+        assert_eq!(pair.percentile(100), 100_000);
+
+        let histogram = pair.histogram();
+        assert_eq!(histogram[0], (512, 1024, 1));
+        assert_eq!(histogram.iter().map(|(_, _, c)| c).sum::<u64>(), 100);
+    }
+}
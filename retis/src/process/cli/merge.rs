@@ -0,0 +1,413 @@
+//! # Merge
+//!
+//! Merge is a post-processing command combining events collected on multiple
+//! hosts into a single timeline.
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    fs::OpenOptions,
+    hash::{Hash, Hasher},
+    io::{stdout, BufWriter},
+    path::PathBuf,
+    str::FromStr,
+};
+
+use anyhow::{anyhow, bail, Result};
+use clap::Parser;
+use pnet_packet::{
+    ethernet::*, ip::*, ipv4::Ipv4Packet, ipv6::Ipv6Packet, tcp::TcpPacket, udp::UdpPacket, Packet,
+};
+
+use crate::{
+    cli::*,
+    events::{
+        file::{FileEventsFactory, FileType},
+        *,
+    },
+    helpers::signals::Running,
+    process::{display::*, template::EventTemplate},
+};
+
+/// Event file to merge, along with the name of the host it was collected on.
+#[derive(Clone, Debug)]
+pub(crate) struct MergeInput {
+    host: String,
+    path: PathBuf,
+}
+
+impl FromStr for MergeInput {
+    type Err = anyhow::Error;
+
+    /// Parses an input following the [HOST=]FILE pattern. The host defaults to
+    /// the file name, without its extension.
+    fn from_str(s: &str) -> Result<Self> {
+        let (host, path) = match s.split_once('=') {
+            Some((host, path)) => (host.to_string(), PathBuf::from(path)),
+            None => {
+                let path = PathBuf::from(s);
+                let host = path
+                    .file_stem()
+                    .ok_or_else(|| anyhow!("Invalid input file '{s}'"))?
+                    .to_string_lossy()
+                    .to_string();
+                (host, path)
+            }
+        };
+
+        if host.is_empty() || path.as_os_str().is_empty() {
+            bail!("Invalid input '{s}' (expected [HOST=]FILE)");
+        }
+
+        Ok(Self { host, path })
+    }
+}
+
+/// Converts a CLOCK_MONOTONIC timestamp to a wall-clock one (in nanoseconds
+/// since the epoch), given the monotonic clock offset of its collection.
+fn to_wall_clock(timestamp: u64, offset: &TimeSpec) -> u64 {
+    let time = TimeSpec::new(0, timestamp as i64) + *offset;
+    (time.sec() * 1_000_000_000 + time.nsec()) as u64
+}
+
+/// Returns a hash of the L4 payload of the raw packet of an event, if any. The
+/// L4 payload does not change while a packet is forwarded between hosts (which
+/// is not the case of e.g. the TTL or MAC addresses).
+fn payload_hash(event: &Event) -> Option<u64> {
+    let skb = event.get_section::<SkbEvent>(SectionId::Skb)?;
+    let eth = EthernetPacket::new(&skb.packet.as_ref()?.packet.0)?;
+
+    let (protocol, payload) = match eth.get_ethertype() {
+        EtherTypes::Ipv4 => {
+            let ip = Ipv4Packet::new(eth.payload())?;
+            (ip.get_next_level_protocol(), ip.payload().to_vec())
+        }
+        EtherTypes::Ipv6 => {
+            let ip = Ipv6Packet::new(eth.payload())?;
+            (ip.get_next_header(), ip.payload().to_vec())
+        }
+        _ => return None,
+    };
+
+    let payload = match protocol {
+        IpNextHeaderProtocols::Tcp => TcpPacket::new(&payload)?.payload().to_vec(),
+        IpNextHeaderProtocols::Udp => UdpPacket::new(&payload)?.payload().to_vec(),
+        // For other protocols (e.g. ICMP) use the full L4 data.
+        _ => payload,
+    };
+
+    // Packets without a payload (e.g. TCP ACKs) can't be told apart.
+    if payload.is_empty() {
+        return None;
+    }
+
+    let mut hasher = DefaultHasher::new();
+    protocol.0.hash(&mut hasher);
+    payload.hash(&mut hasher);
+    Some(hasher.finish())
+}
+
+/// Merge events collected on multiple hosts
+///
+/// Reads events from the INPUTS files, converts their timestamps to wall-clock
+/// time using the information gathered at collection startup, tags them with
+/// the host they were collected on (in the 'origin' section) and orders them
+/// globally. All events are loaded in memory.
+///
+/// The resulting events can be processed as any other event file (e.g. using
+/// `print` or `sort`), with timestamps being the time since the epoch.
+#[derive(Parser, Debug, Default)]
+#[command(name = "merge")]
+pub(crate) struct Merge {
+    /// Event files to merge, following the [HOST=]FILE pattern. If not given,
+    /// the host name defaults to the file name without its extension.
+    #[arg(required = true, num_args = 2..)]
+    pub(super) inputs: Vec<MergeInput>,
+
+    /// Correlate the same packet across hosts, by hashing the payload of the
+    /// raw packets (see the `pcap` profile). Events of packets seen on multiple
+    /// hosts have the 'payload_hash' field of their 'origin' section set.
+    #[arg(long)]
+    pub(super) correlate: bool,
+
+    /// Write the merged events to a file rather than to stdout.
+    #[arg(short, long)]
+    pub(super) out: Option<PathBuf>,
+
+    /// Write events to stdout even if --out is used.
+    #[arg(long, default_value = "false")]
+    pub(super) print: bool,
+
+    /// Format used when printing an event.
+    #[arg(long)]
+    #[clap(value_enum, default_value_t=CliDisplayFormat::MultiLine)]
+    pub(super) format: CliDisplayFormat,
+
+    /// Comma-separated list of columns printed when using the table format.
+    #[arg(long, value_delimiter = ',', default_value = TableColumn::DEFAULT)]
+    pub(super) columns: Vec<TableColumn>,
+
+    /// Print events on a single line following a template referencing event
+    /// fields, e.g. '{common.timestamp} {kernel.symbol} {skb-drop.drop_reason?}'.
+    /// Fields are referenced using their path in the JSON representation of
+    /// events; missing fields are printed as '-' unless followed by '?'. Values
+    /// can be formatted using ':hex', ':ifname' or ':delta' (e.g.
+    /// '{common.timestamp:delta}'). Takes precedence over --format.
+    #[arg(long)]
+    pub(super) template: Option<EventTemplate>,
+
+    /// Print the time as UTC.
+    #[arg(long)]
+    pub(super) utc: bool,
+}
+
+impl SubCommandParserRunner for Merge {
+    fn run(&mut self, _: &MainConfig) -> Result<()> {
+        let mut printers = Vec::new();
+
+        if let Some(out) = &self.out {
+            // If the file doesn't exist it is not one of the inputs.
+            let canonical = out.canonicalize().ok();
+            if canonical.is_some()
+                && self
+                    .inputs
+                    .iter()
+                    .any(|i| i.path.canonicalize().ok() == canonical)
+            {
+                bail!("Cannot write the merged events to one of the input files.");
+            }
+
+            printers.push(PrintEvent::new(
+                Box::new(BufWriter::new(
+                    OpenOptions::new()
+                        .create(true)
+                        .write(true)
+                        .truncate(true)
+                        .open(out)
+                        .or_else(|_| bail!("Could not create or open '{}'", out.display()))?,
+                )),
+                PrintEventFormat::Json,
+            ));
+        }
+
+        if self.out.is_none() || self.print {
+            let format = DisplayFormat::new()
+                .multiline(self.format == CliDisplayFormat::MultiLine)
+                .time_format(if self.utc {
+                    TimeFormat::UtcDate
+                } else {
+                    TimeFormat::MonotonicTimestamp
+                })
+                .table(if self.format == CliDisplayFormat::Table {
+                    self.columns.clone()
+                } else {
+                    Vec::new()
+                });
+
+            printers.push(PrintEvent::new(
+                Box::new(stdout()),
+                match &self.template {
+                    Some(template) => PrintEventFormat::Template(template.clone()),
+                    None => PrintEventFormat::Text(format),
+                },
+            ));
+        }
+
+        for event in merge(&self.inputs, self.correlate)? {
+            printers
+                .iter_mut()
+                .try_for_each(|p| p.process_one(&event))?;
+        }
+
+        printers.iter_mut().try_for_each(|p| p.flush())?;
+        Ok(())
+    }
+}
+
+/// Reads the events of a single input, converting their timestamps and tagging
+/// them with their origin.
+fn read_input(input: &MergeInput, run: &Running) -> Result<Vec<Event>> {
+    let mut factory = FileEventsFactory::new(input.path.as_path())?;
+    if matches!(factory.file_type(), FileType::Series) {
+        bail!(
+            "'{}' contains sorted events, please use unsorted files",
+            input.path.display()
+        );
+    }
+
+    let mut events = Vec::new();
+    let mut offset = None;
+
+    while run.running() {
+        let mut event = match factory.next_event()? {
+            Some(event) => event,
+            None => break,
+        };
+
+        // Startup events apply to the following events of the file.
+        if let Some(startup) = event.get_section::<StartupEvent>(SectionId::Startup) {
+            offset = Some(startup.clock_monotonic_offset);
+            continue;
+        }
+        let offset = offset.as_ref().ok_or_else(|| {
+            anyhow!(
+                "'{}' has events without a preceding startup event, can't convert their timestamps",
+                input.path.display()
+            )
+        })?;
+
+        if let Some(common) = event.get_section_mut::<CommonEvent>(SectionId::Common) {
+            common.timestamp = to_wall_clock(common.timestamp, offset);
+        }
+        // The tracking timestamp is part of the packet identifier, converting
+        // it keeps identifiers unique while being consistent with the event
+        // ordering when sorting.
+        if let Some(tracking) = event.get_section_mut::<SkbTrackingEvent>(SectionId::SkbTracking) {
+            tracking.timestamp = to_wall_clock(tracking.timestamp, offset);
+        }
+
+        // Keep the origin of events coming from an already merged file.
+        match event.get_section_mut::<OriginEvent>(SectionId::Origin) {
+            Some(origin) => origin.payload_hash = None,
+            None => event.insert_section(
+                SectionId::Origin,
+                Box::new(OriginEvent {
+                    host: input.host.clone(),
+                    payload_hash: None,
+                }),
+            )?,
+        }
+
+        events.push(event);
+    }
+
+    Ok(events)
+}
+
+/// Merge the events of multiple inputs and returns them in chronological
+/// order, preceded by a startup event.
+fn merge(inputs: &[MergeInput], correlate: bool) -> Result<Vec<Event>> {
+    // Create running instance that will handle signal termination.
+    let run = Running::new();
+    run.register_term_signals()?;
+
+    let mut events = Vec::new();
+    for input in inputs.iter() {
+        events.extend(read_input(input, &run)?);
+    }
+
+    if correlate {
+        let hashes = events.iter().map(payload_hash).collect::<Vec<_>>();
+
+        // Only report hashes seen on multiple hosts.
+        let mut hosts: HashMap<u64, HashSet<&str>> = HashMap::new();
+        for (event, hash) in events.iter().zip(hashes.iter()) {
+            if let (Some(hash), Some(origin)) =
+                (hash, event.get_section::<OriginEvent>(SectionId::Origin))
+            {
+                hosts.entry(*hash).or_default().insert(origin.host.as_str());
+            }
+        }
+        let shared = hosts
+            .into_iter()
+            .filter_map(|(hash, hosts)| (hosts.len() > 1).then_some(hash))
+            .collect::<HashSet<_>>();
+
+        for (event, hash) in events.iter_mut().zip(hashes) {
+            if let Some(hash) = hash.filter(|h| shared.contains(h)) {
+                if let Some(origin) = event.get_section_mut::<OriginEvent>(SectionId::Origin) {
+                    origin.payload_hash = Some(hash);
+                }
+            }
+        }
+    }
+
+    events.sort_by_key(|e| {
+        e.get_section::<CommonEvent>(SectionId::Common)
+            .map(|c| c.timestamp)
+            .unwrap_or_default()
+    });
+
+    // Timestamps are now wall-clock ones.
+    let mut startup = Event::new();
+    startup.insert_section(
+        SectionId::Common,
+        Box::new(CommonEvent {
+            timestamp: events
+                .first()
+                .and_then(|e| e.get_section::<CommonEvent>(SectionId::Common))
+                .map(|c| c.timestamp)
+                .unwrap_or_default(),
+            ..Default::default()
+        }),
+    )?;
+    startup.insert_section(
+        SectionId::Startup,
+        Box::new(StartupEvent {
+            retis_version: option_env!("RELEASE_VERSION")
+                .unwrap_or("unspec")
+                .to_string(),
+            clock_monotonic_offset: TimeSpec::new(0, 0),
+        }),
+    )?;
+    events.insert(0, startup);
+
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "test_data/test_events_packets.json";
+
+    #[test]
+    fn parse_input() {
+        let input = MergeInput::from_str("host-a=/tmp/a.data").unwrap();
+        assert_eq!(input.host, "host-a");
+        assert_eq!(input.path, PathBuf::from("/tmp/a.data"));
+
+        let input = MergeInput::from_str("/tmp/node1.data").unwrap();
+        assert_eq!(input.host, "node1");
+
+        assert!(MergeInput::from_str("=/tmp/a.data").is_err());
+        assert!(MergeInput::from_str("host-a=").is_err());
+    }
+
+    #[test]
+    fn merge_files() {
+        let inputs = [
+            MergeInput::from_str(&format!("a={INPUT}")).unwrap(),
+            MergeInput::from_str(&format!("b={INPUT}")).unwrap(),
+        ];
+
+        let events = merge(&inputs, true).unwrap();
+        // Startup events are replaced by a single one.
+        assert_eq!(events.len(), 1 + 2 * 26);
+        assert!(events[0]
+            .get_section::<StartupEvent>(SectionId::Startup)
+            .is_some());
+
+        let timestamps = events
+            .iter()
+            .map(|e| {
+                e.get_section::<CommonEvent>(SectionId::Common)
+                    .unwrap()
+                    .timestamp
+            })
+            .collect::<Vec<_>>();
+        assert!(timestamps.windows(2).all(|w| w[0] <= w[1]));
+        // Wall-clock timestamps, after 2025-03-18.
+        assert!(timestamps[1] > 1_742_309_146_000_000_000);
+
+        let origins = events[1..]
+            .iter()
+            .map(|e| e.get_section::<OriginEvent>(SectionId::Origin).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(origins.iter().filter(|o| o.host == "a").count(), 26);
+        assert_eq!(origins.iter().filter(|o| o.host == "b").count(), 26);
+
+        // All events with a raw packet are seen on both "hosts".
+        let correlated = origins.iter().filter(|o| o.payload_hash.is_some()).count();
+        assert!(correlated > 0 && correlated % 2 == 0);
+    }
+}
@@ -2,6 +2,30 @@
 //!
 //! Provides cli commands to perform some post-processing.
 
+pub(crate) mod anonymize;
+pub(crate) use anonymize::*;
+
+pub(crate) mod diff;
+pub(crate) use diff::*;
+
+pub(crate) mod export;
+pub(crate) use export::*;
+
+pub(crate) mod flows;
+pub(crate) use flows::*;
+
+pub(crate) mod graph;
+pub(crate) use graph::*;
+
+pub(crate) mod import;
+pub(crate) use import::*;
+
+pub(crate) mod latency;
+pub(crate) use latency::*;
+
+pub(crate) mod merge;
+pub(crate) use merge::*;
+
 pub(crate) mod pcap;
 pub(crate) use self::pcap::*;
 
@@ -15,3 +39,6 @@ pub(crate) use python::*;
 
 pub(crate) mod sort;
 pub(crate) use sort::*;
+
+pub(crate) mod stats;
+pub(crate) use stats::*;
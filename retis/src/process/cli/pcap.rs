@@ -26,8 +26,75 @@ use crate::{
     core::{kernel::Symbol, probe::kernel::utils::*},
     events::{file::FileEventsFactory, CommonEvent, KernelEvent, SkbEvent, *},
     helpers::signals::Running,
+    process::{
+        filter::{EventMatcher, FilterArgs},
+        series::{ct_state, nft_verdict},
+    },
 };
 
+/// Direction of a packet, as reported in the epb_flags option of enhanced
+/// packet blocks.
+const EPB_FLAGS_INBOUND: u32 = 0b01;
+const EPB_FLAGS_OUTBOUND: u32 = 0b10;
+
+/// Returns the direction of packets seen at a given probe symbol, when known.
+fn direction(symbol: &str) -> Option<u32> {
+    match symbol {
+        "net:netif_receive_skb"
+        | "net:napi_gro_receive_entry"
+        | "net:netif_rx"
+        | "__netif_receive_skb_core"
+        | "netif_receive_skb"
+        | "ip_rcv"
+        | "ipv6_rcv" => Some(EPB_FLAGS_INBOUND),
+        "net:net_dev_queue"
+        | "net:net_dev_start_xmit"
+        | "net:net_dev_xmit"
+        | "dev_queue_xmit"
+        | "dev_hard_start_xmit"
+        | "ip_output"
+        | "ip6_output" => Some(EPB_FLAGS_OUTBOUND),
+        _ => None,
+    }
+}
+
+/// Returns the Retis metadata of an event as a list of `key=value` strings,
+/// to be stored as packet comments. Each one is stored in its own comment so
+/// they can be filtered on individually (e.g. in Wireshark using
+/// `frame.comment == "drop_reason=NO_SOCKET"`).
+fn packet_comments(event: &Event) -> Vec<String> {
+    let mut comments = Vec::new();
+
+    if let Some(probe) = event.probe_name() {
+        comments.push(format!("probe={probe}"));
+    }
+
+    if let Some(tracking_id) = event.tracking_id() {
+        comments.push(format!("tracking_id={tracking_id:#x}"));
+    }
+
+    if let Some(reason) = event.drop_reason() {
+        comments.push(format!("drop_reason={reason}"));
+    }
+
+    if let Some(state) = ct_state(event) {
+        comments.push(format!("ct_state={state}"));
+    }
+
+    if let Some(verdict) = nft_verdict(event) {
+        comments.push(format!("nft={verdict}"));
+    }
+
+    if let Some(ovs) = event.get_section::<OvsEvent>(SectionId::Ovs) {
+        comments.push(format!(
+            "ovs={}",
+            ovs.display(&DisplayFormat::new(), &FormatterConf::new())
+        ));
+    }
+
+    comments
+}
+
 /// Statistics of the event parser about events (processed, skipped, etc).
 #[derive(Default)]
 struct EventParserStats {
@@ -145,17 +212,22 @@ impl EventParser {
             }
         };
 
-        // Add the packet itself.
+        // Add the packet itself, with the event metadata as comments.
+        let mut options = packet_comments(event)
+            .into_iter()
+            .map(|c| EnhancedPacketOption::Comment(Cow::Owned(c)))
+            .collect::<Vec<_>>();
+        if let Some(flags) = direction(&kernel.symbol) {
+            options.push(EnhancedPacketOption::Flags(flags));
+        }
+
         v.push(
             EnhancedPacketBlock {
                 interface_id: id,
                 timestamp: Duration::from_nanos(common.timestamp),
                 original_len: packet.len,
                 data: Cow::Borrowed(&packet.packet.0),
-                options: vec![EnhancedPacketOption::Comment(Cow::Owned(format!(
-                    "probe={}:{}",
-                    &kernel.probe_type, &kernel.symbol
-                )))],
+                options,
             }
             .into_owned()
             .into_block(),
@@ -211,6 +283,8 @@ pub(crate) struct Pcap {
     pub(super) out: Option<PathBuf>,
     #[arg(default_value = "retis.data", help = "File from which to read events")]
     pub(super) input: PathBuf,
+    #[command(flatten)]
+    pub(super) filter: FilterArgs,
 }
 #[derive(Args, Debug, Default)]
 #[group(required = true, multiple = false)]
@@ -224,6 +298,8 @@ pub(crate) struct PcapCmd {
 See `retis collect --help` for more details on the probe format"
     )]
     pub(super) probe: Option<String>,
+    #[arg(short, long, help = "Include events from all probes")]
+    pub(super) all: bool,
 }
 
 impl SubCommandParserRunner for Pcap {
@@ -233,18 +309,23 @@ impl SubCommandParserRunner for Pcap {
             probes.iter().for_each(|p| println!("{p}"));
             return Ok(());
         }
-        // The following unwrap() will never fail as Clap makes sure that either
-        // list_probes is true, or probe is Some().
-        let probe = self.cmd.probe.as_ref().unwrap();
-        let (probe_type, target) = parse_cli_probe(probe)?;
-        let symbol = Symbol::from_name_no_inspect(target);
-
-        // Filtering logic.
+        // Filtering logic. The following unwrap() will never fail as Clap
+        // makes sure that either list_probes or all is true, or probe is
+        // Some().
+        let probe = match self.cmd.all {
+            true => None,
+            false => {
+                let (probe_type, target) = parse_cli_probe(self.cmd.probe.as_ref().unwrap())?;
+                Some((probe_type, Symbol::from_name_no_inspect(target)))
+            }
+        };
         let filter = |r#type: &str, name: &str| -> bool {
-            if name == symbol.name() && r#type == probe_type.to_str() {
-                return true;
+            match &probe {
+                Some((probe_type, symbol)) => {
+                    name == symbol.name() && r#type == probe_type.to_str()
+                }
+                None => true,
             }
-            false
         };
 
         let mut writer: Option<PcapNgWriter<File>> = None;
@@ -271,6 +352,7 @@ impl SubCommandParserRunner for Pcap {
         handle_events(
             self.input.as_path(),
             &filter,
+            &self.filter.matcher()?,
             &mut EventParser::new(),
             write_block,
         )?;
@@ -282,6 +364,7 @@ impl SubCommandParserRunner for Pcap {
 fn handle_events<F>(
     input: &Path,
     filter: &dyn Fn(&str, &str) -> bool,
+    matcher: &EventMatcher,
     parser: &mut EventParser,
     mut writer_callback: F,
 ) -> Result<()>
@@ -307,6 +390,11 @@ where
                     }
                     matched = true;
 
+                    // Check the event is matching the user filters, if any.
+                    if !matcher.matches(&event) {
+                        continue;
+                    }
+
                     // Parse the event and then write the pcap blocks to the file.
                     let parsed_blocks = parser.parse(&event)?;
                     for b in parsed_blocks {
@@ -416,9 +504,17 @@ mod tests {
                         interface_id: 0,
                         timestamp: Duration::from_nanos(30419169125909),
                         original_len: 98,
-                        options: vec![EnhancedPacketOption::Comment(Cow::Owned(
-                            "probe=kretprobe:ovs_dp_upcall".to_string(),
-                        ))],
+                        options: vec![
+                            EnhancedPacketOption::Comment(Cow::Owned(
+                                "probe=kretprobe:ovs_dp_upcall".to_string(),
+                            )),
+                            EnhancedPacketOption::Comment(Cow::Owned(
+                                "tracking_id=0x1baa83c42ba1ffff8e95c3b67c00".to_string(),
+                            )),
+                            EnhancedPacketOption::Comment(Cow::Owned(
+                                "ovs=upcall_ret (6/30419169098548) ret 0".to_string(),
+                            )),
+                        ],
                     }),
                     Block::InterfaceDescription(InterfaceDescriptionBlock {
                         linktype: DataLink::ETHERNET,
@@ -444,9 +540,17 @@ mod tests {
                         interface_id: 1,
                         timestamp: Duration::from_nanos(30419169372774),
                         original_len: 98,
-                        options: vec![EnhancedPacketOption::Comment(Cow::Owned(
-                            "probe=kretprobe:ovs_dp_upcall".to_string(),
-                        ))],
+                        options: vec![
+                            EnhancedPacketOption::Comment(Cow::Owned(
+                                "probe=kretprobe:ovs_dp_upcall".to_string(),
+                            )),
+                            EnhancedPacketOption::Comment(Cow::Owned(
+                                "tracking_id=0x1baa83c8a025ffff8e95c3b67c00".to_string(),
+                            )),
+                            EnhancedPacketOption::Comment(Cow::Owned(
+                                "ovs=upcall_ret (6/30419169364667) ret 0".to_string(),
+                            )),
+                        ],
                     }),
                 ],
             ),
@@ -510,6 +614,7 @@ mod tests {
             match handle_events(
                 Path::new(file_path),
                 &filter,
+                &FilterArgs::default().matcher().unwrap(),
                 &mut EventParser::new(),
                 write_blocks,
             ) {
@@ -542,6 +647,60 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_handle_all_events() {
+        let mut blocks = Vec::<Block>::new();
+        handle_events(
+            Path::new("test_data/test_events_packets.json"),
+            &|_, _| true,
+            &FilterArgs::default().matcher().unwrap(),
+            &mut EventParser::new(),
+            |b: &Block| -> Result<()> {
+                blocks.push(b.clone().into_owned());
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        let packets = blocks
+            .iter()
+            .filter_map(|b| match b {
+                Block::EnhancedPacket(epb) => Some(epb),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let has_option =
+            |epb: &EnhancedPacketBlock, option: EnhancedPacketOption| epb.options.contains(&option);
+        let comment = |c: &str| EnhancedPacketOption::Comment(Cow::Owned(c.to_string()));
+
+        // Packets from all probes are included.
+        assert!(packets.iter().any(|p| has_option(
+            p,
+            comment("probe=raw_tracepoint:net:netif_receive_skb")
+        ) && has_option(
+            p,
+            EnhancedPacketOption::Flags(EPB_FLAGS_INBOUND)
+        )));
+        assert!(packets.iter().any(|p| has_option(
+            p,
+            comment("probe=raw_tracepoint:net:net_dev_start_xmit")
+        ) && has_option(
+            p,
+            EnhancedPacketOption::Flags(EPB_FLAGS_OUTBOUND)
+        )));
+
+        // Drop reasons and conntrack information are reported.
+        assert!(packets.iter().any(|p| {
+            has_option(p, comment("probe=raw_tracepoint:skb:kfree_skb"))
+                && has_option(p, comment("drop_reason=NO_SOCKET"))
+                && has_option(p, comment("ct_state=reply"))
+                && !p
+                    .options
+                    .iter()
+                    .any(|o| matches!(o, EnhancedPacketOption::Flags(_)))
+        }));
+    }
+
     #[test]
     fn test_list_probes() {
         let test_cases = [
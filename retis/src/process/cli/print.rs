@@ -3,7 +3,7 @@
 //! Print is a simple post-processing command that just parses events and prints them back to
 //! stdout
 
-use std::{io::stdout, path::PathBuf};
+use std::{io::stdout, path::PathBuf, thread};
 
 use anyhow::Result;
 use clap::Parser;
@@ -11,11 +11,11 @@ use clap::Parser;
 use crate::{
     cli::*,
     events::{
-        file::{FileEventsFactory, FileType},
+        file::{FileEventsFactory, FileType, FOLLOW_POLL_INTERVAL},
         *,
     },
     helpers::signals::Running,
-    process::display::*,
+    process::{display::*, filter::FilterArgs, template::EventTemplate},
 };
 
 /// Print stored events to stdout
@@ -28,8 +28,33 @@ pub(crate) struct Print {
     #[arg(long, help = "Format used when printing an event.")]
     #[clap(value_enum, default_value_t=CliDisplayFormat::MultiLine)]
     pub(super) format: CliDisplayFormat,
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = TableColumn::DEFAULT,
+        help = "Comma-separated list of columns printed when using the table format."
+    )]
+    pub(super) columns: Vec<TableColumn>,
+    #[arg(
+        long,
+        help = "Print events on a single line following a template referencing event fields, e.g.
+'{common.timestamp} {kernel.symbol} {skb.ip.saddr} {skb-drop.drop_reason?}'. Fields are
+referenced using their path in the JSON representation of events; missing fields are printed
+as '-' unless followed by '?'. Values can be formatted using ':hex', ':ifname' or ':delta'
+(e.g. '{common.timestamp:delta}'). Takes precedence over --format."
+    )]
+    pub(super) template: Option<EventTemplate>,
     #[arg(long, help = "Print the time as UTC")]
     pub(super) utc: bool,
+    #[arg(
+        short,
+        long,
+        help = "Keep waiting for new events once the end of the file is reached, for files
+still being written to (e.g. by an ongoing collection). The file is re-opened if rotated."
+    )]
+    pub(super) follow: bool,
+    #[command(flatten)]
+    pub(super) filter: FilterArgs,
 }
 
 impl SubCommandParserRunner for Print {
@@ -38,8 +63,21 @@ impl SubCommandParserRunner for Print {
         let run = Running::new();
         run.register_term_signals()?;
 
+        // In follow mode, wait for the file to be created and populated.
+        if self.follow && !FileEventsFactory::ready(&self.input) {
+            log::info!("Waiting for events in {}", self.input.display());
+            while run.running() && !FileEventsFactory::ready(&self.input) {
+                thread::sleep(FOLLOW_POLL_INTERVAL);
+            }
+            if !run.running() {
+                return Ok(());
+            }
+        }
+
+        let matcher = self.filter.matcher()?;
+
         // Create event factory.
-        let mut factory = FileEventsFactory::new(self.input.as_path())?;
+        let mut factory = FileEventsFactory::new(self.input.as_path())?.follow(self.follow);
 
         // Format.
         let format = DisplayFormat::new()
@@ -48,29 +86,53 @@ impl SubCommandParserRunner for Print {
                 TimeFormat::UtcDate
             } else {
                 TimeFormat::MonotonicTimestamp
+            })
+            .table(if self.format == CliDisplayFormat::Table {
+                self.columns.clone()
+            } else {
+                Vec::new()
             });
 
+        let format = match &self.template {
+            Some(template) => PrintEventFormat::Template(template.clone()),
+            None => PrintEventFormat::Text(format),
+        };
+
         match factory.file_type() {
             FileType::Event => {
                 // Formatter & printer for events.
-                let mut event_output =
-                    PrintEvent::new(Box::new(stdout()), PrintEventFormat::Text(format));
+                let mut event_output = PrintEvent::new(Box::new(stdout()), format);
 
                 while run.running() {
                     match factory.next_event()? {
-                        Some(event) => event_output.process_one(&event)?,
+                        Some(event) => {
+                            if matcher.matches(&event) {
+                                event_output.process_one(&event)?;
+                            }
+                        }
+                        None if self.follow => {
+                            event_output.flush()?;
+                            thread::sleep(FOLLOW_POLL_INTERVAL);
+                        }
                         None => break,
                     }
                 }
             }
             FileType::Series => {
                 // Formatter & printer for series.
-                let mut series_output =
-                    PrintSeries::new(Box::new(stdout()), PrintEventFormat::Text(format));
+                let mut series_output = PrintSeries::new(Box::new(stdout()), format);
 
                 while run.running() {
                     match factory.next_series()? {
-                        Some(series) => series_output.process_one(&series)?,
+                        Some(series) => {
+                            if matcher.matches_series(&series) {
+                                series_output.process_one(&series)?;
+                            }
+                        }
+                        None if self.follow => {
+                            series_output.flush()?;
+                            thread::sleep(FOLLOW_POLL_INTERVAL);
+                        }
                         None => break,
                     }
                 }
@@ -15,11 +15,14 @@ use crate::{
     cli::*,
     events::{file::FileEventsFactory, *},
     helpers::signals::Running,
-    process::{display::*, series::EventSorter, tracking::AddTracking},
+    process::{
+        display::*, filter::FilterArgs, series::EventSorter, template::EventTemplate,
+        tracking::AddTracking,
+    },
 };
 
 /// The default size of the sorting buffer
-const DEFAULT_BUFFER: usize = 1000;
+pub(crate) const DEFAULT_BUFFER: usize = 1000;
 
 /// Sort stored events in series based on tracking id.
 ///
@@ -56,9 +59,26 @@ pub(crate) struct Sort {
     #[clap(value_enum, default_value_t=CliDisplayFormat::MultiLine)]
     pub(super) format: CliDisplayFormat,
 
+    /// Comma-separated list of columns printed when using the table format.
+    #[arg(long, value_delimiter = ',', default_value = TableColumn::DEFAULT)]
+    pub(super) columns: Vec<TableColumn>,
+
+    /// Print events on a single line following a template referencing event
+    /// fields, e.g. '{common.timestamp} {kernel.symbol} {skb-drop.drop_reason?}'.
+    /// Fields are referenced using their path in the JSON representation of
+    /// events; missing fields are printed as '-' unless followed by '?'. Values
+    /// can be formatted using ':hex', ':ifname' or ':delta' (e.g.
+    /// '{common.timestamp:delta}'). Takes precedence over --format.
+    #[arg(long)]
+    pub(super) template: Option<EventTemplate>,
+
     /// Print the time as UTC.
     #[arg(long)]
     pub(super) utc: bool,
+
+    // Series are output as a whole if at least one of their events matches.
+    #[command(flatten)]
+    pub(super) filter: FilterArgs,
 }
 
 impl SubCommandParserRunner for Sort {
@@ -75,6 +95,7 @@ impl SubCommandParserRunner for Sort {
             return Ok(());
         }
 
+        let matcher = self.filter.matcher()?;
         let mut series = EventSorter::new();
         let mut tracker = AddTracking::new();
         let mut printers = Vec::new();
@@ -113,11 +134,19 @@ impl SubCommandParserRunner for Sort {
                     TimeFormat::UtcDate
                 } else {
                     TimeFormat::MonotonicTimestamp
+                })
+                .table(if self.format == CliDisplayFormat::Table {
+                    self.columns.clone()
+                } else {
+                    Vec::new()
                 });
 
             printers.push(PrintSeries::new(
                 Box::new(stdout()),
-                PrintEventFormat::Text(format),
+                match &self.template {
+                    Some(template) => PrintEventFormat::Template(template.clone()),
+                    None => PrintEventFormat::Text(format),
+                },
             ));
         }
 
@@ -135,6 +164,7 @@ impl SubCommandParserRunner for Sort {
                         while series.len() >= self.max_buffer {
                             // Flush the oldest series
                             match series.pop_oldest()? {
+                                Some(series) if !matcher.matches_series(&series) => (),
                                 Some(series) => printers
                                     .iter_mut()
                                     .try_for_each(|p| p.process_one(&series))?,
@@ -149,6 +179,7 @@ impl SubCommandParserRunner for Sort {
         // Flush remaining events
         while series.len() > 0 {
             match series.pop_oldest()? {
+                Some(series) if !matcher.matches_series(&series) => (),
                 Some(series) => printers
                     .iter_mut()
                     .try_for_each(|p| p.process_one(&series))?,
@@ -0,0 +1,317 @@
+//! # Stats
+//!
+//! Stats is a post-processing command summarizing the content of an event
+//! file: number of events per probe, distinct packets, drop reasons, etc.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use clap::Parser;
+use serde_json::json;
+
+use crate::{
+    cli::*,
+    events::{
+        file::{FileEventsFactory, FileType},
+        *,
+    },
+    helpers::signals::Running,
+    process::{flow::FlowKey, series::interface},
+};
+
+/// Counts occurrences of keys.
+#[derive(Default)]
+struct Counter(HashMap<String, u64>);
+
+impl Counter {
+    fn add<S: Into<String>>(&mut self, key: S) {
+        *self.0.entry(key.into()).or_default() += 1;
+    }
+
+    /// Returns the `n` most frequent keys (all of them if `n` is 0), sorted by
+    /// number of occurrences.
+    fn top(&self, n: usize) -> Vec<(&str, u64)> {
+        let mut entries = self
+            .0
+            .iter()
+            .map(|(k, v)| (k.as_str(), *v))
+            .collect::<Vec<_>>();
+        entries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+
+        if n != 0 {
+            entries.truncate(n);
+        }
+        entries
+    }
+}
+
+/// Statistics gathered from events.
+#[derive(Default)]
+pub(crate) struct EventStats {
+    /// Number of events processed.
+    events: u64,
+    /// Timestamp of the first and last events.
+    first: Option<u64>,
+    last: Option<u64>,
+    /// Distinct packets, identified by their tracking id.
+    packets: HashSet<u128>,
+    probes: Counter,
+    drop_reasons: Counter,
+    interfaces: Counter,
+    flows: Counter,
+    nft_verdicts: Counter,
+    ovs_events: Counter,
+}
+
+impl EventStats {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Account for a single event.
+    pub(crate) fn process_one(&mut self, event: &Event) {
+        // Startup events are not related to the collection itself.
+        if event
+            .get_section::<StartupEvent>(SectionId::Startup)
+            .is_some()
+        {
+            return;
+        }
+
+        self.events += 1;
+
+        if let Some(common) = event.get_section::<CommonEvent>(SectionId::Common) {
+            self.first = Some(
+                self.first
+                    .map_or(common.timestamp, |t| t.min(common.timestamp)),
+            );
+            self.last = Some(
+                self.last
+                    .map_or(common.timestamp, |t| t.max(common.timestamp)),
+            );
+        }
+
+        if let Some(probe) = event.probe_name() {
+            self.probes.add(probe);
+        }
+
+        if let Some(tracking_id) = event.tracking_id() {
+            self.packets.insert(tracking_id);
+        }
+
+        if let Some(reason) = event.drop_reason() {
+            self.drop_reasons.add(reason);
+        }
+
+        if let Some(iface) = interface(event) {
+            self.interfaces.add(iface);
+        }
+
+        if let Some(skb) = event.get_section::<SkbEvent>(SectionId::Skb) {
+            if let Some(flow) = FlowKey::from_skb(skb) {
+                self.flows.add(flow.to_string());
+            }
+        }
+
+        if let Some(nft) = event.get_section::<NftEvent>(SectionId::Nft) {
+            self.nft_verdicts.add(nft.verdict.clone());
+        }
+
+        if let Some(ovs) = event.get_section::<OvsEvent>(SectionId::Ovs) {
+            self.ovs_events.add(ovs.event_type());
+        }
+    }
+
+    /// Duration between the first and the last events, in nanoseconds.
+    fn duration(&self) -> u64 {
+        match (self.first, self.last) {
+            (Some(first), Some(last)) => last - first,
+            _ => 0,
+        }
+    }
+
+    /// Named counters, in display order.
+    fn counters(&self) -> [(&'static str, &'static str, &Counter); 6] {
+        [
+            ("probes", "Events per probe", &self.probes),
+            ("drop_reasons", "Drop reasons", &self.drop_reasons),
+            ("interfaces", "Interfaces", &self.interfaces),
+            ("flows", "Flows", &self.flows),
+            ("nft_verdicts", "Nft verdicts", &self.nft_verdicts),
+            ("ovs_events", "OvS events", &self.ovs_events),
+        ]
+    }
+
+    /// Returns the statistics in a JSON representation, with up to `top`
+    /// entries per counter (all of them if 0).
+    pub(crate) fn to_json(&self, top: usize) -> serde_json::Value {
+        let mut stats = json!({
+            "events": self.events,
+            "packets": self.packets.len(),
+            "duration_ns": self.duration(),
+        });
+
+        for (name, _, counter) in self.counters() {
+            stats[name] = counter
+                .top(top)
+                .iter()
+                .map(|(key, count)| json!({"key": key, "count": count}))
+                .collect();
+        }
+
+        stats
+    }
+
+    /// Returns an object implementing `Display` for the statistics, with up
+    /// to `top` entries per counter (all of them if 0).
+    pub(crate) fn display(&self, top: usize) -> impl fmt::Display + '_ {
+        StatsDisplay { stats: self, top }
+    }
+}
+
+struct StatsDisplay<'a> {
+    stats: &'a EventStats,
+    top: usize,
+}
+
+impl fmt::Display for StatsDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stats = self.stats;
+
+        write!(
+            f,
+            "{} event(s), {} distinct packet(s)",
+            stats.events,
+            stats.packets.len()
+        )?;
+        if stats.events > 1 {
+            write!(f, " over {:.6}s", stats.duration() as f64 / 1e9)?;
+        }
+        writeln!(f)?;
+
+        for (_, title, counter) in stats.counters() {
+            let entries = counter.top(self.top);
+            if entries.is_empty() {
+                continue;
+            }
+
+            write!(f, "\n{title}:")?;
+            if counter.0.len() > entries.len() {
+                write!(f, " (top {} of {})", entries.len(), counter.0.len())?;
+            }
+            writeln!(f)?;
+
+            let width = entries
+                .iter()
+                .map(|(_, count)| count.to_string().len())
+                .max()
+                .unwrap_or(0);
+            for (key, count) in entries {
+                writeln!(f, "  {count:>width$}  {key}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Summarize the content of an event file
+///
+/// Reads events from the INPUT file (sorted or not) and reports the number of
+/// events per probe, the number of distinct packets and the most frequent drop
+/// reasons, interfaces, flows, nft verdicts and OvS event types.
+#[derive(Parser, Debug, Default)]
+#[command(name = "stats")]
+pub(crate) struct Stats {
+    /// File from which to read events.
+    #[arg(default_value = "retis.data")]
+    pub(super) input: PathBuf,
+
+    /// Output format.
+    #[arg(long)]
+    #[clap(value_enum, default_value_t = ReportFormat::Text)]
+    pub(super) format: ReportFormat,
+
+    /// Maximum number of entries to report per category. A value of zero means
+    /// all entries are reported.
+    #[arg(long, default_value_t = 10)]
+    pub(super) top: usize,
+}
+
+impl SubCommandParserRunner for Stats {
+    fn run(&mut self, _: &MainConfig) -> Result<()> {
+        let stats = compute_stats(self.input.as_path())?;
+
+        match self.format {
+            ReportFormat::Text => print!("{}", stats.display(self.top)),
+            ReportFormat::Json => println!("{}", stats.to_json(self.top)),
+        }
+
+        Ok(())
+    }
+}
+
+/// Compute the statistics of an event or series file.
+fn compute_stats(input: &Path) -> Result<EventStats> {
+    // Create running instance that will handle signal termination.
+    let run = Running::new();
+    run.register_term_signals()?;
+
+    let mut factory = FileEventsFactory::new(input)?;
+    let mut stats = EventStats::new();
+
+    match factory.file_type() {
+        FileType::Event => {
+            while run.running() {
+                match factory.next_event()? {
+                    Some(event) => stats.process_one(&event),
+                    None => break,
+                }
+            }
+        }
+        FileType::Series => {
+            while run.running() {
+                match factory.next_series()? {
+                    Some(series) => series.events.iter().for_each(|e| stats.process_one(e)),
+                    None => break,
+                }
+            }
+        }
+    }
+
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_from_file() {
+        let stats = compute_stats(Path::new("test_data/test_events_packets.json")).unwrap();
+        let json = stats.to_json(0);
+
+        assert_eq!(json["events"], stats.events);
+        assert!(stats.events > 0);
+        assert!(!stats.packets.is_empty());
+        assert!(json["probes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|p| p["key"] == "raw_tracepoint:skb:kfree_skb"));
+        assert!(json["ovs_events"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|p| p["key"] == "upcall" && p["count"] == 2));
+
+        // Counters are sorted and can be limited.
+        let probes = stats.probes.top(2);
+        assert_eq!(probes.len(), 2);
+        assert!(probes[0].1 >= probes[1].1);
+    }
+}
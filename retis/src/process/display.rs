@@ -2,6 +2,7 @@ use std::io::{ErrorKind, Write};
 
 use anyhow::Result;
 
+use super::template::EventTemplate;
 use crate::events::*;
 
 /// Select the format to follow when printing events with `PrintEvent`.
@@ -11,17 +12,36 @@ pub(crate) enum PrintEventFormat {
     Text(DisplayFormat),
     /// Json: display the event as JSON.
     Json,
+    /// Template(template): display the events on a single line following a
+    /// user-defined template (see `EventTemplate`).
+    Template(EventTemplate),
+}
+
+/// Returns the table header to print before the first event, if the table
+/// format is used.
+fn table_header(format: &PrintEventFormat) -> Option<String> {
+    match format {
+        PrintEventFormat::Text(format) => format.table_header().map(|h| h + "\n"),
+        _ => None,
+    }
 }
 
 /// Handles event individually and write to a `Write`.
 pub(crate) struct PrintEvent {
     writer: Box<dyn Write>,
     format: PrintEventFormat,
+    /// Table header, if any, until printed.
+    header: Option<String>,
 }
 
 impl PrintEvent {
     pub(crate) fn new(writer: Box<dyn Write>, format: PrintEventFormat) -> Self {
-        Self { writer, format }
+        let header = table_header(&format);
+        Self {
+            writer,
+            format,
+            header,
+        }
     }
 
     /// Process events one by one (format & print).
@@ -38,6 +58,9 @@ impl PrintEvent {
                     if format.multiline {
                         event.push('\n');
                     }
+                    if let Some(header) = self.header.take() {
+                        event.insert_str(0, &header);
+                    }
                     if let Err(e) = self.writer.write_all(event.as_bytes()) {
                         if e.kind() != ErrorKind::BrokenPipe {
                             return Err(e.into());
@@ -50,6 +73,16 @@ impl PrintEvent {
                 event.push(b'\n');
                 self.writer.write_all(&event)?;
             }
+            PrintEventFormat::Template(ref mut template) => {
+                if let Some(mut event) = template.render(e) {
+                    event.push('\n');
+                    if let Err(e) = self.writer.write_all(event.as_bytes()) {
+                        if e.kind() != ErrorKind::BrokenPipe {
+                            return Err(e.into());
+                        }
+                    }
+                }
+            }
         }
 
         Ok(())
@@ -65,11 +98,18 @@ impl PrintEvent {
 pub(crate) struct PrintSeries {
     writer: Box<dyn Write>,
     format: PrintEventFormat,
+    /// Table header, if any, until printed.
+    header: Option<String>,
 }
 
 impl PrintSeries {
     pub(crate) fn new(writer: Box<dyn Write>, format: PrintEventFormat) -> Self {
-        Self { writer, format }
+        let header = table_header(&format);
+        Self {
+            writer,
+            format,
+            header,
+        }
     }
 
     /// Process events one by one (format & print).
@@ -88,7 +128,8 @@ impl PrintSeries {
                     content.push_str(&format!("{}", event.display(format, &fconf)));
                     if !content.is_empty() {
                         content.push('\n');
-                        if first {
+                        // Table rows are not indented to keep columns aligned.
+                        if first && !format.is_table() {
                             first = false;
                             fconf.inc_level(4);
                             fconf.set_item(Some('↳'));
@@ -98,6 +139,9 @@ impl PrintSeries {
 
                 if !content.is_empty() {
                     content.push('\n');
+                    if let Some(header) = self.header.take() {
+                        content.insert_str(0, &header);
+                    }
 
                     if let Err(e) = self.writer.write_all(content.as_bytes()) {
                         if e.kind() != ErrorKind::BrokenPipe {
@@ -111,6 +155,23 @@ impl PrintSeries {
                 event.push(b'\n');
                 self.writer.write_all(&event)?;
             }
+            PrintEventFormat::Template(ref mut template) => {
+                for event in series.events.iter() {
+                    if let Some(event) = template.render(event) {
+                        content.push_str(&event);
+                        content.push('\n');
+                    }
+                }
+
+                if !content.is_empty() {
+                    content.push('\n');
+                    if let Err(e) = self.writer.write_all(content.as_bytes()) {
+                        if e.kind() != ErrorKind::BrokenPipe {
+                            return Err(e.into());
+                        }
+                    }
+                }
+            }
         }
 
         Ok(())
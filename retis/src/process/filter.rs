@@ -0,0 +1,121 @@
+//! # Filter
+//!
+//! Selection of stored events at post-processing time, based on the content of
+//! their sections and/or on their raw packet data.
+
+use std::str::FromStr;
+
+use anyhow::Result;
+use clap::Args;
+
+use crate::{
+    core::filters::packets::filter::PacketMatcher,
+    events::{filter::EventFilter, *},
+};
+
+/// Command line arguments to select events at post-processing time.
+#[derive(Args, Debug, Default)]
+pub(crate) struct FilterArgs {
+    /// Only process events matching the given expression. Fields are referenced
+    /// using their path in the JSON representation of events and can be compared
+    /// using ==, !=, <, <=, >, >= and ~ (glob matching on strings). Expressions can
+    /// be combined using &&, || and !.
+    ///
+    /// E.g. 'skb.tcp.dport == 443 && kernel.symbol ~ "ip_*"'.
+    #[arg(long, value_name = "EXPRESSION")]
+    pub(crate) filter: Option<String>,
+
+    /// Only process events whose raw packet data matches the given pcap-filter
+    /// expression. Requires events to have been collected with raw packets (e.g.
+    /// using the `pcap` profile); events without raw packet data never match.
+    ///
+    /// E.g. 'tcp port 443'.
+    #[arg(long, value_name = "PCAP_FILTER")]
+    pub(crate) match_packet: Option<String>,
+}
+
+impl FilterArgs {
+    /// Builds an EventMatcher from the arguments.
+    pub(crate) fn matcher(&self) -> Result<EventMatcher> {
+        Ok(EventMatcher {
+            filter: self
+                .filter
+                .as_deref()
+                .map(EventFilter::from_str)
+                .transpose()?,
+            packet: self
+                .match_packet
+                .as_deref()
+                .map(PacketMatcher::from_string)
+                .transpose()?,
+        })
+    }
+}
+
+/// Matches events against an optional filter expression and an optional
+/// packet filter. Events have to match both to be selected.
+pub(crate) struct EventMatcher {
+    filter: Option<EventFilter>,
+    packet: Option<PacketMatcher>,
+}
+
+impl EventMatcher {
+    /// Returns true if the event is selected.
+    pub(crate) fn matches(&self, event: &Event) -> bool {
+        if let Some(filter) = &self.filter {
+            if !filter.matches(event) {
+                return false;
+            }
+        }
+
+        if let Some(packet) = &self.packet {
+            return event
+                .get_section::<SkbEvent>(SectionId::Skb)
+                .and_then(|skb| skb.packet.as_ref())
+                .is_some_and(|p| packet.matches(&p.packet.0));
+        }
+
+        true
+    }
+
+    /// Returns true if at least one event of the series is selected. Series are
+    /// selected as a whole so the full path of matching packets is kept.
+    pub(crate) fn matches_series(&self, series: &EventSeries) -> bool {
+        series.events.iter().any(|e| self.matches(e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::events::file::FileEventsFactory;
+
+    #[test]
+    fn filter_events() {
+        let matcher = FilterArgs {
+            filter: Some("kernel.symbol == 'skb:kfree_skb' && skb.ip.protocol == 1".to_string()),
+            ..Default::default()
+        }
+        .matcher()
+        .unwrap();
+
+        let mut factory =
+            FileEventsFactory::new(Path::new("test_data/test_events_packets.json")).unwrap();
+        let mut matched = 0;
+        while let Some(event) = factory.next_event().unwrap() {
+            if matcher.matches(&event) {
+                matched += 1;
+            }
+        }
+        assert_eq!(matched, 2);
+
+        assert!(FilterArgs {
+            filter: Some("skb.tcp.dport ==".to_string()),
+            ..Default::default()
+        }
+        .matcher()
+        .is_err());
+    }
+}
@@ -0,0 +1,98 @@
+//! # Flow
+//!
+//! Helpers identifying the flow (L4 protocol, addresses and ports) events
+//! belong to.
+
+use std::fmt;
+
+use crate::events::{helpers::protocol_str, *};
+
+/// Flow identifier, built from the packet information found in events.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub(crate) struct FlowKey {
+    /// L4 protocol.
+    pub(crate) protocol: u8,
+    /// Source IP address.
+    pub(crate) saddr: String,
+    /// Destination IP address.
+    pub(crate) daddr: String,
+    /// Source port, for protocols having one.
+    pub(crate) sport: Option<u16>,
+    /// Destination port, for protocols having one.
+    pub(crate) dport: Option<u16>,
+}
+
+impl FlowKey {
+    /// Builds a flow key from an skb section. Returns None if the packet has
+    /// no IP information.
+    pub(crate) fn from_skb(skb: &SkbEvent) -> Option<Self> {
+        Some(Self::from_ip(skb.ip.as_ref()?, skb.ports()))
+    }
+
+    /// Builds a flow key from the inner packet of an encapsulated skb. Returns
+    /// None if the inner packet has no IP information.
+    pub(crate) fn from_inner(inner: &SkbInnerEvent) -> Option<Self> {
+        Some(Self::from_ip(inner.ip.as_ref()?, inner.ports()))
+    }
+
+    fn from_ip(ip: &SkbIpEvent, ports: Option<(u16, u16)>) -> Self {
+        Self {
+            protocol: ip.protocol,
+            saddr: ip.saddr.clone(),
+            daddr: ip.daddr.clone(),
+            sport: ports.map(|p| p.0),
+            dport: ports.map(|p| p.1),
+        }
+    }
+
+    /// Builds a flow key from the original tuple of a conntrack section.
+    pub(crate) fn from_ct(ct: &CtEvent) -> Self {
+        let tuple = &ct.base.orig;
+        let (protocol, sport, dport) = match &tuple.proto {
+            CtProto::Tcp { tcp } => (6, Some(tcp.sport), Some(tcp.dport)),
+            CtProto::Udp { udp } => (17, Some(udp.sport), Some(udp.dport)),
+            CtProto::Icmp { .. } => match tuple.ip.version {
+                CtIpVersion::V4 => (1, None, None),
+                CtIpVersion::V6 => (58, None, None),
+            },
+        };
+
+        Self {
+            protocol,
+            saddr: tuple.ip.src.clone(),
+            daddr: tuple.ip.dst.clone(),
+            sport,
+            dport,
+        }
+    }
+
+    /// Builds a flow key from an event, using its skb section or, if the
+    /// packet has no IP information, its conntrack section. Returns None if
+    /// neither is available.
+    pub(crate) fn from_event(event: &Event) -> Option<Self> {
+        event
+            .get_section::<SkbEvent>(SectionId::Skb)
+            .and_then(Self::from_skb)
+            .or_else(|| {
+                event
+                    .get_section::<CtEvent>(SectionId::Ct)
+                    .map(Self::from_ct)
+            })
+    }
+}
+
+impl fmt::Display for FlowKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.sport, self.dport) {
+            (Some(sport), Some(dport)) => {
+                write!(f, "{}.{} > {}.{}", self.saddr, sport, self.daddr, dport)?
+            }
+            _ => write!(f, "{} > {}", self.saddr, self.daddr)?,
+        }
+
+        match protocol_str(self.protocol) {
+            Some(proto) => write!(f, " {proto}"),
+            None => write!(f, " proto {}", self.protocol),
+        }
+    }
+}
@@ -5,5 +5,9 @@
 pub(crate) mod cli;
 
 pub(crate) mod display;
+pub(crate) mod filter;
+pub(crate) mod flow;
 pub(crate) mod series;
+pub(crate) mod socket;
+pub(crate) mod template;
 pub(crate) mod tracking;
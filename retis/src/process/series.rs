@@ -5,11 +5,66 @@
 //! Events can be added to EventSeries in any order and it will internally arrange them based on
 //! their TrackingInfo.
 
-use std::collections::{BTreeMap, VecDeque};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    path::Path,
+};
 
 use anyhow::{anyhow, Result};
 
-use crate::events::{CommonEvent, Event, EventSeries, SectionId, TrackingInfo};
+use super::tracking::AddTracking;
+use crate::events::{
+    file::{FileEventsFactory, FileType},
+    CommonEvent, CtEvent, DisplayFormat, Event, EventDisplay, EventSeries, FormatterConf, NftEvent,
+    OvsEvent, SectionId, SkbEvent, TrackingInfo,
+};
+
+/// Returns the conntrack state of the packet an event is about (e.g.
+/// `established`), if any.
+pub(crate) fn ct_state(event: &Event) -> Option<String> {
+    let ct = event.get_section::<CtEvent>(SectionId::Ct)?;
+    match serde_json::to_value(&ct.state) {
+        Ok(serde_json::Value::String(state)) => Some(state),
+        _ => None,
+    }
+}
+
+/// Returns the interface an event was seen on, prefixed by its network
+/// namespace when known (e.g. `ns 4026531840 if 2 (eth0)`), if any.
+pub(crate) fn interface(event: &Event) -> Option<String> {
+    let skb = event.get_section::<SkbEvent>(SectionId::Skb)?;
+    let dev = skb.dev.as_ref()?;
+
+    let mut iface = match &skb.ns {
+        Some(ns) => format!("ns {} ", ns.netns),
+        None => String::new(),
+    };
+    iface.push_str(&format!("if {}", dev.ifindex));
+    if !dev.name.is_empty() {
+        iface.push_str(&format!(" ({})", dev.name));
+    }
+    Some(iface)
+}
+
+/// Returns the nft verdict of an event with its table and chain (e.g.
+/// `firewalld/filter_IN_public drop`), if any.
+pub(crate) fn nft_verdict(event: &Event) -> Option<String> {
+    event
+        .get_section::<NftEvent>(SectionId::Nft)
+        .map(|nft| format!("{}/{} {}", nft.table_name, nft.chain_name, nft.verdict))
+}
+
+/// Returns the OpenVSwitch action executed in an event, if any.
+pub(crate) fn ovs_action(event: &Event) -> Option<String> {
+    match event.get_section::<OvsEvent>(SectionId::Ovs) {
+        Some(OvsEvent::Action { action_execute }) => Some(
+            action_execute
+                .display(&DisplayFormat::new(), &FormatterConf::new())
+                .to_string(),
+        ),
+        _ => None,
+    }
+}
 
 #[derive(Default)]
 pub(crate) struct EventSorter {
@@ -107,3 +162,49 @@ impl EventSorter {
         }
     }
 }
+
+/// Reads EventSeries from a file. Files containing events that were not sorted
+/// yet are sorted on the fly, the same way the `sort` command does.
+pub(crate) struct SeriesReader {
+    factory: FileEventsFactory,
+    tracker: AddTracking,
+    sorter: EventSorter,
+    /// Maximum number of events to buffer while sorting. A value of zero means
+    /// the buffer can grow endlessly.
+    max_buffer: usize,
+    /// All events were read from the file.
+    eof: bool,
+}
+
+impl SeriesReader {
+    /// Creates a SeriesReader reading from `input`, buffering up to
+    /// `max_buffer` events if sorting is needed.
+    pub(crate) fn new(input: &Path, max_buffer: usize) -> Result<Self> {
+        Ok(SeriesReader {
+            factory: FileEventsFactory::new(input)?,
+            tracker: AddTracking::new(),
+            sorter: EventSorter::new(),
+            max_buffer,
+            eof: false,
+        })
+    }
+
+    /// Returns the next EventSeries, if any.
+    pub(crate) fn next_series(&mut self) -> Result<Option<EventSeries>> {
+        if matches!(self.factory.file_type(), FileType::Series) {
+            return self.factory.next_series();
+        }
+
+        while !self.eof && (self.max_buffer == 0 || self.sorter.len() < self.max_buffer) {
+            match self.factory.next_event()? {
+                Some(mut event) => {
+                    self.tracker.process_one(&mut event)?;
+                    self.sorter.add(event);
+                }
+                None => self.eof = true,
+            }
+        }
+
+        self.sorter.pop_oldest()
+    }
+}
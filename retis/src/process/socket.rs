@@ -0,0 +1,429 @@
+//! # Socket
+//!
+//! Provides a `Write` implementation streaming newline-delimited data (e.g.
+//! events in their JSON representation) to any number of readers connected to
+//! a Unix or TCP socket.
+//!
+//! Each reader has its own bounded buffer; when a reader can't keep up with the
+//! event rate the lines that do not fit in its buffer are dropped (and
+//! accounted for) instead of slowing the collection down.
+
+use std::{
+    fmt, fs,
+    io::{self, Write},
+    net::TcpListener,
+    os::unix::{
+        fs::FileTypeExt,
+        net::{UnixListener, UnixStream},
+    },
+    path::PathBuf,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{sync_channel, Receiver, SyncSender, TrySendError},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Result};
+use log::{debug, info, warn};
+
+use crate::helpers::signals::Running;
+
+/// Default number of lines buffered per reader before starting to drop them.
+pub(crate) const DEFAULT_SOCKET_BUFFER: usize = 1024;
+
+/// Maximum time to wait for the lines queued for the readers to be written,
+/// when flushing or closing the socket.
+const SOCKET_DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Address a `SocketWriter` listens on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum SocketAddr {
+    /// Path to a Unix socket.
+    Unix(PathBuf),
+    /// TCP address, e.g. 127.0.0.1:9000.
+    Tcp(String),
+}
+
+impl FromStr for SocketAddr {
+    type Err = anyhow::Error;
+
+    /// Parses a socket address following the TYPE:ADDRESS pattern, with TYPE
+    /// being 'unix' or 'tcp'.
+    fn from_str(s: &str) -> Result<Self> {
+        let (kind, addr) = s
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Invalid socket address '{s}' (expected TYPE:ADDRESS)"))?;
+
+        if addr.is_empty() {
+            bail!("Invalid socket address '{s}': empty address");
+        }
+
+        Ok(match kind {
+            "unix" => SocketAddr::Unix(PathBuf::from(addr)),
+            "tcp" => SocketAddr::Tcp(addr.to_string()),
+            _ => bail!("Invalid socket type '{kind}' (expected 'unix' or 'tcp')"),
+        })
+    }
+}
+
+impl fmt::Display for SocketAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SocketAddr::Unix(path) => write!(f, "unix:{}", path.display()),
+            SocketAddr::Tcp(addr) => write!(f, "tcp:{addr}"),
+        }
+    }
+}
+
+/// Listener abstraction over the supported socket types.
+enum Listener {
+    Unix(UnixListener),
+    Tcp(TcpListener),
+}
+
+impl Listener {
+    fn bind(addr: &SocketAddr) -> Result<Self> {
+        Ok(match addr {
+            SocketAddr::Unix(path) => {
+                // Remove stale sockets left behind by a previous run, i.e.
+                // sockets no one answers on. Do not touch anything else.
+                if let Ok(meta) = fs::symlink_metadata(path) {
+                    if !meta.file_type().is_socket() {
+                        bail!("'{}' exists and is not a socket", path.display());
+                    }
+                    if UnixStream::connect(path).is_ok() {
+                        bail!("'{}' is in use by another process", path.display());
+                    }
+                    fs::remove_file(path)?;
+                }
+
+                let listener = UnixListener::bind(path)
+                    .map_err(|e| anyhow!("Could not bind to {addr}: {e}"))?;
+                listener.set_nonblocking(true)?;
+                Listener::Unix(listener)
+            }
+            SocketAddr::Tcp(tcp) => {
+                let listener =
+                    TcpListener::bind(tcp).map_err(|e| anyhow!("Could not bind to {addr}: {e}"))?;
+                listener.set_nonblocking(true)?;
+                Listener::Tcp(listener)
+            }
+        })
+    }
+
+    /// Accepts a new connection, if any. Returns the stream in blocking mode
+    /// and a description of the peer.
+    fn accept(&self) -> io::Result<(Box<dyn Write + Send>, String)> {
+        Ok(match self {
+            Listener::Unix(listener) => {
+                let (stream, _) = listener.accept()?;
+                stream.set_nonblocking(false)?;
+                (Box::new(stream), "unix peer".to_string())
+            }
+            Listener::Tcp(listener) => {
+                let (stream, peer) = listener.accept()?;
+                stream.set_nonblocking(false)?;
+                stream.set_nodelay(true)?;
+                (Box::new(stream), peer.to_string())
+            }
+        })
+    }
+}
+
+/// A connected reader.
+struct Client {
+    /// Description of the reader, used for logging.
+    name: String,
+    /// Bounded channel to the thread writing to the reader.
+    tx: SyncSender<Arc<Vec<u8>>>,
+    /// Number of lines queued and not yet written to the reader.
+    pending: Arc<AtomicUsize>,
+    /// Thread writing to the reader.
+    thread: JoinHandle<()>,
+    /// Number of lines dropped for this reader.
+    dropped: u64,
+}
+
+/// `Write` implementation dispatching complete lines to all the readers
+/// connected to a socket.
+pub(crate) struct SocketWriter {
+    addr: SocketAddr,
+    clients: Arc<Mutex<Vec<Client>>>,
+    /// Total number of lines dropped, for all readers.
+    dropped: u64,
+    /// Partial line not yet dispatched.
+    line: Vec<u8>,
+    run: Running,
+    accept_thread: Option<JoinHandle<()>>,
+}
+
+impl SocketWriter {
+    /// Creates a new `SocketWriter` listening on `addr`. Up to `buffer` lines
+    /// are queued per reader before starting to drop them.
+    pub(crate) fn new(addr: SocketAddr, buffer: usize) -> Result<Self> {
+        if buffer == 0 {
+            bail!("Socket buffer size must be greater than 0");
+        }
+
+        let listener = Listener::bind(&addr)?;
+        let clients: Arc<Mutex<Vec<Client>>> = Arc::new(Mutex::new(Vec::new()));
+        let run = Running::new();
+
+        let accept_thread = {
+            let clients = Arc::clone(&clients);
+            let run = run.clone();
+
+            thread::spawn(move || {
+                while run.running() {
+                    match listener.accept() {
+                        Ok((stream, name)) => {
+                            debug!("New reader connected to the event socket ({name})");
+                            let (tx, rx) = sync_channel(buffer);
+                            let pending = Arc::new(AtomicUsize::new(0));
+                            let thread = Self::spawn_client(stream, rx, Arc::clone(&pending));
+
+                            match clients.lock() {
+                                Ok(mut clients) => clients.push(Client {
+                                    name,
+                                    tx,
+                                    pending,
+                                    thread,
+                                    dropped: 0,
+                                }),
+                                Err(_) => break,
+                            }
+                        }
+                        Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                            thread::sleep(Duration::from_millis(100))
+                        }
+                        Err(e) => warn!("Could not accept connection on the event socket: {e}"),
+                    }
+                }
+            })
+        };
+
+        info!("Streaming events to {addr}");
+
+        Ok(Self {
+            addr,
+            clients,
+            dropped: 0,
+            line: Vec::new(),
+            run,
+            accept_thread: Some(accept_thread),
+        })
+    }
+
+    /// Spawns the thread writing the queued lines to a reader. The thread
+    /// exits when the reader disconnects or when the `SocketWriter` is gone and
+    /// the queue is drained.
+    fn spawn_client(
+        mut stream: Box<dyn Write + Send>,
+        rx: Receiver<Arc<Vec<u8>>>,
+        pending: Arc<AtomicUsize>,
+    ) -> JoinHandle<()> {
+        thread::spawn(move || {
+            while let Ok(line) = rx.recv() {
+                if stream.write_all(&line).is_err() {
+                    break;
+                }
+                pending.fetch_sub(1, Ordering::Relaxed);
+            }
+        })
+    }
+
+    /// Sends a complete line to all connected readers.
+    fn dispatch(&mut self, line: Vec<u8>) -> Result<()> {
+        let line = Arc::new(line);
+        let dropped = &mut self.dropped;
+        let mut clients = self
+            .clients
+            .lock()
+            .map_err(|e| anyhow!("Could not lock socket readers: {e}"))?;
+
+        clients.retain_mut(|c| {
+            c.pending.fetch_add(1, Ordering::Relaxed);
+            match c.tx.try_send(Arc::clone(&line)) {
+                Ok(_) => true,
+                Err(TrySendError::Full(_)) => {
+                    c.pending.fetch_sub(1, Ordering::Relaxed);
+                    c.dropped += 1;
+                    *dropped += 1;
+                    true
+                }
+                Err(TrySendError::Disconnected(_)) => {
+                    // Lines still queued when the reader went away were not
+                    // written either.
+                    let lost = c.pending.load(Ordering::Relaxed) as u64 - 1;
+                    c.dropped += lost;
+                    *dropped += lost;
+                    debug!(
+                        "Reader disconnected from the event socket ({}, {} line(s) dropped)",
+                        c.name, c.dropped
+                    );
+                    false
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Waits for the lines queued for the connected readers to be written, up
+    /// to `deadline`.
+    fn drain(&self, deadline: Instant) -> Result<()> {
+        loop {
+            let pending = self
+                .clients
+                .lock()
+                .map_err(|e| anyhow!("Could not lock socket readers: {e}"))?
+                .iter()
+                .filter(|c| !c.thread.is_finished())
+                .any(|c| c.pending.load(Ordering::Relaxed) > 0);
+
+            if !pending || Instant::now() >= deadline {
+                return Ok(());
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// Returns the total number of lines dropped because readers were too slow.
+    pub(crate) fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+impl Write for SocketWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.line.extend_from_slice(buf);
+
+        while let Some(pos) = self.line.iter().position(|b| *b == b'\n') {
+            let rest = self.line.split_off(pos + 1);
+            let line = std::mem::replace(&mut self.line, rest);
+            self.dispatch(line).map_err(io::Error::other)?;
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.drain(Instant::now() + SOCKET_DRAIN_TIMEOUT)
+            .map_err(io::Error::other)
+    }
+}
+
+impl Drop for SocketWriter {
+    fn drop(&mut self) {
+        self.run.terminate();
+        if let Some(thread) = self.accept_thread.take() {
+            let _ = thread.join();
+        }
+
+        // Dropping the senders makes the per-reader threads exit once their
+        // queue is drained. Wait for them, for a bounded time as readers might
+        // be stuck; lines not written by then are accounted as dropped.
+        let clients = match self.clients.lock() {
+            Ok(mut clients) => std::mem::take(&mut *clients),
+            Err(_) => Vec::new(),
+        };
+        let threads = clients
+            .into_iter()
+            .map(|c| (c.name, c.pending, c.thread))
+            .collect::<Vec<_>>();
+
+        let deadline = Instant::now() + SOCKET_DRAIN_TIMEOUT;
+        while threads.iter().any(|(_, _, t)| !t.is_finished()) && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        for (name, pending, thread) in threads {
+            if thread.is_finished() {
+                let _ = thread.join();
+            }
+
+            let lost = pending.load(Ordering::Relaxed) as u64;
+            if lost > 0 {
+                debug!("{lost} line(s) not written to reader {name}");
+                self.dropped += lost;
+            }
+        }
+
+        if let SocketAddr::Unix(path) = &self.addr {
+            let _ = fs::remove_file(path);
+        }
+
+        let dropped = self.dropped();
+        if dropped > 0 {
+            warn!("{dropped} event(s) dropped on {} (slow readers)", self.addr);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader};
+
+    use super::*;
+
+    #[test]
+    fn parse_addr() {
+        assert_eq!(
+            SocketAddr::from_str("unix:/run/retis.sock").unwrap(),
+            SocketAddr::Unix(PathBuf::from("/run/retis.sock"))
+        );
+        assert_eq!(
+            SocketAddr::from_str("tcp:127.0.0.1:9000").unwrap(),
+            SocketAddr::Tcp("127.0.0.1:9000".to_string())
+        );
+        assert!(SocketAddr::from_str("/run/retis.sock").is_err());
+        assert!(SocketAddr::from_str("unix:").is_err());
+        assert!(SocketAddr::from_str("udp:127.0.0.1:9000").is_err());
+    }
+
+    #[test]
+    fn stream_lines() {
+        let path = std::env::temp_dir().join(format!("retis-test-{}.sock", std::process::id()));
+        let mut writer = SocketWriter::new(SocketAddr::Unix(path.clone()), 16).unwrap();
+
+        let mut readers = (0..2)
+            .map(|_| BufReader::new(UnixStream::connect(&path).unwrap()))
+            .collect::<Vec<_>>();
+
+        // Wait for the readers to be registered.
+        while writer.clients.lock().unwrap().len() != 2 {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        // The socket is in use, it can't be taken over.
+        assert!(SocketWriter::new(SocketAddr::Unix(path.clone()), 16).is_err());
+
+        // Partial lines are only sent once complete.
+        writer.write_all(b"{\"first\":").unwrap();
+        writer.write_all(b"1}\n{\"second\":2}\n").unwrap();
+        writer.flush().unwrap();
+        assert!(writer
+            .clients
+            .lock()
+            .unwrap()
+            .iter()
+            .all(|c| c.pending.load(Ordering::Relaxed) == 0));
+
+        for reader in readers.iter_mut() {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            assert_eq!(line, "{\"first\":1}\n");
+            line.clear();
+            reader.read_line(&mut line).unwrap();
+            assert_eq!(line, "{\"second\":2}\n");
+        }
+        assert_eq!(writer.dropped(), 0);
+
+        drop(writer);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,279 @@
+//! # Template
+//!
+//! User-defined templates formatting events on a single line, e.g.
+//! `{common.timestamp} {kernel.symbol} {skb.ip.saddr}->{skb.ip.daddr} {skb-drop.drop_reason?}`.
+//!
+//! Placeholders reference event fields using their path in the JSON
+//! representation of events, starting with the section name. Missing fields
+//! are rendered as `-`, unless marked as optional using a trailing `?` in
+//! which case nothing is rendered. Values can be formatted using a formatter
+//! after a colon (e.g. `{skb.dev.ifindex:ifname}`):
+//! - `hex`: integers in hexadecimal.
+//! - `ifname`: interface indexes as interface names, as seen in the `skb.dev`
+//!   section of events in the same network namespace.
+//! - `delta`: timestamps (in nanoseconds) as the time elapsed since the
+//!   previous event.
+//!
+//! Literal braces are written as `{{` and `}}`.
+
+use std::{collections::HashMap, str::FromStr};
+
+use anyhow::{bail, Result};
+
+use crate::{events::*, helpers::time::format_ns};
+
+/// Formatter applied to a field value.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ValueFormat {
+    Hex,
+    Ifname,
+    Delta,
+}
+
+impl FromStr for ValueFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "hex" => ValueFormat::Hex,
+            "ifname" => ValueFormat::Ifname,
+            "delta" => ValueFormat::Delta,
+            x => bail!("Invalid template: unknown formatter '{x}' (expected hex, ifname or delta)"),
+        })
+    }
+}
+
+/// Reference to an event field in a template.
+#[derive(Clone, Debug)]
+struct Placeholder {
+    /// Path of the field in the event JSON representation, starting with the
+    /// section name.
+    path: Vec<String>,
+    optional: bool,
+    format: Option<ValueFormat>,
+    /// Last value seen, for the delta formatter.
+    last: Option<u64>,
+}
+
+impl FromStr for Placeholder {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (field, format) = match s.split_once(':') {
+            Some((field, format)) => (field, Some(format)),
+            None => (s, None),
+        };
+
+        // The optional marker can be put after the field or the formatter.
+        let mut optional = false;
+        let field = field.strip_suffix('?').map_or(field, |f| {
+            optional = true;
+            f
+        });
+        let format = format.map(|format| {
+            format.strip_suffix('?').map_or(format, |f| {
+                optional = true;
+                f
+            })
+        });
+
+        let path = field.split('.').map(String::from).collect::<Vec<_>>();
+        if path.iter().any(|p| p.is_empty()) {
+            bail!("Invalid template: invalid field '{field}'");
+        }
+        if SectionId::from_str(&path[0]).is_err() {
+            bail!("Invalid template: unknown section '{}'", path[0]);
+        }
+
+        Ok(Self {
+            path,
+            optional,
+            format: format.map(ValueFormat::from_str).transpose()?,
+            last: None,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Part {
+    Text(String),
+    Field(Placeholder),
+}
+
+/// Template formatting events on a single line, see the module documentation
+/// for the syntax.
+#[derive(Clone, Debug)]
+pub(crate) struct EventTemplate {
+    parts: Vec<Part>,
+    /// Interface names, by network namespace and interface index.
+    ifnames: HashMap<(u32, u32), String>,
+}
+
+impl FromStr for EventTemplate {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = s.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let Some((placeholder, rest)) = chars.as_str().split_once('}') else {
+                        bail!("Invalid template: unterminated placeholder");
+                    };
+
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(Part::Field(Placeholder::from_str(placeholder)?));
+                    chars = rest.chars();
+                }
+                '}' => bail!("Invalid template: unexpected '}}' (use '}}}}' for a literal brace)"),
+                c => text.push(c),
+            }
+        }
+
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+
+        Ok(Self {
+            parts,
+            ifnames: HashMap::new(),
+        })
+    }
+}
+
+impl EventTemplate {
+    /// Formats an event following the template. Returns None for events not
+    /// related to the collection itself (e.g. startup events).
+    pub(crate) fn render(&mut self, event: &Event) -> Option<String> {
+        if event
+            .get_section::<StartupEvent>(SectionId::Startup)
+            .is_some()
+        {
+            return None;
+        }
+
+        // Learn the interface names seen so far.
+        let skb = event.get_section::<SkbEvent>(SectionId::Skb);
+        let netns = skb.and_then(|s| s.ns.as_ref()).map_or(0, |ns| ns.netns);
+        if let Some(dev) = skb.and_then(|s| s.dev.as_ref()) {
+            if !dev.name.is_empty() {
+                self.ifnames.insert((netns, dev.ifindex), dev.name.clone());
+            }
+        }
+
+        let json = event.to_json();
+        let mut line = String::new();
+
+        for part in self.parts.iter_mut() {
+            let placeholder = match part {
+                Part::Text(text) => {
+                    line.push_str(text);
+                    continue;
+                }
+                Part::Field(placeholder) => placeholder,
+            };
+
+            let value = placeholder
+                .path
+                .iter()
+                .try_fold(&json, |value, key| match value {
+                    serde_json::Value::Object(obj) => obj.get(key),
+                    serde_json::Value::Array(arr) => arr.get(key.parse::<usize>().ok()?),
+                    _ => None,
+                })
+                .filter(|v| !v.is_null());
+
+            let Some(value) = value else {
+                if !placeholder.optional {
+                    line.push('-');
+                }
+                continue;
+            };
+
+            match (placeholder.format, value.as_u64()) {
+                (Some(ValueFormat::Hex), Some(n)) => line.push_str(&format!("{n:#x}")),
+                (Some(ValueFormat::Ifname), Some(n)) => {
+                    match self.ifnames.get(&(netns, n as u32)) {
+                        Some(name) => line.push_str(name),
+                        None => line.push_str(&n.to_string()),
+                    }
+                }
+                (Some(ValueFormat::Delta), Some(n)) => {
+                    let delta = n as i128 - placeholder.last.unwrap_or(n) as i128;
+                    placeholder.last = Some(n);
+                    line.push_str(&format!(
+                        "{}{}",
+                        if delta < 0 { '-' } else { '+' },
+                        format_ns(delta.unsigned_abs() as u64)
+                    ));
+                }
+                _ => match value {
+                    serde_json::Value::String(s) => line.push_str(s),
+                    value => line.push_str(&value.to_string()),
+                },
+            }
+        }
+
+        Some(line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::events::file::FileEventsFactory;
+
+    #[test]
+    fn template_parse() {
+        assert!(EventTemplate::from_str("{common.timestamp} {{literal}}").is_ok());
+        assert!(EventTemplate::from_str("{skb-drop.drop_reason?:hex}").is_ok());
+        assert!(EventTemplate::from_str("{skb.dev.ifindex:ifname?}").is_ok());
+        assert!(EventTemplate::from_str("{common.timestamp").is_err());
+        assert!(EventTemplate::from_str("common.timestamp}").is_err());
+        assert!(EventTemplate::from_str("{foo.bar}").is_err());
+        assert!(EventTemplate::from_str("{skb..ip}").is_err());
+        assert!(EventTemplate::from_str("{common.timestamp:foo}").is_err());
+    }
+
+    #[test]
+    fn template_render() {
+        let mut factory =
+            FileEventsFactory::new(Path::new("test_data/test_events_packets.json")).unwrap();
+        let mut template = EventTemplate::from_str(
+            "{{{kernel.symbol}}} {common.timestamp:delta} {skb.ip.saddr}->{skb.ip.daddr} \
+             {skb.dev.ifindex:ifname} {skb.meta.len:hex} {skb-drop.drop_reason?}{ovs.foo}",
+        )
+        .unwrap();
+
+        let mut lines = Vec::new();
+        while let Some(event) = factory.next_event().unwrap() {
+            if let Some(line) = template.render(&event) {
+                lines.push(line);
+            }
+        }
+
+        // The startup event is not rendered.
+        assert!(!lines.is_empty());
+        assert!(lines[0].starts_with(
+            "{net:net_dev_start_xmit} +0ns 192.168.125.10->192.168.125.11 veth-ns01 0x"
+        ));
+        assert!(lines[0].ends_with(" -"));
+        assert!(lines[1].starts_with("{net:netif_receive_skb} +20.555us "));
+        assert!(lines.iter().any(|l| l.ends_with(" NO_SOCKET-")));
+    }
+}